@@ -1,8 +1,13 @@
 use crate::{
     ot::mozzarella::{
-        cache::{cacheinit::GenCache, prover::CachedProver, verifier::CachedVerifier},
-        lpn::LLCode,
-        CODE_D,
+        cache::{
+            bootstrap::{BaseVoleProver, BaseVoleVerifier},
+            cacheinit::GenCache,
+            prover::CachedProver,
+            verifier::CachedVerifier,
+        },
+        lpn::{EACode, LLCode, LpnCode},
+        lpn_estimator, CODE_D, EA_CODE_D,
     },
     Error,
 };
@@ -11,7 +16,11 @@ use rand::{
     distributions::{Distribution, Standard},
     Rng, SeedableRng,
 };
-use scuttlebutt::{ring::Ring, AesRng, Block};
+use scuttlebutt::{
+    channel::{Receivable, Sendable},
+    ring::Ring,
+    AbstractChannel, AesRng, Block,
+};
 use scuttlebutt::{SyncChannel, TrackChannel};
 use serde::Serialize;
 use std::{
@@ -95,6 +104,23 @@ pub fn setup_network(options: &NetworkOptions) -> Result<NetworkChannel, Error>
     }
 }
 
+/// Set up `n` connections on consecutive ports starting at `options.port`.
+pub fn setup_network_multi(
+    options: &NetworkOptions,
+    n: usize,
+) -> Result<Vec<NetworkChannel>, Error> {
+    (0..n)
+        .map(|i| {
+            let port = options.port + i as u16;
+            if options.listen {
+                listen(&options.host, port)
+            } else {
+                connect(&options.host, port, options.connect_timeout_seconds)
+            }
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone, clap::ArgEnum)]
 pub enum RingParameter {
     R64,
@@ -108,6 +134,7 @@ pub enum RingParameter {
     R212,
     R224,
     R244,
+    F61p,
 }
 
 impl fmt::Display for RingParameter {
@@ -124,6 +151,41 @@ impl fmt::Display for RingParameter {
             RingParameter::R212 => write!(f, "R212"),
             RingParameter::R224 => write!(f, "R224"),
             RingParameter::R244 => write!(f, "R244"),
+            RingParameter::F61p => write!(f, "F61p"),
+        }
+    }
+}
+
+impl RingParameter {
+    pub fn bit_length(&self) -> usize {
+        match self {
+            RingParameter::R64 => 64,
+            RingParameter::R72 => 72,
+            RingParameter::R104 => 104,
+            RingParameter::R112 => 112,
+            RingParameter::R130 => 130,
+            RingParameter::R144 => 144,
+            RingParameter::R162 => 162,
+            RingParameter::R192 => 192,
+            RingParameter::R212 => 212,
+            RingParameter::R224 => 224,
+            RingParameter::R244 => 244,
+            RingParameter::F61p => 61,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, clap::ArgEnum, Serialize)]
+pub enum CodeParameter {
+    LocalLinear,
+    ExpandAccumulate,
+}
+
+impl fmt::Display for CodeParameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            CodeParameter::LocalLinear => write!(f, "local-linear"),
+            CodeParameter::ExpandAccumulate => write!(f, "expand-accumulate"),
         }
     }
 }
@@ -131,17 +193,80 @@ impl fmt::Display for RingParameter {
 #[derive(Debug, Copy, Clone, clap::Parser, Serialize)]
 pub struct LpnParameters {
     /// Length of the secret vector
-    #[clap(short = 'K', long)]
+    #[clap(short = 'K', long, default_value_t = 0)]
     pub base_vole_size: usize,
     /// Length of the output vector
     #[clap(short = 'N', long)]
     pub extension_size: usize,
     /// Number of noisy coordinates
-    #[clap(short = 'T', long)]
+    #[clap(short = 'T', long, default_value_t = 0)]
     pub num_noise_coordinates: usize,
+    /// Choose K and T for the given bit security level (overrides -K and -T)
+    #[clap(long)]
+    pub security_level: Option<usize>,
+    /// Code family used for the LPN expansion
+    #[clap(long, arg_enum, default_value_t = CodeParameter::LocalLinear)]
+    pub code_family: CodeParameter,
 }
 
 impl LpnParameters {
+    /// Number of nonzero entries per column of the code.
+    pub fn code_d(&self) -> usize {
+        match self.code_family {
+            CodeParameter::LocalLinear => CODE_D,
+            CodeParameter::ExpandAccumulate => EA_CODE_D,
+        }
+    }
+
+    /// If a security level is given, select K and T accordingly.  Fails if K and T are still
+    /// unset afterwards, e.g., if no suitable parameters exist for this extension size.
+    pub fn apply_security_level(&mut self, ring_bit_length: usize) -> Result<(), Error> {
+        let security_level = match self.security_level {
+            Some(security_level) => security_level,
+            None if self.base_vole_size > 0 && self.num_noise_coordinates > 0 => return Ok(()),
+            None => {
+                return Err(Error::Other(
+                    "either -K and -T or --security-level are required".to_string(),
+                ))
+            }
+        };
+        // the estimator only models local linear codes
+        if let CodeParameter::ExpandAccumulate = self.code_family {
+            return Err(Error::Other(format!(
+                "--security-level is not supported for {} codes, use -K and -T instead",
+                self.code_family
+            )));
+        }
+        match lpn_estimator::select_parameters(
+            self.extension_size,
+            security_level,
+            self.code_d(),
+            ring_bit_length,
+        ) {
+            Some((base_vole_size, num_noise_coordinates)) => {
+                self.base_vole_size = base_vole_size;
+                self.num_noise_coordinates = num_noise_coordinates;
+                Ok(())
+            }
+            None => Err(Error::Other(format!(
+                "no parameters reach {} bits of security for N = {}",
+                security_level, self.extension_size
+            ))),
+        }
+    }
+
+    /// Estimated bit security of these parameters.
+    pub fn estimate_security(&self, ring_bit_length: usize) -> f64 {
+        lpn_estimator::estimate_security(
+            self.base_vole_size,
+            self.extension_size,
+            self.num_noise_coordinates,
+            self.code_d(),
+            ring_bit_length,
+        )
+        .bits()
+    }
+
     pub fn recompute_extension_size(&mut self) {
         assert!(self.num_noise_coordinates > 0);
         // increase extension_size s.t. it is a multiple of the number of noise coordinates
@@ -199,15 +324,137 @@ where
     (prover_cache, (verifier_cache, delta))
 }
 
-pub fn generate_code<RingT>(lpn_parameters: &LpnParameters) -> LLCode<RingT>
+pub fn bootstrap_prover_cache<RingT, C: AbstractChannel>(
+    channel: &mut C,
+    lpn_parameters: &LpnParameters,
+) -> Result<CachedProver<RingT>, Error>
+where
+    RingT: Ring + Receivable,
+    for<'b> &'b RingT: Sendable,
+    Standard: Distribution<RingT>,
+{
+    let mut base_vole_prover = BaseVoleProver::<RingT>::new();
+    base_vole_prover.init(channel)?;
+    base_vole_prover.extend(channel, lpn_parameters.get_required_cache_size())
+}
+
+pub fn bootstrap_verifier_cache<RingT, C: AbstractChannel>(
+    channel: &mut C,
+    lpn_parameters: &LpnParameters,
+) -> Result<(CachedVerifier<RingT>, RingT), Error>
+where
+    RingT: Ring + Receivable,
+    for<'b> &'b RingT: Sendable,
+    Standard: Distribution<RingT>,
+{
+    let delta = AesRng::new().gen::<RingT>();
+    let mut base_vole_verifier = BaseVoleVerifier::<RingT>::new();
+    base_vole_verifier.init(channel, delta)?;
+    let verifier_cache =
+        base_vole_verifier.extend(channel, lpn_parameters.get_required_cache_size())?;
+    Ok((verifier_cache, base_vole_verifier.get_delta()))
+}
+
+/// Get the prover's base VOLEs, either from the dealer in `setup_cache` or by running the
+/// bootstrap protocol with the verifier.
+pub fn get_prover_cache<RingT, C: AbstractChannel>(
+    channel: &mut C,
+    lpn_parameters: &LpnParameters,
+    bootstrap: bool,
+) -> Result<CachedProver<RingT>, Error>
+where
+    RingT: Ring + Receivable,
+    for<'b> &'b RingT: Sendable,
+    Standard: Distribution<RingT>,
+{
+    if bootstrap {
+        bootstrap_prover_cache::<RingT, C>(channel, lpn_parameters)
+    } else {
+        Ok(setup_cache(lpn_parameters).0)
+    }
+}
+
+/// Get the verifier's base VOLEs and `delta`, either from the dealer in `setup_cache` or by
+/// running the bootstrap protocol with the prover.
+pub fn get_verifier_cache<RingT, C: AbstractChannel>(
+    channel: &mut C,
+    lpn_parameters: &LpnParameters,
+    bootstrap: bool,
+) -> Result<(CachedVerifier<RingT>, RingT), Error>
+where
+    RingT: Ring + Receivable,
+    for<'b> &'b RingT: Sendable,
+    Standard: Distribution<RingT>,
+{
+    if bootstrap {
+        bootstrap_verifier_cache::<RingT, C>(channel, lpn_parameters)
+    } else {
+        Ok(setup_cache(lpn_parameters).1)
+    }
+}
+
+pub fn generate_code<RingT>(lpn_parameters: &LpnParameters) -> Box<dyn LpnCode<RingT>>
+where
+    RingT: Ring,
+    Standard: Distribution<RingT>,
+{
+    generate_code_from_seed(lpn_parameters, Block::default())
+}
+
+pub fn generate_code_from_seed<RingT>(
+    lpn_parameters: &LpnParameters,
+    seed: Block,
+) -> Box<dyn LpnCode<RingT>>
 where
     RingT: Ring,
     Standard: Distribution<RingT>,
 {
-    LLCode::<RingT>::from_seed(
-        lpn_parameters.base_vole_size,
-        lpn_parameters.extension_size,
-        CODE_D,
-        Block::default(),
-    )
+    match lpn_parameters.code_family {
+        CodeParameter::LocalLinear => Box::new(LLCode::<RingT>::from_seed(
+            lpn_parameters.base_vole_size,
+            lpn_parameters.extension_size,
+            lpn_parameters.code_d(),
+            seed,
+        )),
+        CodeParameter::ExpandAccumulate => Box::new(EACode::<RingT>::from_seed(
+            lpn_parameters.base_vole_size,
+            lpn_parameters.extension_size,
+            lpn_parameters.code_d(),
+            seed,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodeParameter, LpnParameters};
+
+    #[test]
+    fn test_apply_security_level() {
+        let mut lpn_parameters = LpnParameters {
+            base_vole_size: 0,
+            extension_size: 1 << 20,
+            num_noise_coordinates: 0,
+            security_level: None,
+            code_family: CodeParameter::LocalLinear,
+        };
+        assert!(lpn_parameters.apply_security_level(64).is_err());
+
+        lpn_parameters.security_level = Some(100);
+        lpn_parameters.apply_security_level(64).unwrap();
+        assert!(lpn_parameters.base_vole_size > 0);
+        assert!(lpn_parameters.num_noise_coordinates > 0);
+        assert!(lpn_parameters.estimate_security(64) >= 100.0);
+
+        // there is no estimate for expand-accumulate codes
+        let mut lpn_parameters = LpnParameters {
+            base_vole_size: 0,
+            num_noise_coordinates: 0,
+            code_family: CodeParameter::ExpandAccumulate,
+            ..lpn_parameters
+        };
+        let err = lpn_parameters.apply_security_level(64).unwrap_err();
+        assert!(err.to_string().contains("expand-accumulate"));
+        assert_eq!(lpn_parameters.base_vole_size, 0);
+    }
 }
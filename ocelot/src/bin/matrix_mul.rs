@@ -1,71 +1,97 @@
-use std::sync::Arc;
-use std::thread;
-use std::thread::JoinHandle;
-use std::time::{Duration, Instant};
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2020 Galois, Inc.
+// See LICENSE for licensing information.
 
 use clap::{ErrorKind, IntoApp, Parser};
-use rand::distributions::{Distribution, Standard};
-use rand::{CryptoRng, Rng, SeedableRng};
+use ocelot::{
+    benchmark_tools::{
+        generate_code, get_prover_cache, get_verifier_cache, setup_network, LpnParameters,
+        NetworkOptions, Party, RingParameter,
+    },
+    ot::mozzarella::{
+        cache::{prover::CachedProver, verifier::CachedVerifier},
+        lpn::LpnCode,
+    },
+    quarksilver::{
+        QuarkSilverProver, QuarkSilverProverStats, QuarkSilverVerifier, QuarkSilverVerifierStats,
+    },
+    tools::BenchmarkMetaData,
+};
+use rand::{
+    distributions::{Distribution, Standard},
+    rngs::OsRng,
+    Rng,
+};
+use scuttlebutt::{
+    channel::{track_unix_channel_pair, Receivable, Sendable, TrackChannel},
+    ring::{z2r, F61p, Ring, R64},
+    AbstractChannel,
+};
 use serde::Serialize;
 use serde_json;
-
-use ocelot::benchmark_tools::{
-    generate_code, setup_cache, setup_network, LpnParameters, NetworkOptions, Party, RingParameter,
+use std::{
+    string::ToString,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
 };
-use ocelot::ot::mozzarella::cache::prover::CachedProver;
-use ocelot::ot::mozzarella::cache::verifier::CachedVerifier;
-use ocelot::ot::mozzarella::lpn::LLCode;
-use ocelot::quarksilver::{
-    QuarkSilverProver, QuarkSilverProverStats, QuarkSilverVerifier, QuarkSilverVerifierStats,
-};
-use ocelot::tools::BenchmarkMetaData;
-
-use scuttlebutt::channel::{Receivable, Sendable};
-use scuttlebutt::ring::{Ring, R64};
-use scuttlebutt::{track_unix_channel_pair, AbstractChannel, AesRng, Block};
 
-const CHUNK_SIZE: usize = 10000;
-
-#[derive(Debug, Parser)]
+#[derive(Clone, Debug, Parser)]
 #[clap(
-    name = "Mozzarella VOLE Generation Benchmark",
+    name = "QuarkSilver Matrix Multiplication Benchmark",
     author = "Alex Hansen, Lennart Braun",
     version = "0.1"
 )]
 struct Options {
+    /// Which party should be run
     #[clap(short = 'P', long, arg_enum)]
     party: Party,
 
+    /// Which ring to use (rXXX = integers modulo 2^XXX, f61p = integers modulo 2^61 - 1)
     #[clap(short = 'R', long, arg_enum, default_value_t = RingParameter::R64)]
     ring: RingParameter,
 
-    // what the hell does "long" mean?
+    /// Size of the plaintext ring
+    #[clap(short = 'k', long)]
+    plain_size: usize,
+
+    /// Statistical security parameter
+    #[clap(short, long)]
+    statsec: usize,
+
+    /// Dimension of the square matrices
     #[clap(short = 'D', long)]
     dim: usize,
 
+    /// Parameters for the Leaky Regular LPN Problem
     #[clap(flatten, help_heading = "LPN parameters")]
     lpn_parameters: LpnParameters,
 
-    #[clap(flatten, help_heading = "network options")]
+    /// Network options
+    #[clap(flatten, help_heading = "Network options")]
     network_options: NetworkOptions,
 
+    /// How many threads to use
     #[clap(short, long, default_value_t = 0)]
     threads: usize,
 
+    /// Number of repetitions
     #[clap(short, long, default_value_t = 1)]
     repetitions: usize,
 
-    #[clap(short, long)]
-    nightly: bool,
+    /// Generate the base VOLEs together with the other party instead of using a trusted dealer
+    #[clap(long)]
+    bootstrap: bool,
 
+    /// Output recorded data in JSON
     #[clap(short, long)]
     json: bool,
 
+    /// Output additional information
     #[clap(short, long)]
     verbose: bool,
-
-    #[clap(short = 'M', long)]
-    multi_thread: bool,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -78,28 +104,39 @@ enum PartyStats {
 struct StatTuple {
     pub n: usize,
     pub ns_avg: f64,
-    pub ns_avg_per_vole: f64,
     pub ns_median: f64,
-    pub ns_median_per_vole: f64,
     pub ns_stddev: f64,
-    pub ns_stddev_per_vole: f64,
+}
+
+// run time and communication of one phase of a run
+#[derive(Copy, Clone, Debug, Default)]
+struct Phase {
+    run_time: Duration,
+    kilobytes_sent: f64,
+    kilobytes_received: f64,
 }
 
 #[derive(Clone, Debug, Default, Serialize)]
 struct RunTimeStats {
     pub init_run_times: Vec<Duration>,
-    pub extend_run_times: Vec<Duration>,
-    pub multiplication_check_time: Vec<Duration>,
+    pub input_run_times: Vec<Duration>,
+    pub multiply_run_times: Vec<Duration>,
+    pub check_run_times: Vec<Duration>,
     pub init_stats: StatTuple,
-    pub extend_stats: StatTuple,
-    pub mul_check_stats: StatTuple,
-    pub kilobytes_sent: f64,
-    pub kilobytes_received: f64,
+    pub input_stats: StatTuple,
+    pub multiply_stats: StatTuple,
+    pub check_stats: StatTuple,
+    pub kilobytes_sent_input: f64,
+    pub kilobytes_sent_multiply: f64,
+    pub kilobytes_sent_check: f64,
+    pub kilobytes_received_input: f64,
+    pub kilobytes_received_multiply: f64,
+    pub kilobytes_received_check: f64,
     pub party_stats: Vec<PartyStats>,
 }
 
 impl RunTimeStats {
-    fn analyse_times(times: &[Duration], num_voles: usize) -> StatTuple {
+    fn analyse_times(times: &[Duration]) -> StatTuple {
         let n = times.len();
         assert!(n > 0);
         let mut ns: Vec<u128> = times.iter().map(|d| d.as_nanos()).collect();
@@ -118,21 +155,35 @@ impl RunTimeStats {
         StatTuple {
             n,
             ns_avg,
-            ns_avg_per_vole: ns_avg / num_voles as f64,
             ns_median,
-            ns_median_per_vole: ns_median / num_voles as f64,
             ns_stddev,
-            ns_stddev_per_vole: ns_stddev / num_voles as f64,
         }
     }
 
-    pub fn compute_statistics(&mut self, num_voles: usize) {
-        self.init_stats = Self::analyse_times(&self.init_run_times, num_voles);
-        //self.extend_stats = Self::analyse_times(&self.extend_run_times, num_voles);
+    pub fn compute_statistics(&mut self) {
+        self.init_stats = Self::analyse_times(&self.init_run_times);
+        self.input_stats = Self::analyse_times(&self.input_run_times);
+        self.multiply_stats = Self::analyse_times(&self.multiply_run_times);
+        self.check_stats = Self::analyse_times(&self.check_run_times);
     }
 
-    pub fn compute_quarksilver_statistics(&mut self, num_muls: usize) {
-        self.mul_check_stats = Self::analyse_times(&self.multiplication_check_time, num_muls);
+    pub fn record(
+        &mut self,
+        (init, input, multiply, check): (Duration, Phase, Phase, Phase),
+        party_stats: PartyStats,
+    ) {
+        self.init_run_times.push(init);
+        self.input_run_times.push(input.run_time);
+        self.multiply_run_times.push(multiply.run_time);
+        self.check_run_times.push(check.run_time);
+        self.party_stats.push(party_stats);
+        // the communication is the same in every repetition
+        self.kilobytes_sent_input = input.kilobytes_sent;
+        self.kilobytes_sent_multiply = multiply.kilobytes_sent;
+        self.kilobytes_sent_check = check.kilobytes_sent;
+        self.kilobytes_received_input = input.kilobytes_received;
+        self.kilobytes_received_multiply = multiply.kilobytes_received;
+        self.kilobytes_received_check = check.kilobytes_received;
     }
 }
 
@@ -142,7 +193,9 @@ struct BenchmarkResult {
     pub repetitions: usize,
     pub party: String,
     pub ring: String,
+    pub dim: usize,
     pub threads: usize,
+    pub bootstrap: bool,
     pub network_options: NetworkOptions,
     pub lpn_parameters: LpnParameters,
     pub meta_data: BenchmarkMetaData,
@@ -151,21 +204,13 @@ struct BenchmarkResult {
 impl BenchmarkResult {
     pub fn new(options: &Options) -> Self {
         Self {
-            run_time_stats: RunTimeStats {
-                init_run_times: Vec::with_capacity(options.repetitions),
-                extend_run_times: Vec::with_capacity(options.repetitions),
-                multiplication_check_time: Vec::with_capacity(options.repetitions),
-                init_stats: Default::default(),
-                extend_stats: Default::default(),
-                mul_check_stats: Default::default(),
-                kilobytes_sent: 0f64,
-                kilobytes_received: 0f64,
-                party_stats: Vec::with_capacity(options.repetitions),
-            },
+            run_time_stats: Default::default(),
             repetitions: 0,
             party: options.party.to_string(),
             ring: options.ring.to_string(),
+            dim: options.dim,
             threads: options.threads,
+            bootstrap: options.bootstrap,
             network_options: options.network_options.clone(),
             lpn_parameters: options.lpn_parameters,
             meta_data: BenchmarkMetaData::collect(),
@@ -173,390 +218,249 @@ impl BenchmarkResult {
     }
 }
 
-#[allow(non_snake_case)]
-fn generate_matrices<RingT, R: CryptoRng + Rng>(
-    delta: RingT,
-    mut rng: R,
-    dim: usize,
-) -> (
-    Vec<(RingT, RingT)>,
-    Vec<(RingT, RingT)>,
-    Vec<RingT>,
-    Vec<RingT>,
-    Vec<RingT>,
-)
-where
-    RingT: Ring,
-    Standard: Distribution<RingT>,
-{
-    let mut prover_A: Vec<(RingT, RingT)> = vec![(RingT::default(), RingT::default()); dim.pow(2)];
-    let mut prover_B: Vec<(RingT, RingT)> = vec![(RingT::default(), RingT::default()); dim.pow(2)];
-    let mut C: Vec<RingT> = vec![RingT::default(); dim.pow(2)];
-
-    let mut verifier_A: Vec<RingT> = vec![RingT::default(); dim.pow(2)];
-    let mut verifier_B: Vec<RingT> = vec![RingT::default(); dim.pow(2)];
-
-    for i in 0..dim {
-        for j in 0..dim {
-            let a1: RingT = rng.gen();
-            let b1: RingT = rng.gen();
-            let a2: RingT = a1 * delta + b1;
-
-            let a3: RingT = rng.gen();
-            let b2: RingT = rng.gen();
-            let a4: RingT = a3 * delta + b2;
-
-            prover_A[i * dim + j] = (a1, a2);
-            prover_B[i * dim + j] = (a3, a4);
-
-            verifier_A[i * dim + j] = b1;
-            verifier_B[i * dim + j] = b2;
-
-            C[i * dim + j] = a1 * a3;
-        }
+// measure run time and communication of f
+fn phase<C: AbstractChannel, F: FnOnce(&mut TrackChannel<C>)>(
+    channel: &mut TrackChannel<C>,
+    f: F,
+) -> Phase {
+    channel.clear();
+    let t_start = Instant::now();
+    f(channel);
+    let run_time = t_start.elapsed();
+    Phase {
+        run_time,
+        kilobytes_sent: channel.kilobytes_written(),
+        kilobytes_received: channel.kilobytes_read(),
     }
-
-    (prover_A, prover_B, verifier_A, verifier_B, C)
 }
 
 #[allow(non_snake_case)]
-fn run_verifier<RingT, C: AbstractChannel>(
-    channel: &mut C,
-    lpn_parameters: LpnParameters,
-    code: &LLCode<RingT>,
-    cache: CachedVerifier<RingT>,
-    mut delta: RingT,
-    dim: usize,
-    verifier_A: Vec<RingT>,
-    verifier_B: Vec<RingT>,
-    _C_mat: &Vec<RingT>,
-    multi_thread: bool,
-    _nightly: bool,
-) -> (Duration, Duration, PartyStats)
+fn run_prover<RingT, C: AbstractChannel>(
+    channel: &mut TrackChannel<C>,
+    options: &Options,
+    code: &dyn LpnCode<RingT>,
+    cache: CachedProver<RingT>,
+) -> ((Duration, Phase, Phase, Phase), PartyStats)
 where
     RingT: Ring + Receivable,
     for<'b> &'b RingT: Sendable,
     Standard: Distribution<RingT>,
 {
-    let rng = AesRng::from_seed(Block::default());
-
-    let mut quarksilver_verifier = QuarkSilverVerifier::<RingT>::new(
-        64,
-        40,
+    let dim = options.dim;
+    let dims = (dim, dim, dim);
+    let mut qs_prover = QuarkSilverProver::<RingT>::new(
+        options.plain_size,
+        options.statsec,
         cache,
         code,
-        lpn_parameters.base_vole_size,
-        lpn_parameters.num_noise_coordinates,
-        lpn_parameters.get_block_size(),
+        options.lpn_parameters.base_vole_size,
+        options.lpn_parameters.num_noise_coordinates,
+        options.lpn_parameters.get_block_size(),
     );
 
-    let init_start = Instant::now();
-    quarksilver_verifier.init(channel, delta);
-    let init_time = init_start.elapsed();
-
-    let mut triples: Vec<(RingT, RingT, RingT)> = Vec::new();
-
-    for row in 0..dim {
-        for col in 0..dim {
-            for i in 0..dim {
-                let out = quarksilver_verifier
-                    .multiply(
-                        channel,
-                        (verifier_A[row * dim + i], verifier_B[i * dim + col]),
-                    )
-                    .unwrap();
-
-                //C[row][col] = out.2;
-                triples.push(out);
-            }
-        }
-    }
-
     let t_start = Instant::now();
-    quarksilver_verifier
-        .check_multiply(
-            channel,
-            rng,
-            triples.as_mut_slice(),
-            multi_thread,
-            CHUNK_SIZE,
-        )
-        .unwrap();
-    let run_time_multiply = t_start.elapsed();
-    let stats = quarksilver_verifier.get_stats();
+    qs_prover.init(channel).unwrap();
+    let run_time_init = t_start.elapsed();
+
+    let A: Vec<RingT> = (0..dim * dim).map(|_| OsRng.gen()).collect();
+    let B: Vec<RingT> = (0..dim * dim).map(|_| OsRng.gen()).collect();
+    let (mut A, mut B) = ((A, Vec::new()), (B, Vec::new()));
+    let input = phase(channel, |channel| {
+        A = qs_prover.input_batch(channel, A.0.clone()).unwrap();
+        B = qs_prover.input_batch(channel, B.0.clone()).unwrap();
+        channel.flush().unwrap();
+    });
+
+    let mut C = (Vec::new(), Vec::new());
+    let multiply = phase(channel, |channel| {
+        C = qs_prover
+            .matrix_multiply(channel, dims, (&A.0, &A.1), (&B.0, &B.1))
+            .unwrap();
+    });
+
+    let check = phase(channel, |channel| {
+        qs_prover
+            .check_matrix_multiply(channel, dims, (&A.0, &A.1), (&B.0, &B.1), (&C.0, &C.1))
+            .unwrap();
+    });
 
     (
-        init_time,
-        run_time_multiply,
-        PartyStats::VerifierStats(stats),
+        (run_time_init, input, multiply, check),
+        PartyStats::ProverStats(qs_prover.get_stats()),
     )
 }
 
 #[allow(non_snake_case)]
-fn run_prover<RingT, C: AbstractChannel>(
-    channel: &mut C,
-    lpn_parameters: LpnParameters,
-    code: &LLCode<RingT>,
-    cache: CachedProver<RingT>,
-    dim: usize,
-    prover_A: Vec<(RingT, RingT)>,
-    prover_B: Vec<(RingT, RingT)>,
-    _C_mat: &Vec<RingT>,
-    multi_thread: bool,
-    _nightly: bool,
-) -> (Duration, Duration, PartyStats)
+fn run_verifier<RingT, C: AbstractChannel>(
+    channel: &mut TrackChannel<C>,
+    options: &Options,
+    code: &dyn LpnCode<RingT>,
+    cache: CachedVerifier<RingT>,
+    delta: RingT,
+) -> ((Duration, Phase, Phase, Phase), PartyStats)
 where
     RingT: Ring + Receivable,
     for<'b> &'b RingT: Sendable,
     Standard: Distribution<RingT>,
 {
-    let mut quarksilver_prover = QuarkSilverProver::<RingT>::new(
-        64,
-        40,
+    let dim = options.dim;
+    let dims = (dim, dim, dim);
+    let mut qs_verifier = QuarkSilverVerifier::<RingT>::new(
+        options.plain_size,
+        options.statsec,
         cache,
         code,
-        lpn_parameters.base_vole_size,
-        lpn_parameters.num_noise_coordinates,
-        lpn_parameters.get_block_size(),
+        options.lpn_parameters.base_vole_size,
+        options.lpn_parameters.num_noise_coordinates,
+        options.lpn_parameters.get_block_size(),
     );
 
-    let init_start = Instant::now();
-    quarksilver_prover.init(channel);
-    let init_time = init_start.elapsed();
-
-    let mut triples: Vec<((RingT, RingT), (RingT, RingT), (RingT, RingT))> = Vec::new();
-
-    for row in 0..dim {
-        for col in 0..dim {
-            //let mut tmp: RingT = RingT::default();
-            for i in 0..dim {
-                let out = quarksilver_prover
-                    .multiply(channel, prover_A[row * dim + i], prover_B[i * dim + col])
-                    .unwrap();
-                //tmp += out.2.0;
-                triples.push(out);
-            }
-            //C[row][col] = tmp;
-        }
-    }
-
     let t_start = Instant::now();
-    quarksilver_prover
-        .check_multiply(channel, triples.as_mut_slice(), multi_thread, CHUNK_SIZE)
-        .unwrap();
-    let run_time_multiply = t_start.elapsed();
-    let stats = quarksilver_prover.get_stats();
+    qs_verifier.init(channel, delta).unwrap();
+    let run_time_init = t_start.elapsed();
+
+    let (mut A_keys, mut B_keys) = (Vec::new(), Vec::new());
+    let input = phase(channel, |channel| {
+        A_keys = qs_verifier.input_batch(channel, dim * dim).unwrap();
+        B_keys = qs_verifier.input_batch(channel, dim * dim).unwrap();
+    });
+
+    let mut C_keys = Vec::new();
+    let multiply = phase(channel, |channel| {
+        C_keys = qs_verifier.matrix_multiply(channel, dims).unwrap();
+    });
+
+    let check = phase(channel, |channel| {
+        qs_verifier
+            .check_matrix_multiply(channel, dims, &A_keys, &B_keys, &C_keys)
+            .expect("check_matrix_multiply failed");
+    });
+
+    (
+        (run_time_init, input, multiply, check),
+        PartyStats::VerifierStats(qs_verifier.get_stats()),
+    )
+}
 
-    (init_time, run_time_multiply, PartyStats::ProverStats(stats))
+fn print_results(options: &Options, results: &[BenchmarkResult]) {
+    for results in results.iter() {
+        if options.json {
+            println!("{}", serde_json::to_string_pretty(results).unwrap());
+        } else {
+            println!("results {}: {:?}", results.party, results);
+        }
+    }
 }
 
-#[allow(non_snake_case)]
-fn run_matrix_mul_benchmark<RingT>(options: &Options)
+fn run_benchmark<RingT>(options: &Options)
 where
     RingT: Ring + Receivable,
     for<'b> &'b RingT: Sendable,
     Standard: Distribution<RingT>,
 {
     rayon::ThreadPoolBuilder::new()
-        .num_threads(0)
+        .num_threads(options.threads)
         .build_global()
         .unwrap();
-
-    let mut results = BenchmarkResult::new(&options);
-    let (prover_cache, (verifier_cache, delta)) = setup_cache(&options.lpn_parameters);
-    let code = generate_code::<RingT>(&options.lpn_parameters);
-
-    // todo: should we generate these fresh each iteration to make sure the cpu won't do any tricks?
-    let (prover_A, prover_B, verifier_A, verifier_B, C) =
-        generate_matrices(delta, AesRng::from_seed(Block::default()), options.dim);
+    let code: Arc<dyn LpnCode<RingT>> = Arc::from(generate_code::<RingT>(&options.lpn_parameters));
 
     match &options.party {
         Party::Both => {
             let (mut channel_p, mut channel_v) = track_unix_channel_pair();
-            let lpn_parameters_p = options.lpn_parameters;
-            let lpn_parameters_v = options.lpn_parameters;
-
-            let repetitions = options.repetitions;
-            let dim = options.dim;
-            let code_p = Arc::new(code);
-            let code_v = code_p.clone();
-
-            let mt_p = options.multi_thread;
-            let mt_v = options.multi_thread;
-
-            let C_p = Arc::new(C);
-            let C_v = C_p.clone();
-
-            let nightly = options.nightly;
-
-            let mut results_p = BenchmarkResult::new(&options);
-            let mut results_v = results_p.clone();
-
-            let prover_thread: JoinHandle<BenchmarkResult> = thread::spawn(move || {
-                for _ in 0..repetitions {
-                    let (run_time_init, run_time_multiply, party_stats) = run_prover::<RingT, _>(
+            let options_p = options.clone();
+            let code_p = code.clone();
+            let mut results_p = BenchmarkResult::new(options);
+            results_p.party = Party::Prover.to_string();
+            let mut results_v = BenchmarkResult::new(options);
+            results_v.party = Party::Verifier.to_string();
+            let prover_thread = thread::spawn(move || {
+                let prover_cache = get_prover_cache::<RingT, _>(
+                    &mut channel_p,
+                    &options_p.lpn_parameters,
+                    options_p.bootstrap,
+                )
+                .expect("base VOLE setup failed");
+                for _ in 0..options_p.repetitions {
+                    let (phases, party_stats) = run_prover::<RingT, _>(
                         &mut channel_p,
-                        lpn_parameters_p,
-                        &code_p,
+                        &options_p,
+                        &*code_p,
                         prover_cache.clone(),
-                        dim,
-                        prover_A.clone(),
-                        prover_B.clone(),
-                        &C_p,
-                        mt_p,
-                        nightly,
                     );
-
-                    results_p.run_time_stats.init_run_times.push(run_time_init);
-                    results_p
-                        .run_time_stats
-                        .multiplication_check_time
-                        .push(run_time_multiply);
-                    results_p.run_time_stats.party_stats.push(party_stats);
+                    results_p.run_time_stats.record(phases, party_stats);
                     results_p.repetitions += 1;
-                    if results_p.repetitions == 1 {
-                        // only need this once, it's static lol
-                        results_p.run_time_stats.kilobytes_sent = channel_p.kilobytes_written();
-                        results_p.run_time_stats.kilobytes_received = channel_p.kilobytes_read();
-                    }
                 }
                 results_p
             });
-
-            let verifier_thread: JoinHandle<BenchmarkResult> = thread::spawn(move || {
-                for _ in 0..repetitions {
-                    let (run_time_init, run_time_multiply, party_stats) = run_verifier::<RingT, _>(
-                        &mut channel_v,
-                        lpn_parameters_v,
-                        &code_v,
-                        verifier_cache.clone(),
-                        delta,
-                        dim,
-                        verifier_A.clone(),
-                        verifier_B.clone(),
-                        &C_v,
-                        mt_v,
-                        nightly,
-                    );
-
-                    results_v.run_time_stats.init_run_times.push(run_time_init);
-                    results_v
-                        .run_time_stats
-                        .multiplication_check_time
-                        .push(run_time_multiply);
-                    results_v.run_time_stats.party_stats.push(party_stats);
-                    results_v.repetitions += 1;
-                    if results_v.repetitions == 1 {
-                        // only need this once, it's static lol
-                        results_v.run_time_stats.kilobytes_sent = channel_v.kilobytes_written();
-                        results_v.run_time_stats.kilobytes_received = channel_v.kilobytes_read();
-                    }
-                }
-                results_v
-            });
-
-            let mut results_p = prover_thread.join().unwrap();
-            results_p
-                .run_time_stats
-                .compute_statistics(options.lpn_parameters.get_vole_output_size());
-
-            results_p
-                .run_time_stats
-                .compute_quarksilver_statistics(dim.pow(3));
-
-            let mut results_v = verifier_thread.join().unwrap();
-            results_v
-                .run_time_stats
-                .compute_statistics(options.lpn_parameters.get_vole_output_size());
-            results_v
-                .run_time_stats
-                .compute_quarksilver_statistics(dim.pow(3));
-
-            if options.json {
-                println!("{}", serde_json::to_string_pretty(&results_p).unwrap());
-                println!("{}", serde_json::to_string_pretty(&results_v).unwrap());
-            } else {
-                println!("results prover: {:?}", results_p);
-                println!("results verifier: {:?}", results_v);
+            let (verifier_cache, delta) = get_verifier_cache::<RingT, _>(
+                &mut channel_v,
+                &options.lpn_parameters,
+                options.bootstrap,
+            )
+            .expect("base VOLE setup failed");
+            for _ in 0..options.repetitions {
+                let (phases, party_stats) = run_verifier::<RingT, _>(
+                    &mut channel_v,
+                    options,
+                    &*code,
+                    verifier_cache.clone(),
+                    delta,
+                );
+                results_v.run_time_stats.record(phases, party_stats);
+                results_v.repetitions += 1;
             }
+            let mut results_p = prover_thread.join().unwrap();
+            results_p.run_time_stats.compute_statistics();
+            results_v.run_time_stats.compute_statistics();
+            print_results(options, &[results_p, results_v]);
         }
         party => {
-            println!("Setting up channel");
-            let mut channel = {
-                match setup_network(&options.network_options) {
-                    Ok(channel) => channel,
-                    Err(e) => {
-                        eprintln!("Network connection failed: {}", e.to_string());
-                        return;
-                    }
+            let mut channel = match setup_network(&options.network_options) {
+                Ok(channel) => channel,
+                Err(e) => {
+                    eprintln!("Network connection failed: {}", e.to_string());
+                    return;
                 }
             };
-
-            for _ in 0..options.repetitions {
-                println!("Running");
-                let (run_time_init, run_time_multiply, party_stats) = match party {
-                    Party::Prover => run_prover::<RingT, _>(
-                        &mut channel,
-                        options.lpn_parameters,
-                        &code,
-                        prover_cache.clone(),
-                        options.dim,
-                        prover_A.clone(),
-                        prover_B.clone(),
-                        &C,
-                        options.multi_thread,
-                        options.nightly,
-                    ),
-                    Party::Verifier => run_verifier::<RingT, _>(
+            let mut results = BenchmarkResult::new(options);
+            match party {
+                Party::Prover => {
+                    let cache = get_prover_cache::<RingT, _>(
                         &mut channel,
-                        options.lpn_parameters,
-                        &code,
-                        verifier_cache.clone(),
-                        delta,
-                        options.dim,
-                        verifier_A.clone(),
-                        verifier_B.clone(),
-                        &C,
-                        options.multi_thread,
-                        options.nightly,
-                    ),
-                    _ => panic!("can't happen"),
-                };
-
-                results.run_time_stats.init_run_times.push(run_time_init);
-                results
-                    .run_time_stats
-                    .multiplication_check_time
-                    .push(run_time_multiply);
-                results.run_time_stats.party_stats.push(party_stats);
-                results.repetitions += 1;
-                if results.repetitions == 1 {
-                    results.run_time_stats.kilobytes_sent = channel.kilobytes_written();
-                    results.run_time_stats.kilobytes_received = channel.kilobytes_read();
+                        &options.lpn_parameters,
+                        options.bootstrap,
+                    )
+                    .expect("base VOLE setup failed");
+                    for _ in 0..options.repetitions {
+                        let (phases, party_stats) =
+                            run_prover::<RingT, _>(&mut channel, options, &*code, cache.clone());
+                        results.run_time_stats.record(phases, party_stats);
+                        results.repetitions += 1;
+                    }
                 }
-                if !options.json {
-                    println!("{:?} time (init): {:?}", options.party, run_time_init);
-                    println!("{:?} time (vole): {:?}", options.party, run_time_multiply);
-                    println!("sent data: {:.2} MiB", channel.kilobytes_written() / 1024.0);
-                    println!(
-                        "received data: {:.2} MiB",
-                        channel.kilobytes_read() / 1024.0
-                    );
+                Party::Verifier => {
+                    let (cache, delta) = get_verifier_cache::<RingT, _>(
+                        &mut channel,
+                        &options.lpn_parameters,
+                        options.bootstrap,
+                    )
+                    .expect("base VOLE setup failed");
+                    for _ in 0..options.repetitions {
+                        let (phases, party_stats) = run_verifier::<RingT, _>(
+                            &mut channel,
+                            options,
+                            &*code,
+                            cache.clone(),
+                            delta,
+                        );
+                        results.run_time_stats.record(phases, party_stats);
+                        results.repetitions += 1;
+                    }
                 }
+                _ => panic!("can't happen"),
             }
-
-            results
-                .run_time_stats
-                .compute_statistics(options.lpn_parameters.get_vole_output_size());
-
-            results
-                .run_time_stats
-                .compute_quarksilver_statistics(options.dim.pow(3));
-            if options.json {
-                println!("{}", serde_json::to_string_pretty(&results).unwrap());
-            } else {
-                println!("results: {:?}", results);
-            }
+            results.run_time_stats.compute_statistics();
+            print_results(options, &[results]);
         }
     }
 }
@@ -565,6 +469,10 @@ fn run() {
     let mut options = Options::parse();
     let mut app = Options::into_app();
 
+    let ring_bit_length = options.ring.bit_length();
+    if let Err(e) = options.lpn_parameters.apply_security_level(ring_bit_length) {
+        app.error(ErrorKind::ArgumentConflict, e).exit();
+    }
     if !options.json {
         println!("LPN Parameters: {}", options.lpn_parameters);
     }
@@ -576,51 +484,21 @@ fn run() {
         )
         .exit();
     }
-    assert!(options.lpn_parameters.validate());
     if !options.json {
         println!("{:?}", options);
     }
-    /*
-       match options.ring {
-           RingParameter::R64 => run_matrix_mul_benchmark::<R64>(&options),
-           // RingParameter::R72 => run_benchmark::<z2r::R72>(&options),
-           // RingParameter::R78 => run_benchmark::<z2r::R78>(&options),
-           RingParameter::R104 => run_matrix_mul_benchmark::<z2r::R104>(&options),
-           // RingParameter::R110 => run_benchmark::<z2r::R110>(&options),
-           // RingParameter::R112 => run_benchmark::<z2r::R112>(&options),
-           // RingParameter::R118 => run_benchmark::<z2r::R118>(&options),
-           // RingParameter::R119 => run_benchmark::<z2r::R119>(&options),
-           RingParameter::R144 => run_benchmark::<z2r::R144>(&options),
-           RingParameter::R150 => run_benchmark::<z2r::R150>(&options),
-           // RingParameter::R151 => run_benchmark::<z2r::R151>(&options),
-           // RingParameter::R196 => run_benchmark::<z2r::R196>(&options),
-           // RingParameter::R203 => run_benchmark::<z2r::R203>(&options),
-           RingParameter::R224 => run_benchmark::<z2r::R224>(&options),
-           RingParameter::R231 => run_benchmark::<z2r::R231>(&options),
-           _ => println!("selected ring {} not compiled in", options.ring.to_string()),
-       }
-    */
-}
-
-fn main() {
-    let mut options = Options::parse();
-    let mut app = Options::into_app();
 
-    if !options.json {
-        println!("LPN Parameters: {}", options.lpn_parameters);
-    }
-    options.lpn_parameters.recompute_extension_size();
-    if !options.lpn_parameters.validate() {
-        app.error(
-            ErrorKind::ArgumentConflict,
-            "Invalid / not-supported LPN parameters",
-        )
-        .exit();
-    }
-    assert!(options.lpn_parameters.validate());
-    if !options.json {
-        println!("{:?}", options);
+    match options.ring {
+        RingParameter::R64 => run_benchmark::<R64>(&options),
+        RingParameter::R130 => run_benchmark::<z2r::R130>(&options),
+        RingParameter::R162 => run_benchmark::<z2r::R162>(&options),
+        RingParameter::R212 => run_benchmark::<z2r::R212>(&options),
+        RingParameter::R244 => run_benchmark::<z2r::R244>(&options),
+        RingParameter::F61p => run_benchmark::<F61p>(&options),
+        _ => println!("selected ring {} not compiled in", options.ring.to_string()),
     }
+}
 
-    run_matrix_mul_benchmark::<R64>(&options)
+fn main() {
+    run()
 }
@@ -7,12 +7,13 @@
 use clap::{ErrorKind, IntoApp, Parser};
 use ocelot::{
     benchmark_tools::{
-        generate_code, setup_cache, setup_network, LpnParameters, NetworkOptions, Party,
-        RingParameter,
+        generate_code, get_prover_cache, get_verifier_cache, setup_network, LpnParameters,
+        NetworkOptions, Party, RingParameter,
     },
     ot::mozzarella::{
         cache::{prover::CachedProver, verifier::CachedVerifier},
-        lpn::LLCode,
+        ggm::TreeExpansion,
+        lpn::{cointoss_code_seed, LpnCode},
         MozzarellaProver, MozzarellaProverStats, MozzarellaVerifier, MozzarellaVerifierStats,
     },
     tools::BenchmarkMetaData,
@@ -21,8 +22,8 @@ use rand::distributions::{Distribution, Standard};
 use rayon;
 use scuttlebutt::{
     channel::{track_unix_channel_pair, Receivable, Sendable, TrackChannel},
-    ring::{z2r, Ring, R64},
-    AbstractChannel,
+    ring::{z2r, F61p, Ring, R64},
+    AbstractChannel, AesRng,
 };
 use serde::Serialize;
 use serde_json;
@@ -44,7 +45,7 @@ struct Options {
     #[clap(short = 'P', long, arg_enum)]
     party: Party,
 
-    /// Which ring to use (rXXX = integers modulo 2^XXX)
+    /// Which ring to use (rXXX = integers modulo 2^XXX, f61p = integers modulo 2^61 - 1)
     #[clap(short = 'R', long, arg_enum, default_value_t = RingParameter::R64)]
     ring: RingParameter,
 
@@ -68,6 +69,27 @@ struct Options {
     #[clap(short, long)]
     nightly: bool,
 
+    /// Jointly sample a fresh LPN code in each session
+    #[clap(long)]
+    fresh_code: bool,
+
+    /// Use the half-tree expansion instead of GGM trees
+    #[clap(long)]
+    half_tree: bool,
+
+    /// Generate the base VOLEs together with the other party instead of using a trusted dealer
+    #[clap(long)]
+    bootstrap: bool,
+
+    /// Length of the secret vector of a setup iteration that generates the base VOLEs of the
+    /// main iteration
+    #[clap(long)]
+    setup_base_vole_size: Option<usize>,
+
+    /// Number of noisy coordinates of the setup iteration
+    #[clap(long)]
+    setup_num_noise_coordinates: Option<usize>,
+
     /// Output recorded data in JSON
     #[clap(short, long)]
     json: bool,
@@ -77,6 +99,26 @@ struct Options {
     verbose: bool,
 }
 
+impl Options {
+    // the setup iteration outputs (at least) enough base VOLEs for the main iteration
+    fn setup_lpn_parameters(&self) -> Option<LpnParameters> {
+        match (self.setup_base_vole_size, self.setup_num_noise_coordinates) {
+            (Some(base_vole_size), Some(num_noise_coordinates)) => {
+                let mut lpn_parameters = LpnParameters {
+                    base_vole_size,
+                    extension_size: self.lpn_parameters.get_required_cache_size(),
+                    num_noise_coordinates,
+                    security_level: None,
+                    code_family: self.lpn_parameters.code_family,
+                };
+                lpn_parameters.recompute_extension_size();
+                Some(lpn_parameters)
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 enum PartyStats {
     ProverStats(MozzarellaProverStats),
@@ -147,6 +189,10 @@ struct BenchmarkResult {
     pub party: String,
     pub ring: String,
     pub threads: usize,
+    pub fresh_code: bool,
+    pub half_tree: bool,
+    pub bootstrap: bool,
+    pub setup_lpn_parameters: Option<LpnParameters>,
     pub network_options: NetworkOptions,
     pub lpn_parameters: LpnParameters,
     pub meta_data: BenchmarkMetaData,
@@ -168,6 +214,10 @@ impl BenchmarkResult {
             party: options.party.to_string(),
             ring: options.ring.to_string(),
             threads: options.threads,
+            fresh_code: options.fresh_code,
+            half_tree: options.half_tree,
+            bootstrap: options.bootstrap,
+            setup_lpn_parameters: options.setup_lpn_parameters(),
             network_options: options.network_options.clone(),
             lpn_parameters: options.lpn_parameters,
             meta_data: BenchmarkMetaData::collect(),
@@ -175,12 +225,16 @@ impl BenchmarkResult {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_prover<RingT, C: AbstractChannel>(
     channel: &mut TrackChannel<C>,
     lpn_parameters: LpnParameters,
-    code: &LLCode<RingT>,
+    code: &dyn LpnCode<RingT>,
+    setup: Option<(&dyn LpnCode<RingT>, LpnParameters)>,
     cache: CachedProver<RingT>,
     nightly: bool,
+    fresh_code: bool,
+    tree_expansion: TreeExpansion,
 ) -> (Duration, Duration, PartyStats)
 where
     RingT: Ring + Receivable,
@@ -195,8 +249,25 @@ where
         lpn_parameters.get_block_size(),
         nightly,
     );
+    moz_prover.set_tree_expansion(tree_expansion);
     let t_start = Instant::now();
-    moz_prover.init(channel).unwrap();
+    if fresh_code {
+        let code_seed = cointoss_code_seed(channel, &mut AesRng::new(), true).unwrap();
+        moz_prover.init_with_code_seed(channel, code_seed).unwrap();
+    } else {
+        moz_prover.init(channel).unwrap();
+    }
+    // the setup iteration is counted as part of the initialization
+    if let Some((setup_code, setup_lpn_parameters)) = setup {
+        moz_prover
+            .setup(
+                channel,
+                setup_code,
+                setup_lpn_parameters.num_noise_coordinates,
+                setup_lpn_parameters.get_block_size(),
+            )
+            .unwrap();
+    }
     let run_time_init = t_start.elapsed();
 
     channel.clear();
@@ -212,13 +283,17 @@ where
     )
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_verifier<RingT, C: AbstractChannel>(
     channel: &mut TrackChannel<C>,
     lpn_parameters: LpnParameters,
-    code: &LLCode<RingT>,
+    code: &dyn LpnCode<RingT>,
+    setup: Option<(&dyn LpnCode<RingT>, LpnParameters)>,
     cache: CachedVerifier<RingT>,
     delta: RingT,
     nightly: bool,
+    fresh_code: bool,
+    tree_expansion: TreeExpansion,
 ) -> (Duration, Duration, PartyStats)
 where
     RingT: Ring + Receivable,
@@ -233,8 +308,27 @@ where
         lpn_parameters.get_block_size(),
         nightly,
     );
+    moz_verifier.set_tree_expansion(tree_expansion);
     let t_start = Instant::now();
-    moz_verifier.init(channel, delta).unwrap();
+    if fresh_code {
+        let code_seed = cointoss_code_seed(channel, &mut AesRng::new(), false).unwrap();
+        moz_verifier
+            .init_with_code_seed(channel, delta, code_seed)
+            .unwrap();
+    } else {
+        moz_verifier.init(channel, delta).unwrap();
+    }
+    // the setup iteration is counted as part of the initialization
+    if let Some((setup_code, setup_lpn_parameters)) = setup {
+        moz_verifier
+            .setup(
+                channel,
+                setup_code,
+                setup_lpn_parameters.num_noise_coordinates,
+                setup_lpn_parameters.get_block_size(),
+            )
+            .unwrap();
+    }
     let run_time_init = t_start.elapsed();
 
     channel.clear();
@@ -257,37 +351,57 @@ where
     Standard: Distribution<RingT>,
 {
     let t_start = Instant::now();
-    let code = generate_code::<RingT>(&options.lpn_parameters);
     rayon::ThreadPoolBuilder::new()
         .num_threads(options.threads)
         .build_global()
         .unwrap();
-    let (prover_cache, (verifier_cache, delta)) = setup_cache(&options.lpn_parameters);
+    let code = generate_code::<RingT>(&options.lpn_parameters);
+    let setup_lpn_parameters = options.setup_lpn_parameters();
+    let setup_code: Option<Arc<dyn LpnCode<RingT>>> =
+        setup_lpn_parameters.map(|p| Arc::from(generate_code::<RingT>(&p)));
+    // with a setup iteration, only its base VOLEs are needed
+    let cache_lpn_parameters = setup_lpn_parameters.unwrap_or(options.lpn_parameters);
     if !options.json {
         println!("Startup time: {:?}", t_start.elapsed());
     }
 
     let mut results = BenchmarkResult::new(&options);
+    let tree_expansion = if options.half_tree {
+        TreeExpansion::HalfTree
+    } else {
+        TreeExpansion::Ggm
+    };
 
     match &options.party {
         Party::Both => {
             let (mut channel_v, mut channel_p) = track_unix_channel_pair();
             let lpn_parameters_p = options.lpn_parameters;
             let lpn_parameters_v = options.lpn_parameters;
-            let code_p = Arc::new(code);
+            let code_p: Arc<dyn LpnCode<RingT>> = Arc::from(code);
             let code_v = code_p.clone();
+            let setup_code_p = setup_code.clone();
+            let setup_code_v = setup_code;
             let repetitions = options.repetitions;
             let nightly = options.nightly;
+            let fresh_code = options.fresh_code;
+            let bootstrap = options.bootstrap;
             let mut results_p = BenchmarkResult::new(&options);
             let mut results_v = results_p.clone();
             let prover_thread = thread::spawn(move || {
+                let prover_cache =
+                    get_prover_cache::<RingT, _>(&mut channel_p, &cache_lpn_parameters, bootstrap)
+                        .expect("base VOLE setup failed");
+                channel_p.clear();
                 for _ in 0..repetitions {
                     let (run_time_init, run_time_extend, party_stats) = run_prover::<RingT, _>(
                         &mut channel_p,
                         lpn_parameters_p,
-                        &code_p,
+                        &*code_p,
+                        setup_code_p.as_deref().zip(setup_lpn_parameters),
                         prover_cache.clone(),
                         nightly,
+                        fresh_code,
+                        tree_expansion,
                     );
                     results_p.run_time_stats.init_run_times.push(run_time_init);
                     results_p
@@ -304,14 +418,24 @@ where
                 results_p
             });
             let verifier_thread = thread::spawn(move || {
+                let (verifier_cache, delta) = get_verifier_cache::<RingT, _>(
+                    &mut channel_v,
+                    &cache_lpn_parameters,
+                    bootstrap,
+                )
+                .expect("base VOLE setup failed");
+                channel_v.clear();
                 for _ in 0..repetitions {
                     let (run_time_init, run_time_extend, party_stats) = run_verifier::<RingT, _>(
                         &mut channel_v,
                         lpn_parameters_v,
-                        &code_v,
+                        &*code_v,
+                        setup_code_v.as_deref().zip(setup_lpn_parameters),
                         verifier_cache.clone(),
                         delta,
                         nightly,
+                        fresh_code,
+                        tree_expansion,
                     );
                     results_v.run_time_stats.init_run_times.push(run_time_init);
                     results_v
@@ -353,22 +477,51 @@ where
                     }
                 }
             };
+            let (prover_cache, verifier_cache, delta) = match party {
+                Party::Prover => {
+                    let cache = get_prover_cache::<RingT, _>(
+                        &mut channel,
+                        &cache_lpn_parameters,
+                        options.bootstrap,
+                    )
+                    .expect("base VOLE setup failed");
+                    (Some(cache), None, RingT::ZERO)
+                }
+                Party::Verifier => {
+                    let (cache, delta) = get_verifier_cache::<RingT, _>(
+                        &mut channel,
+                        &cache_lpn_parameters,
+                        options.bootstrap,
+                    )
+                    .expect("base VOLE setup failed");
+                    (None, Some(cache), delta)
+                }
+                _ => panic!("can't happen"),
+            };
+            // do not count the setup of the base VOLEs
+            channel.clear();
             for _ in 0..options.repetitions {
                 let (run_time_init, run_time_extend, party_stats) = match party {
                     Party::Prover => run_prover::<RingT, _>(
                         &mut channel,
                         options.lpn_parameters,
-                        &code,
-                        prover_cache.clone(),
+                        &*code,
+                        setup_code.as_deref().zip(setup_lpn_parameters),
+                        prover_cache.clone().unwrap(),
                         options.nightly,
+                        options.fresh_code,
+                        tree_expansion,
                     ),
                     Party::Verifier => run_verifier::<RingT, _>(
                         &mut channel,
                         options.lpn_parameters,
-                        &code,
-                        verifier_cache.clone(),
+                        &*code,
+                        setup_code.as_deref().zip(setup_lpn_parameters),
+                        verifier_cache.clone().unwrap(),
                         delta,
                         options.nightly,
+                        options.fresh_code,
+                        tree_expansion,
                     ),
                     _ => panic!("can't happen"),
                 };
@@ -409,6 +562,10 @@ fn run() {
     let mut options = Options::parse();
     let mut app = Options::into_app();
 
+    let ring_bit_length = options.ring.bit_length();
+    if let Err(e) = options.lpn_parameters.apply_security_level(ring_bit_length) {
+        app.error(ErrorKind::ArgumentConflict, e).exit();
+    }
     if !options.json {
         println!("LPN Parameters: {}", options.lpn_parameters);
     }
@@ -421,6 +578,25 @@ fn run() {
         .exit();
     }
     assert!(options.lpn_parameters.validate());
+    if options.setup_base_vole_size.is_some() != options.setup_num_noise_coordinates.is_some() {
+        app.error(
+            ErrorKind::ArgumentConflict,
+            "--setup-base-vole-size and --setup-num-noise-coordinates must be given together",
+        )
+        .exit();
+    }
+    if let Some(setup_lpn_parameters) = options.setup_lpn_parameters() {
+        if !setup_lpn_parameters.validate() {
+            app.error(
+                ErrorKind::ArgumentConflict,
+                "Invalid / not-supported LPN parameters for the setup iteration",
+            )
+            .exit();
+        }
+        if !options.json {
+            println!("Setup LPN Parameters: {}", setup_lpn_parameters);
+        }
+    }
     if !options.json {
         println!("{:?}", options);
     }
@@ -437,6 +613,7 @@ fn run() {
         RingParameter::R212 => run_benchmark::<z2r::R212>(&options),
         RingParameter::R224 => run_benchmark::<z2r::R224>(&options),
         RingParameter::R244 => run_benchmark::<z2r::R244>(&options),
+        RingParameter::F61p => run_benchmark::<F61p>(&options),
         // _ => println!("selected ring {} not compiled in", options.ring.to_string()),
     }
 }
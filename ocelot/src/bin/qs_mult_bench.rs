@@ -7,12 +7,12 @@
 use clap::{ErrorKind, IntoApp, Parser};
 use ocelot::{
     benchmark_tools::{
-        generate_code, setup_cache, setup_network, LpnParameters, NetworkOptions, Party,
-        RingParameter,
+        generate_code, get_prover_cache, get_verifier_cache, setup_network, LpnParameters,
+        NetworkOptions, Party, RingParameter,
     },
     ot::mozzarella::{
         cache::{prover::CachedProver, verifier::CachedVerifier},
-        lpn::LLCode,
+        lpn::LpnCode,
         MozzarellaProver, MozzarellaVerifier,
     },
     quarksilver::{
@@ -24,7 +24,7 @@ use rand::distributions::{Distribution, Standard};
 use rayon;
 use scuttlebutt::{
     channel::{track_unix_channel_pair, Receivable, Sendable, TrackChannel},
-    ring::{z2r, Ring},
+    ring::{z2r, F61p, Ring},
     AbstractChannel,
 };
 use serde::Serialize;
@@ -47,7 +47,7 @@ struct Options {
     #[clap(short = 'P', long, arg_enum)]
     party: Party,
 
-    /// Which ring to use (rXXX = integers modulo 2^XXX)
+    /// Which ring to use (rXXX = integers modulo 2^XXX, f61p = integers modulo 2^61 - 1)
     #[clap(short = 'R', long, arg_enum, default_value_t = RingParameter::R64)]
     ring: RingParameter,
 
@@ -64,11 +64,7 @@ struct Options {
     lpn_parameters: LpnParameters,
 
     /// Number of multiplications to verifiy
-    #[clap(
-        short,
-        long,
-        default_value_t = 1,
-    )]
+    #[clap(short, long, default_value_t = 1)]
     num_mults: usize,
 
     /// Network options
@@ -87,6 +83,10 @@ struct Options {
     #[clap(long)]
     nightly: bool,
 
+    /// Generate the base VOLEs together with the other party instead of using a trusted dealer
+    #[clap(long)]
+    bootstrap: bool,
+
     /// Output recorded data in JSON
     #[clap(short, long)]
     json: bool,
@@ -177,6 +177,7 @@ struct BenchmarkResult {
     pub party: String,
     pub ring: String,
     pub threads: usize,
+    pub bootstrap: bool,
     pub network_options: NetworkOptions,
     pub lpn_parameters: LpnParameters,
     pub meta_data: BenchmarkMetaData,
@@ -208,6 +209,7 @@ impl BenchmarkResult {
             party: options.party.to_string(),
             ring: options.ring.to_string(),
             threads: options.threads,
+            bootstrap: options.bootstrap,
             network_options: options.network_options.clone(),
             lpn_parameters: options.lpn_parameters,
             meta_data: BenchmarkMetaData::collect(),
@@ -220,7 +222,7 @@ fn run_prover<RingT, C: AbstractChannel>(
     plain_size: usize,
     statsec: usize,
     lpn_parameters: LpnParameters,
-    code: &LLCode<RingT>,
+    code: &dyn LpnCode<RingT>,
     cache: CachedProver<RingT>,
     num_mults: usize,
     _nightly: bool,
@@ -321,7 +323,7 @@ fn run_verifier<RingT, C: AbstractChannel>(
     plain_size: usize,
     statsec: usize,
     lpn_parameters: LpnParameters,
-    code: &LLCode<RingT>,
+    code: &dyn LpnCode<RingT>,
     cache: CachedVerifier<RingT>,
     delta: RingT,
     num_mults: usize,
@@ -419,12 +421,11 @@ where
     Standard: Distribution<RingT>,
 {
     let t_start = Instant::now();
-    let code = generate_code::<RingT>(&options.lpn_parameters);
     rayon::ThreadPoolBuilder::new()
         .num_threads(options.threads)
         .build_global()
         .unwrap();
-    let (prover_cache, (verifier_cache, delta)) = setup_cache(&options.lpn_parameters);
+    let code = generate_code::<RingT>(&options.lpn_parameters);
     if !options.json {
         println!("Startup time: {:?}", t_start.elapsed());
     }
@@ -438,14 +439,19 @@ where
             let statsec = options.statsec;
             let lpn_parameters_p = options.lpn_parameters;
             let lpn_parameters_v = options.lpn_parameters;
-            let code_p = Arc::new(code);
+            let code_p: Arc<dyn LpnCode<RingT>> = Arc::from(code);
             let code_v = code_p.clone();
             let repetitions = options.repetitions;
             let num_mults = options.num_mults;
             let nightly = options.nightly;
+            let bootstrap = options.bootstrap;
             let mut results_p = BenchmarkResult::new(&options);
             let mut results_v = results_p.clone();
             let prover_thread = thread::spawn(move || {
+                let prover_cache =
+                    get_prover_cache::<RingT, _>(&mut channel_p, &lpn_parameters_p, bootstrap)
+                        .expect("base VOLE setup failed");
+                channel_p.clear();
                 for _ in 0..repetitions {
                     let (
                         run_time_init,
@@ -458,7 +464,7 @@ where
                         plain_size,
                         statsec,
                         lpn_parameters_p,
-                        &code_p,
+                        &*code_p,
                         prover_cache.clone(),
                         num_mults,
                         nightly,
@@ -495,6 +501,10 @@ where
                 results_p
             });
             let verifier_thread = thread::spawn(move || {
+                let (verifier_cache, delta) =
+                    get_verifier_cache::<RingT, _>(&mut channel_v, &lpn_parameters_v, bootstrap)
+                        .expect("base VOLE setup failed");
+                channel_v.clear();
                 for _ in 0..repetitions {
                     let (
                         run_time_init,
@@ -507,7 +517,7 @@ where
                         plain_size,
                         statsec,
                         lpn_parameters_v,
-                        &code_v,
+                        &*code_v,
                         verifier_cache.clone(),
                         delta,
                         num_mults,
@@ -570,6 +580,29 @@ where
                     }
                 }
             };
+            let (prover_cache, verifier_cache, delta) = match party {
+                Party::Prover => {
+                    let cache = get_prover_cache::<RingT, _>(
+                        &mut channel,
+                        &options.lpn_parameters,
+                        options.bootstrap,
+                    )
+                    .expect("base VOLE setup failed");
+                    (Some(cache), None, RingT::ZERO)
+                }
+                Party::Verifier => {
+                    let (cache, delta) = get_verifier_cache::<RingT, _>(
+                        &mut channel,
+                        &options.lpn_parameters,
+                        options.bootstrap,
+                    )
+                    .expect("base VOLE setup failed");
+                    (None, Some(cache), delta)
+                }
+                _ => panic!("can't happen"),
+            };
+            // do not count the setup of the base VOLEs
+            channel.clear();
             for _ in 0..options.repetitions {
                 let (
                     run_time_init,
@@ -583,8 +616,8 @@ where
                         options.plain_size,
                         options.statsec,
                         options.lpn_parameters,
-                        &code,
-                        prover_cache.clone(),
+                        &*code,
+                        prover_cache.clone().unwrap(),
                         options.num_mults,
                         options.nightly,
                     ),
@@ -593,8 +626,8 @@ where
                         options.plain_size,
                         options.statsec,
                         options.lpn_parameters,
-                        &code,
-                        verifier_cache.clone(),
+                        &*code,
+                        verifier_cache.clone().unwrap(),
                         delta,
                         options.num_mults,
                         options.nightly,
@@ -651,6 +684,10 @@ fn run() {
     let mut options = Options::parse();
     let mut app = Options::into_app();
 
+    let ring_bit_length = options.ring.bit_length();
+    if let Err(e) = options.lpn_parameters.apply_security_level(ring_bit_length) {
+        app.error(ErrorKind::ArgumentConflict, e).exit();
+    }
     if !options.json {
         println!("LPN Parameters: {}", options.lpn_parameters);
     }
@@ -672,6 +709,7 @@ fn run() {
         RingParameter::R162 => run_benchmark::<z2r::R162>(&options),
         RingParameter::R212 => run_benchmark::<z2r::R212>(&options),
         RingParameter::R244 => run_benchmark::<z2r::R244>(&options),
+        RingParameter::F61p => run_benchmark::<F61p>(&options),
         _ => println!("selected ring {} not compiled in", options.ring.to_string()),
     }
 }
@@ -0,0 +1,263 @@
+// -*- mode: rust; -*-
+//
+// This file is part of ocelot.
+// Copyright © 2020 Galois, Inc.
+// See LICENSE for licensing information.
+
+use clap::{ErrorKind, IntoApp, Parser};
+use ocelot::{
+    benchmark_tools::{
+        bootstrap_prover_cache, bootstrap_verifier_cache, generate_code, setup_network,
+        LpnParameters, NetworkOptions, Party, RingParameter,
+    },
+    quarksilver::{self, circuit::CircuitStats, sieve, QuarkSilverProver, QuarkSilverVerifier},
+    tools::BenchmarkMetaData,
+    Error,
+};
+use rand::distributions::{Distribution, Standard};
+use scuttlebutt::{
+    channel::{Receivable, Sendable},
+    ring::{z2r, F61p, Ring, R64},
+};
+use serde::Serialize;
+use serde_json;
+use std::{
+    fs,
+    path::PathBuf,
+    string::ToString,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Parser)]
+#[clap(
+    name = "QuarkSilver SIEVE IR Prover/Verifier",
+    author = "Alex Hansen, Lennart Braun",
+    version = "0.1"
+)]
+struct Options {
+    /// Which party should be run
+    #[clap(short = 'P', long, arg_enum)]
+    party: Party,
+
+    /// Which ring to use (rXXX = integers modulo 2^XXX, f61p = integers modulo 2^61 - 1)
+    #[clap(short = 'R', long, arg_enum, default_value_t = RingParameter::R64)]
+    ring: RingParameter,
+
+    /// Statistical security parameter
+    #[clap(short, long)]
+    statsec: usize,
+
+    /// Relation file
+    #[clap(long, parse(from_os_str))]
+    relation: PathBuf,
+
+    /// Instance file
+    #[clap(long, parse(from_os_str))]
+    instance: PathBuf,
+
+    /// Short witness file (only used by the prover)
+    #[clap(long, parse(from_os_str))]
+    witness: Option<PathBuf>,
+
+    /// Parameters for the Leaky Regular LPN Problem
+    #[clap(flatten, help_heading = "LPN parameters")]
+    lpn_parameters: LpnParameters,
+
+    /// Network options
+    #[clap(flatten, help_heading = "Network options")]
+    network_options: NetworkOptions,
+
+    /// Output recorded data in JSON
+    #[clap(short, long)]
+    json: bool,
+
+    /// Output additional information
+    #[clap(short, long)]
+    verbose: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct RunResult {
+    pub party: String,
+    pub ring: String,
+    pub plain_size: usize,
+    pub statsec: usize,
+    pub init_time: Duration,
+    pub circuit_stats: CircuitStats,
+    pub kilobytes_sent: f64,
+    pub kilobytes_received: f64,
+    pub network_options: NetworkOptions,
+    pub lpn_parameters: LpnParameters,
+    pub meta_data: BenchmarkMetaData,
+}
+
+fn read_file(path: &PathBuf) -> Result<String, Error> {
+    fs::read_to_string(path)
+        .map_err(|e| Error::Other(format!("cannot read {}: {}", path.display(), e)))
+}
+
+fn run_party<RingT>(options: &Options) -> Result<RunResult, Error>
+where
+    RingT: Ring + Receivable,
+    for<'b> &'b RingT: Sendable,
+    Standard: Distribution<RingT>,
+{
+    let relation = sieve::parse_relation(&read_file(&options.relation)?)?;
+    let instance = sieve::parse_instance(&read_file(&options.instance)?)?;
+    let characteristic = relation.characteristic();
+    let circuit = sieve::build_circuit::<RingT>(&relation, &instance)?;
+    let witness: Vec<RingT> = match (&options.party, &options.witness) {
+        (Party::Prover, Some(path)) => {
+            let witness = sieve::parse_witness(&read_file(path)?)?;
+            if witness.characteristic != characteristic {
+                return Err(Error::Other(
+                    "witness and relation are over different fields".to_string(),
+                ));
+            }
+            witness.values.iter().map(|&v| sieve::to_ring(v)).collect()
+        }
+        (Party::Prover, None) => {
+            return Err(Error::Other("the prover needs a witness".to_string()))
+        }
+        _ => Vec::new(),
+    };
+    let plain_size = characteristic.bit_length();
+    quarksilver::validate_parameters::<RingT>(plain_size, options.statsec)?;
+    if let Party::Prover = options.party {
+        if witness.len() != circuit.gate_counts().inputs {
+            return Err(Error::Other(format!(
+                "the relation needs {} witness values, but {} are given",
+                circuit.gate_counts().inputs,
+                witness.len()
+            )));
+        }
+        if !circuit.is_satisfied(&witness, plain_size) {
+            return Err(Error::Other(
+                "the witness does not satisfy the relation".to_string(),
+            ));
+        }
+    }
+    if options.verbose {
+        println!("plain size: {}", plain_size);
+        println!("gates: {:?}", circuit.gate_counts());
+    }
+
+    let code = generate_code::<RingT>(&options.lpn_parameters);
+    let lpn_parameters = options.lpn_parameters;
+    let mut channel = setup_network(&options.network_options)?;
+    let t_start = Instant::now();
+    let circuit_stats = match &options.party {
+        Party::Prover => {
+            let cache = bootstrap_prover_cache::<RingT, _>(&mut channel, &lpn_parameters)?;
+            let mut prover = QuarkSilverProver::<RingT>::new(
+                plain_size,
+                options.statsec,
+                cache,
+                &*code,
+                lpn_parameters.base_vole_size,
+                lpn_parameters.num_noise_coordinates,
+                lpn_parameters.get_block_size(),
+            );
+            prover.init(&mut channel)?;
+            prover.prove_circuit(&mut channel, &circuit, &witness)?
+        }
+        Party::Verifier => {
+            let (cache, delta) =
+                bootstrap_verifier_cache::<RingT, _>(&mut channel, &lpn_parameters)?;
+            let mut verifier = QuarkSilverVerifier::<RingT>::new(
+                plain_size,
+                options.statsec,
+                cache,
+                &*code,
+                lpn_parameters.base_vole_size,
+                lpn_parameters.num_noise_coordinates,
+                lpn_parameters.get_block_size(),
+            );
+            verifier.init(&mut channel, delta)?;
+            verifier.verify_circuit(&mut channel, &circuit)?
+        }
+        Party::Both => panic!("can't happen"),
+    };
+    let total_time = t_start.elapsed();
+    Ok(RunResult {
+        party: options.party.to_string(),
+        ring: options.ring.to_string(),
+        plain_size,
+        statsec: options.statsec,
+        init_time: total_time
+            - circuit_stats.vole_time
+            - circuit_stats.evaluation_time
+            - circuit_stats.check_multiply_time
+            - circuit_stats.check_zero_time,
+        circuit_stats,
+        kilobytes_sent: channel.kilobytes_written(),
+        kilobytes_received: channel.kilobytes_read(),
+        network_options: options.network_options.clone(),
+        lpn_parameters,
+        meta_data: BenchmarkMetaData::collect(),
+    })
+}
+
+fn run() {
+    let mut options = Options::parse();
+    let mut app = Options::into_app();
+
+    if let Party::Both = options.party {
+        app.error(
+            ErrorKind::ArgumentConflict,
+            "Only one party can be run, use --party prover or --party verifier",
+        )
+        .exit();
+    }
+    let ring_bit_length = options.ring.bit_length();
+    if let Err(e) = options.lpn_parameters.apply_security_level(ring_bit_length) {
+        app.error(ErrorKind::ArgumentConflict, e).exit();
+    }
+    options.lpn_parameters.recompute_extension_size();
+    if !options.lpn_parameters.validate() {
+        app.error(
+            ErrorKind::ArgumentConflict,
+            "Invalid / not-supported LPN parameters",
+        )
+        .exit();
+    }
+    if options.verbose {
+        println!("{:?}", options);
+    }
+
+    let result = match options.ring {
+        RingParameter::R64 => run_party::<R64>(&options),
+        RingParameter::R130 => run_party::<z2r::R130>(&options),
+        RingParameter::R144 => run_party::<z2r::R144>(&options),
+        RingParameter::R162 => run_party::<z2r::R162>(&options),
+        RingParameter::R212 => run_party::<z2r::R212>(&options),
+        RingParameter::R244 => run_party::<z2r::R244>(&options),
+        RingParameter::F61p => run_party::<F61p>(&options),
+        _ => {
+            eprintln!("selected ring {} not compiled in", options.ring.to_string());
+            std::process::exit(1);
+        }
+    };
+    match result {
+        Ok(result) if options.json => {
+            println!("{}", serde_json::to_string_pretty(&result).unwrap())
+        }
+        Ok(result) => {
+            println!("{:?} succeeded", options.party);
+            println!("{:?}", result.circuit_stats);
+            println!("sent data: {:.2} MiB", result.kilobytes_sent / 1024.0);
+            println!(
+                "received data: {:.2} MiB",
+                result.kilobytes_received / 1024.0
+            );
+        }
+        Err(e) => {
+            eprintln!("{:?} failed: {}", options.party, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    run()
+}
@@ -40,12 +40,16 @@ pub struct Sender<OT: OtReceiver<Msg = Block> + SemiHonest> {
     pub(super) hash: AesHash,
     s: Vec<bool>,
     pub(super) s_: Block,
+    // base OT outputs, kept to reseed `rngs`
+    seeds: Vec<Block>,
     rngs: Vec<AesRng>,
 }
 /// Oblivious transfer receiver.
 pub struct Receiver<OT: OtSender<Msg = Block> + SemiHonest> {
     _ot: PhantomData<OT>,
     pub(super) hash: AesHash,
+    // base OT inputs, kept to reseed `rngs`
+    seeds: Vec<(Block, Block)>,
     rngs: Vec<(AesRng, AesRng)>,
 }
 
@@ -57,9 +61,10 @@ impl<OT: OtReceiver<Msg = Block> + SemiHonest> FixedKeyInitializer for Sender<OT
     ) -> Result<Self, Error> {
         let mut ot = OT::init(channel, rng)?;
         let s = utils::u8vec_to_boolvec(&s_);
-        let ks = ot.receive(channel, &s, rng)?;
-        let rngs = ks
-            .into_iter()
+        let seeds = ot.receive(channel, &s, rng)?;
+        let rngs = seeds
+            .iter()
+            .copied()
             .map(AesRng::from_seed)
             .collect::<Vec<AesRng>>();
         Ok(Self {
@@ -67,12 +72,24 @@ impl<OT: OtReceiver<Msg = Block> + SemiHonest> FixedKeyInitializer for Sender<OT
             hash: AES_HASH,
             s,
             s_: Block::from(s_),
+            seeds,
             rngs,
         })
     }
 }
 
 impl<OT: OtReceiver<Msg = Block> + SemiHonest> Sender<OT> {
+    // derive fresh PRGs from the base OT outputs, `epoch` must not have been used before (the
+    // initial PRGs correspond to epoch 0)
+    pub(super) fn reseed(&mut self, epoch: u64) {
+        assert!(epoch > 0);
+        self.rngs = self
+            .seeds
+            .iter()
+            .map(|&k| AesRng::from_seed(self.hash.tccr_hash(Block::from(epoch as u128), k)))
+            .collect();
+    }
+
     pub(super) fn send_setup<C: AbstractChannel>(
         &mut self,
         channel: &mut C,
@@ -184,6 +201,22 @@ impl<OT: OtReceiver<Msg = Block> + SemiHonest> RandomSender for Sender<OT> {
 }
 
 impl<OT: OtSender<Msg = Block> + SemiHonest> Receiver<OT> {
+    // counterpart of `Sender::reseed`
+    pub(super) fn reseed(&mut self, epoch: u64) {
+        assert!(epoch > 0);
+        let tweak = Block::from(epoch as u128);
+        self.rngs = self
+            .seeds
+            .iter()
+            .map(|&(k0, k1)| {
+                (
+                    AesRng::from_seed(self.hash.tccr_hash(tweak, k0)),
+                    AesRng::from_seed(self.hash.tccr_hash(tweak, k1)),
+                )
+            })
+            .collect();
+    }
+
     pub(super) fn receive_setup<C: AbstractChannel>(
         &mut self,
         channel: &mut C,
@@ -226,12 +259,13 @@ impl<OT: OtSender<Msg = Block> + SemiHonest> OtReceiver for Receiver<OT> {
         }
         ot.send(channel, &ks, rng)?;
         let rngs = ks
-            .into_iter()
-            .map(|(k0, k1)| (AesRng::from_seed(k0), AesRng::from_seed(k1)))
+            .iter()
+            .map(|&(k0, k1)| (AesRng::from_seed(k0), AesRng::from_seed(k1)))
             .collect::<Vec<(AesRng, AesRng)>>();
         Ok(Self {
             _ot: PhantomData::<OT>,
             hash: AES_HASH,
+            seeds: ks,
             rngs,
         })
     }
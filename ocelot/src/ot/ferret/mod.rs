@@ -25,6 +25,10 @@ pub type FerretReceiver = Receiver<true>;
 pub type FerretSenderUniform = Sender<false>;
 pub type FerretReceiverUniform = Receiver<false>;
 
+// ferret with regular error and half-tree instead of GGM trees in the SPCOT
+pub type FerretSenderHalfTree = Sender<true, true>;
+pub type FerretReceiverHalfTree = Receiver<true, true>;
+
 // used to break the COT correlation for when ROT is desired
 fn cr_cot_hash() -> AesHash {
     AesHash::new([1u8; 16].into())
@@ -33,6 +37,7 @@ fn cr_cot_hash() -> AesHash {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ot::{VoleReceiver, VoleSender};
 
     use std::thread::spawn;
 
@@ -52,7 +57,40 @@ mod tests {
         test_ferret::<false>();
     }
 
+    #[test]
+    fn test_ferret_reg_half_tree() {
+        test_ferret_with::<true, true>();
+    }
+
+    #[test]
+    fn test_ferret_vole_traits() {
+        const NUM_VOLES: usize = 1000;
+        let (mut c1, mut c2) = unix_channel_pair();
+
+        let handle = spawn(move || {
+            let mut rng = StdRng::seed_from_u64(0);
+            let mut sender = FerretSender::init(rng.gen(), &mut c1, &mut rng).unwrap();
+            let ys = sender.send_vole(&mut c1, NUM_VOLES, &mut rng).unwrap();
+            (sender.get_delta(), ys)
+        });
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut receiver = FerretReceiver::init(&mut c2, &mut rng).unwrap();
+        let (xs, zs) = receiver.receive_vole(&mut c2, NUM_VOLES, &mut rng).unwrap();
+        let (delta, ys) = handle.join().unwrap();
+
+        assert_eq!(ys.len(), NUM_VOLES);
+        for ((x, z), y) in xs.into_iter().zip(zs.into_iter()).zip(ys.into_iter()) {
+            // z = x * delta + y over GF(2^128)
+            assert_eq!(z, if x { y ^ delta } else { y });
+        }
+    }
+
     fn test_ferret<const REG: bool>() {
+        test_ferret_with::<REG, false>();
+    }
+
+    fn test_ferret_with<const REG: bool, const HALF_TREE: bool>() {
         let mut root = StdRng::seed_from_u64(0x5367_FA32_72B1_8478);
 
         {
@@ -63,7 +101,7 @@ mod tests {
             let handle = spawn(move || {
                 let delta: Block = rng1.gen();
                 let mut ys: Vec<Block> = Vec::with_capacity(GEN_COTS);
-                let mut sender = Sender::<REG>::init(delta, &mut c1, &mut rng1).unwrap();
+                let mut sender = Sender::<REG, HALF_TREE>::init(delta, &mut c1, &mut rng1).unwrap();
                 for _ in 0..GEN_COTS {
                     ys.push(sender.cot(&mut c1, &mut rng1).unwrap());
                 }
@@ -71,7 +109,7 @@ mod tests {
             });
 
             let mut xzs: Vec<(bool, Block)> = Vec::with_capacity(GEN_COTS);
-            let mut receiver = Receiver::<REG>::init(&mut c2, &mut rng2).unwrap();
+            let mut receiver = Receiver::<REG, HALF_TREE>::init(&mut c2, &mut rng2).unwrap();
             for _ in 0..GEN_COTS {
                 xzs.push(receiver.cot(&mut c2, &mut rng2).unwrap());
             }
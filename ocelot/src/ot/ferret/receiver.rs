@@ -1,5 +1,5 @@
 use super::*;
-use crate::ot::{KosDeltaReceiver, Receiver as OtReceiver};
+use crate::ot::{KosDeltaReceiver, Receiver as OtReceiver, VoleReceiver};
 
 use crate::Error;
 use scuttlebutt::{AbstractChannel, AesHash, Block};
@@ -8,13 +8,13 @@ use rand::{CryptoRng, Rng};
 
 use cache::CachedReceiver;
 
-pub struct Receiver<const REG: bool> {
+pub struct Receiver<const REG: bool, const HALF_TREE: bool = false> {
     hash: AesHash,
     cots: cache::CachedReceiver,
     spcot: spcot::Receiver,
 }
 
-impl<const REG: bool> Receiver<REG> {
+impl<const REG: bool, const HALF_TREE: bool> Receiver<REG, HALF_TREE> {
     pub fn init<C: AbstractChannel, R: Rng + CryptoRng>(
         channel: &mut C,
         rng: &mut R,
@@ -30,7 +30,11 @@ impl<const REG: bool> Receiver<REG> {
         )?;
 
         // do 1-time setup iteration
-        let mut spcot = spcot::Receiver::init();
+        let mut spcot = if HALF_TREE {
+            spcot::Receiver::init_half_tree()
+        } else {
+            spcot::Receiver::init()
+        };
         let (x, z) = ferret::Receiver::<REG>::extend_setup(&mut cots, &mut spcot, rng, channel)?;
         cots.append(x.into_iter(), z.into_iter());
 
@@ -76,3 +80,25 @@ impl<const REG: bool> Receiver<REG> {
         }
     }
 }
+
+// COTs are subfield VOLEs with values in GF(2) and MACs in GF(2^128)
+impl<const REG: bool, const HALF_TREE: bool> VoleReceiver for Receiver<REG, HALF_TREE> {
+    type Value = bool;
+    type Mac = Block;
+
+    fn receive_vole<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+        rng: &mut RNG,
+    ) -> Result<(Vec<bool>, Vec<Block>), Error> {
+        let mut xs = Vec::with_capacity(n);
+        let mut zs = Vec::with_capacity(n);
+        for _ in 0..n {
+            let (x, z) = self.cot(channel, rng)?;
+            xs.push(x);
+            zs.push(z);
+        }
+        Ok((xs, zs))
+    }
+}
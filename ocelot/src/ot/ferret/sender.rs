@@ -1,5 +1,5 @@
 use super::*;
-use crate::ot::{FixedKeyInitializer, KosDeltaSender};
+use crate::ot::{FixedKeyInitializer, KosDeltaSender, VoleSender};
 
 use crate::Error;
 use scuttlebutt::{AbstractChannel, AesHash, Block};
@@ -8,13 +8,13 @@ use rand::{CryptoRng, Rng};
 
 use cache::CachedSender;
 
-pub struct Sender<const REG: bool> {
+pub struct Sender<const REG: bool, const HALF_TREE: bool = false> {
     hash: AesHash,
     cots: cache::CachedSender,
     spcot: spcot::Sender,
 }
 
-impl<const REG: bool> FixedKeyInitializer for Sender<REG> {
+impl<const REG: bool, const HALF_TREE: bool> FixedKeyInitializer for Sender<REG, HALF_TREE> {
     fn init_fixed_key<C: AbstractChannel, RNG: CryptoRng + Rng>(
         channel: &mut C,
         s: [u8; 16],
@@ -24,7 +24,7 @@ impl<const REG: bool> FixedKeyInitializer for Sender<REG> {
     }
 }
 
-impl<const REG: bool> Sender<REG> {
+impl<const REG: bool, const HALF_TREE: bool> Sender<REG, HALF_TREE> {
     pub fn init<C: AbstractChannel, R: Rng + CryptoRng>(
         delta: Block,
         channel: &mut C,
@@ -42,7 +42,11 @@ impl<const REG: bool> Sender<REG> {
         )?;
 
         // do 1-time setup iteration
-        let mut spcot = spcot::Sender::init(delta);
+        let mut spcot = if HALF_TREE {
+            spcot::Sender::init_half_tree(delta)
+        } else {
+            spcot::Sender::init(delta)
+        };
         let y = ferret::Sender::<REG>::extend_setup(&mut cots, &mut spcot, rng, channel)?;
         cots.append(y.into_iter());
 
@@ -82,3 +86,22 @@ impl<const REG: bool> Sender<REG> {
         ))
     }
 }
+
+// COTs are subfield VOLEs with values in GF(2) and MACs in GF(2^128)
+impl<const REG: bool, const HALF_TREE: bool> VoleSender for Sender<REG, HALF_TREE> {
+    type Value = bool;
+    type Mac = Block;
+
+    fn get_delta(&self) -> Block {
+        self.spcot.delta
+    }
+
+    fn send_vole<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+        rng: &mut RNG,
+    ) -> Result<Vec<Block>, Error> {
+        (0..n).map(|_| self.cot(channel, rng)).collect()
+    }
+}
@@ -39,6 +39,16 @@ fn prg2(h: &AesHash, k1: Block) -> (Block, Block) {
     (o1, o2)
 }
 
+// Node expansion of the half-tree (cf. Guo et al., https://eprint.iacr.org/2022/1431):
+// the children of a node sum to the node itself, hence every level sums to Delta.
+// As for the GGM tree, the tweak is derived from the repetition l and the level i.
+#[inline(always)]
+fn expand(h: &AesHash, k: Block, l: usize, i: usize) -> (Block, Block) {
+    let tweak: Block = (((l as u128) << 64) | i as u128).into();
+    let o1 = h.ccr_hash(tweak, k);
+    (o1, o1 ^ k)
+}
+
 #[inline]
 fn unpack_bits<const N: usize>(mut n: usize) -> [bool; N] {
     debug_assert!(n < (1 << N));
@@ -132,6 +142,12 @@ mod tests {
     }
 
     fn test_spcot_correlation<const H: usize, const N: usize>(num: usize) {
+        test_spcot_correlation_with::<H, N, false>(num);
+    }
+
+    fn test_spcot_correlation_with<const H: usize, const N: usize, const HALF_TREE: bool>(
+        num: usize,
+    ) {
         let mut root = StdRng::seed_from_u64(0x5367_FA32_72B1_8478);
         for _ in 0..10 {
             // de-randomize the test
@@ -148,7 +164,11 @@ mod tests {
                 cache
                     .generate(&mut kos18, &mut c2, &mut rng1, H * num + CSP)
                     .unwrap();
-                let mut send: Sender = Sender::init(delta);
+                let mut send: Sender = if HALF_TREE {
+                    Sender::init_half_tree(delta)
+                } else {
+                    Sender::init(delta)
+                };
                 let v = send
                     .extend::<_, _, H, N>(&mut cache, &mut c2, &mut rng1, num)
                     .unwrap();
@@ -163,7 +183,11 @@ mod tests {
                 .generate(&mut kos18, &mut c1, &mut rng2, H * num + CSP)
                 .unwrap();
 
-            let mut recv: Receiver = Receiver::init();
+            let mut recv: Receiver = if HALF_TREE {
+                Receiver::init_half_tree()
+            } else {
+                Receiver::init()
+            };
             //( let out = recv.receive_random(&mut c1, &[true], &mut OsRng).unwrap();
 
             let alpha: Vec<usize> = (0..num).map(|_| rng2.gen::<usize>() % N).collect();
@@ -216,4 +240,13 @@ mod tests {
             test_spcot_correlation::<5, 32>(i);
         }
     }
+
+    #[test]
+    fn test_spcot_correlation_half_tree() {
+        for i in vec![1, 2, 5, 10].into_iter() {
+            test_spcot_correlation_with::<1, 2, true>(i);
+            test_spcot_correlation_with::<2, 4, true>(i);
+            test_spcot_correlation_with::<5, 32, true>(i);
+        }
+    }
 }
@@ -9,6 +9,7 @@ use super::*;
 pub struct Receiver {
     hash: AesHash,
     l: usize,
+    half_tree: bool,
 }
 
 impl Receiver {
@@ -16,6 +17,67 @@ impl Receiver {
         Self {
             hash: cr_hash(),
             l: 0,
+            half_tree: false,
+        }
+    }
+
+    /// Use the half-tree expansion instead of GGM trees.
+    pub fn init_half_tree() -> Self {
+        Self {
+            half_tree: true,
+            ..Self::init()
+        }
+    }
+
+    // Evaluate the half-tree at all leaves except alpha, where t_i = K_i ^ ~a_i Delta
+    fn eval_half_tree<const H: usize, const N: usize>(
+        &self,
+        t: &[Block],
+        c: &[Block; H],
+        alpha: usize,
+        si: &mut [Block; N],
+    ) {
+        let a: [bool; H] = unpack_bits::<H>(alpha);
+
+        // s_{~a[1]} := K_{~a[1]}^1
+        si[!a[0] as usize] = t[0];
+        for i in 1..H {
+            let s: usize = H - i - 1;
+            let a_s: usize = (alpha >> s) ^ 0x1;
+            let a_sm: usize = a_s >> 1;
+            let nai: usize = a_s & 1;
+
+            // K_{~a[i]}^i := c_i ^ t_i
+            let kna = c[i] ^ t[i];
+
+            // expand all seeds of the previous level except the one on the path
+            let mut j = (1 << i) - 1;
+            loop {
+                if j != a_sm {
+                    let (s0, s1) = expand(&self.hash, si[j], self.l, i);
+                    si[2 * j] = s0;
+                    si[2 * j + 1] = s1;
+                }
+                if j == 0 {
+                    break;
+                }
+                j -= 1;
+            }
+
+            si[a_s] = kna;
+            for j in 0..(1 << i) {
+                if j != a_sm {
+                    si[a_s] ^= si[2 * j + nai];
+                }
+            }
+        }
+
+        // all leaves sum to Delta
+        si[alpha] = Block::default();
+        for i in 0..N {
+            if i != alpha {
+                si[alpha] ^= si[i];
+            }
         }
     }
 
@@ -55,6 +117,16 @@ impl Receiver {
             let a: [bool; H] = unpack_bits::<H>(alpha);
             let t: &[Block] = &t[H * rep..H * (rep + 1)];
 
+            if self.half_tree {
+                let mut c: [Block; H] = [Default::default(); H];
+                for ci in c.iter_mut().skip(1) {
+                    *ci = channel.receive()?;
+                }
+                self.eval_half_tree::<H, N>(t, &c, alpha, &mut ws[rep]);
+                self.l += 1;
+                continue;
+            }
+
             // receive (m, c) from S
             let m: [(Block, Block); H] = channel.receive()?;
             let c: Block = channel.receive()?;
@@ -8,6 +8,7 @@ pub struct Sender {
     pub(crate) delta: Block,
     hash: AesHash,
     l: usize, // repetition of SPCOT
+    half_tree: bool,
 }
 
 impl Sender {
@@ -16,9 +17,51 @@ impl Sender {
             delta,
             hash: cr_hash(),
             l: 0,
+            half_tree: false,
         }
     }
 
+    /// Use the half-tree expansion instead of GGM trees.
+    pub fn init_half_tree(delta: Block) -> Self {
+        Self {
+            half_tree: true,
+            ..Self::init(delta)
+        }
+    }
+
+    // Expand a half-tree whose first level is (K_1, K_1 ^ Delta) with the COT keys
+    // K_i := q_i ^ b_i Delta, and return c_i := K^i_0 ^ K_i for the levels i >= 2
+    fn expand_half_tree<const H: usize, const N: usize>(
+        &self,
+        q: &[Block],
+        b: usize,
+        v: &mut [Block; N],
+    ) -> [Block; H] {
+        let b: [bool; H] = unpack_bits::<H>(b);
+        let k = |i: usize| if b[i] { q[i] ^ self.delta } else { q[i] };
+        let mut c: [Block; H] = [Default::default(); H];
+
+        v[0] = k(0);
+        v[1] = k(0) ^ self.delta;
+        for (i, ci) in c.iter_mut().enumerate().skip(1) {
+            // K^i_0: XOR of all the left child seeds
+            let mut k0 = Block::default();
+            let mut j = (1 << i) - 1;
+            loop {
+                let res = expand(&self.hash, v[j], self.l, i);
+                k0 ^= res.0;
+                v[2 * j] = res.0;
+                v[2 * j + 1] = res.1;
+                if j == 0 {
+                    break;
+                }
+                j -= 1;
+            }
+            *ci = k0 ^ k(i);
+        }
+        c
+    }
+
     #[allow(non_snake_case)]
     pub fn extend<C: AbstractChannel, RNG: CryptoRng + Rng, const H: usize, const N: usize>(
         &mut self,
@@ -50,6 +93,14 @@ impl Sender {
             let mut m: [(Block, Block); H] = [(Default::default(), Default::default()); H];
             let v: &mut [Block; N] = &mut vs[rep];
 
+            // the leaves of a half-tree already sum to Delta, so c is not needed
+            if self.half_tree {
+                let c = self.expand_half_tree::<H, N>(q, b, v);
+                channel.send(&c[1..])?;
+                self.l += 1;
+                continue;
+            }
+
             // pick root seed
             v[0] = rng.gen();
 
@@ -39,6 +39,10 @@ pub struct Receiver<OT: OtSender<Msg = Block> + Malicious> {
 }
 
 impl<OT: OtReceiver<Msg = Block> + Malicious> Sender<OT> {
+    pub(super) fn reseed(&mut self, epoch: u64) {
+        self.ot.reseed(epoch)
+    }
+
     pub(super) fn send_setup<C: AbstractChannel, RNG: CryptoRng + Rng>(
         &mut self,
         channel: &mut C,
@@ -179,6 +183,10 @@ impl<OT: OtReceiver<Msg = Block> + Malicious> std::fmt::Display for Sender<OT> {
 }
 
 impl<OT: OtSender<Msg = Block> + Malicious> Receiver<OT> {
+    pub(super) fn reseed(&mut self, epoch: u64) {
+        self.ot.reseed(epoch)
+    }
+
     pub(super) fn receive_setup<C: AbstractChannel, RNG: CryptoRng + Rng>(
         &mut self,
         channel: &mut C,
@@ -34,6 +34,13 @@ pub struct Receiver<OT: OtSender<Msg = Block> + Malicious> {
 }
 
 impl<OT: OtReceiver<Msg = Block> + Malicious> Sender<OT> {
+    /// Derive fresh extension PRGs from the base OTs, e.g., to continue on a new connection
+    /// after an interrupted extension without redoing the base OTs.  Both parties need to use
+    /// the same `epoch`, which must be non-zero and must not have been used before.
+    pub fn reseed(&mut self, epoch: u64) {
+        self.ot.reseed(epoch)
+    }
+
     fn send_setup<C: AbstractChannel, RNG: CryptoRng + Rng>(
         &mut self,
         channel: &mut C,
@@ -113,6 +120,11 @@ impl<OT: OtReceiver<Msg = Block> + Malicious> std::fmt::Display for Sender<OT> {
 }
 
 impl<OT: OtSender<Msg = Block> + Malicious> Receiver<OT> {
+    /// Counterpart of `Sender::reseed`.
+    pub fn reseed(&mut self, epoch: u64) {
+        self.ot.reseed(epoch)
+    }
+
     fn receive_setup<C: AbstractChannel, RNG: CryptoRng + Rng>(
         &mut self,
         channel: &mut C,
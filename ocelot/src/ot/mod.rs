@@ -177,6 +177,53 @@ where
     ) -> Result<Vec<Self::Msg>, Error>;
 }
 
+/// Trait for random vector oblivious linear evaluation (VOLE) from the point-of-view of the
+/// party holding the global key `delta`.
+///
+/// A VOLE is a tuple `(u, w, v)` such that `w = delta * u + v`, where the receiver holds the value
+/// `u` and its MAC `w`, and the sender holds the key `v`.  In a subfield VOLE, the values `u` come
+/// from a subfield (or subring) of the domain of `w`, `v`, and `delta`.
+///
+/// The traits only cover the generation of random VOLEs.  Setup, rekeying, and statistics are
+/// specific to each backend, so QuarkSilver still uses the Mozzarella prover and verifier directly.
+pub trait VoleSender
+where
+    Self: Sized,
+{
+    /// Domain of the values `u`.
+    type Value;
+    /// Domain of the MACs `w`, the keys `v`, and `delta`.
+    type Mac;
+    /// Returns the global key `delta`.
+    fn get_delta(&self) -> Self::Mac;
+    /// Generates `n` random VOLEs and returns the keys.
+    fn send_vole<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+        rng: &mut RNG,
+    ) -> Result<Vec<Self::Mac>, Error>;
+}
+
+/// Trait for random vector oblivious linear evaluation (VOLE) from the point-of-view of the
+/// party holding the values and their MACs.
+pub trait VoleReceiver
+where
+    Self: Sized,
+{
+    /// Domain of the values `u`.
+    type Value;
+    /// Domain of the MACs `w`, the keys `v`, and `delta`.
+    type Mac;
+    /// Generates `n` random VOLEs and returns the values and their MACs.
+    fn receive_vole<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+        rng: &mut RNG,
+    ) -> Result<(Vec<Self::Value>, Vec<Self::Mac>), Error>;
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(feature = "nightly")]
@@ -0,0 +1,607 @@
+//! Two-party generation of the initial base VOLEs, replacing the trusted dealer in `GenCache`.
+//!
+//! This follows the COPEe approach: the verifier uses the bits of `delta` as choice bits for
+//! `RingT::BIT_LENGTH` random OTs, both parties expand the OT keys with a PRG, and for each VOLE
+//! the prover sends one correction per bit of `delta`.  Since `delta` is fixed by the OT choice
+//! bits, the verifier cannot use inconsistent `delta`s.  A final random linear combination check
+//! catches a prover that did not use the same `u` for every bit.
+//!
+//! Over Z_2^l, a check in Z_2^l itself is not sound: an error in the top bits of a correction
+//! vanishes when it is multiplied with an even coefficient.  As in SPDZ2k, the VOLEs are therefore
+//! generated and checked in Z_2^(l + s) and only reduced to Z_2^l afterwards, which bounds the
+//! probability that an error modulo 2^l passes the check by about 2^-s.  Over a field, the check
+//! is done in the field itself.  As usual for COPEe, a cheating prover can still guess bits of
+//! `delta` at the risk of being caught.
+//!
+//! The correlation is the same as in the rest of Mozzarella: the prover obtains `(u, w)`, the
+//! verifier obtains `v`, such that `w = delta * u + v`.
+use crate::{
+    ot::{
+        mozzarella::cache::{prover::CachedProver, verifier::CachedVerifier},
+        KosDeltaReceiver, KosDeltaSender, RandomReceiver, RandomSender, Receiver as OtReceiver,
+        Sender as OtSender,
+    },
+    Error,
+};
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng, SeedableRng,
+};
+use scuttlebutt::{
+    channel::{Receivable, Sendable},
+    ring::Ring,
+    AbstractChannel, AesHash, AesRng, Block,
+};
+use std::{
+    marker::PhantomData,
+    ops::{Add, AddAssign, Mul, Neg, Sub},
+};
+
+/// Number of additional bits of the ring in which VOLEs over Z_2^l are generated and checked.
+const CHECK_STATSEC: usize = 40;
+
+const WIDE_LIMBS: usize = 5;
+
+// used to break the correlation of the KOS COTs
+fn cr_ot_hash() -> AesHash {
+    AesHash::new([2u8; 16].into())
+}
+
+// the arithmetic in which the correlations are generated and checked
+trait CopeElement<RingT>:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Neg<Output = Self>
+where
+    Self: AddAssign + PartialEq,
+{
+    const ZERO: Self;
+    fn random<R: Rng>(rng: &mut R) -> Self;
+    fn lift(x: RingT) -> Self;
+    fn project(&self) -> RingT;
+    fn send_to<C: AbstractChannel>(&self, channel: &mut C) -> Result<(), Error>;
+    fn receive_from<C: AbstractChannel>(channel: &mut C) -> Result<Self, Error>;
+}
+
+// fields: the ring itself
+impl<RingT> CopeElement<RingT> for RingT
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    const ZERO: Self = <RingT as Ring>::ZERO;
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        rng.gen()
+    }
+
+    fn lift(x: RingT) -> Self {
+        x
+    }
+
+    fn project(&self) -> RingT {
+        self.reduce()
+    }
+
+    fn send_to<C: AbstractChannel>(&self, channel: &mut C) -> Result<(), Error> {
+        channel.send(self)?;
+        Ok(())
+    }
+
+    fn receive_from<C: AbstractChannel>(channel: &mut C) -> Result<Self, Error> {
+        Ok(channel.receive()?)
+    }
+}
+
+/// An element of Z_2^(l + CHECK_STATSEC) for l = `RingT::BIT_LENGTH`.  The arithmetic is done
+/// modulo 2^(64 * WIDE_LIMBS) and reduced when comparing and sending.
+struct Wide<RingT>([u64; WIDE_LIMBS], PhantomData<RingT>);
+
+// not derived, since that would require `RingT: Copy`
+impl<RingT> Clone for Wide<RingT> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<RingT> Copy for Wide<RingT> {}
+
+impl<RingT: Ring> Wide<RingT>
+where
+    Standard: Distribution<RingT>,
+{
+    const BIT_LENGTH: usize = RingT::BIT_LENGTH + CHECK_STATSEC;
+    const BYTE_LENGTH: usize = Self::BIT_LENGTH.div_ceil(8);
+
+    fn reduced(&self) -> [u64; WIDE_LIMBS] {
+        let mut limbs = self.0;
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let bits = Self::BIT_LENGTH.saturating_sub(64 * i);
+            if bits < 64 {
+                *limb &= (1u64 << bits) - 1;
+            }
+        }
+        limbs
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        let mut limbs = [0u64; WIDE_LIMBS];
+        for (i, &b) in bytes.iter().enumerate() {
+            limbs[i / 8] |= (b as u64) << (8 * (i % 8));
+        }
+        Self(limbs, PhantomData)
+    }
+}
+
+impl<RingT> Add for Wide<RingT> {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        let mut out = [0u64; WIDE_LIMBS];
+        let mut carry = false;
+        for ((o, &a), &b) in out.iter_mut().zip(self.0.iter()).zip(rhs.0.iter()) {
+            let (s, c1) = a.overflowing_add(b);
+            let (s, c2) = s.overflowing_add(carry as u64);
+            *o = s;
+            carry = c1 || c2;
+        }
+        Self(out, PhantomData)
+    }
+}
+
+impl<RingT> AddAssign for Wide<RingT> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<RingT> Neg for Wide<RingT> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        let mut one = [0u64; WIDE_LIMBS];
+        one[0] = 1;
+        // two's complement
+        Self(self.0.map(|x| !x), PhantomData) + Self(one, PhantomData)
+    }
+}
+
+impl<RingT> Sub for Wide<RingT> {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self + (-rhs)
+    }
+}
+
+impl<RingT> Mul for Wide<RingT> {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        // schoolbook multiplication, truncated to WIDE_LIMBS limbs
+        let mut out = [0u64; WIDE_LIMBS];
+        for i in 0..WIDE_LIMBS {
+            let mut carry = 0u128;
+            for j in 0..WIDE_LIMBS - i {
+                let t = self.0[i] as u128 * rhs.0[j] as u128 + out[i + j] as u128 + carry;
+                out[i + j] = t as u64;
+                carry = t >> 64;
+            }
+        }
+        Self(out, PhantomData)
+    }
+}
+
+impl<RingT: Ring> PartialEq for Wide<RingT>
+where
+    Standard: Distribution<RingT>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.reduced() == other.reduced()
+    }
+}
+
+impl<RingT> CopeElement<RingT> for Wide<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    const ZERO: Self = Self([0u64; WIDE_LIMBS], PhantomData);
+
+    fn random<R: Rng>(rng: &mut R) -> Self {
+        let mut limbs = [0u64; WIDE_LIMBS];
+        rng.fill(&mut limbs[..]);
+        Self(limbs, PhantomData)
+    }
+
+    fn lift(x: RingT) -> Self {
+        Self::from_bytes(x.reduce().as_ref())
+    }
+
+    fn project(&self) -> RingT {
+        // Horner's rule in base 2^64
+        let mut acc = <RingT as Ring>::ZERO;
+        for &limb in self.0.iter().rev() {
+            acc = acc * (1u64 << 32) * (1u64 << 32) + limb;
+        }
+        acc.reduce()
+    }
+
+    fn send_to<C: AbstractChannel>(&self, channel: &mut C) -> Result<(), Error> {
+        let mut bytes = [0u8; 8 * WIDE_LIMBS];
+        for (i, limb) in self.0.iter().enumerate() {
+            bytes[8 * i..8 * (i + 1)].copy_from_slice(&limb.to_le_bytes());
+        }
+        channel.write_bytes(&bytes[..Self::BYTE_LENGTH])?;
+        Ok(())
+    }
+
+    fn receive_from<C: AbstractChannel>(channel: &mut C) -> Result<Self, Error> {
+        let mut bytes = [0u8; 8 * WIDE_LIMBS];
+        channel.read_bytes(&mut bytes[..Self::BYTE_LENGTH])?;
+        Ok(Self::from_bytes(&bytes[..Self::BYTE_LENGTH]))
+    }
+}
+
+// compute sum_j 2^j * xs[j] using Horner's rule
+#[inline]
+fn combine_bits<RingT, X: CopeElement<RingT>>(xs: &[X]) -> X {
+    let mut acc = X::ZERO;
+    for &x in xs.iter().rev() {
+        acc = acc + acc + x;
+    }
+    acc
+}
+
+// derive the linear combination coefficients for the consistency check
+fn gen_chis<RingT, X: CopeElement<RingT>>(seed: Block, n: usize) -> Vec<X> {
+    let mut rng = AesRng::from_seed(seed);
+    (0..n).map(|_| X::random(&mut rng)).collect()
+}
+
+pub struct BaseVoleProver<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    rng: AesRng,
+    prg_pairs: Vec<(AesRng, AesRng)>,
+    is_init_done: bool,
+    _phantom: PhantomData<RingT>,
+}
+
+impl<RingT> Default for BaseVoleProver<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<RingT> BaseVoleProver<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    pub fn new() -> Self {
+        assert!(RingT::IS_FIELD || Wide::<RingT>::BIT_LENGTH <= 64 * WIDE_LIMBS);
+        Self {
+            rng: AesRng::new(),
+            prg_pairs: Vec::with_capacity(RingT::BIT_LENGTH),
+            is_init_done: false,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn init<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        let mut ot_sender = KosDeltaSender::init(channel, &mut self.rng)?;
+        self.init_with_ot(channel, &mut ot_sender)
+    }
+
+    pub fn init_with_ot<C: AbstractChannel, OT: OtSender<Msg = Block> + RandomSender>(
+        &mut self,
+        channel: &mut C,
+        ot_sender: &mut OT,
+    ) -> Result<(), Error> {
+        let keys = ot_sender.send_random(channel, RingT::BIT_LENGTH, &mut self.rng)?;
+        let hash = cr_ot_hash();
+        self.prg_pairs = keys
+            .into_iter()
+            .enumerate()
+            .map(|(j, (k0, k1))| {
+                let tweak = Block::from(j as u128);
+                (
+                    AesRng::from_seed(hash.tccr_hash(k0, tweak)),
+                    AesRng::from_seed(hash.tccr_hash(k1, tweak)),
+                )
+            })
+            .collect();
+        self.is_init_done = true;
+        Ok(())
+    }
+
+    fn gen_vole<X: CopeElement<RingT>, C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        t0s: &mut [X],
+    ) -> Result<(X, X), Error> {
+        let u = X::random(&mut self.rng);
+        for ((prg_0, prg_1), t0) in self.prg_pairs.iter_mut().zip(t0s.iter_mut()) {
+            *t0 = X::random(prg_0);
+            let t1 = X::random(prg_1);
+            // tau_j = t0_j - t1_j + u
+            (*t0 - t1 + u).send_to(channel)?;
+        }
+        Ok((u, -combine_bits(t0s)))
+    }
+
+    // prove that the VOLEs are consistent, the last one is used as mask
+    fn prove_consistency<X: CopeElement<RingT>, C: AbstractChannel>(
+        channel: &mut C,
+        us: &[X],
+        ws: &[X],
+    ) -> Result<(), Error> {
+        let n = us.len() - 1;
+        let chi_seed: Block = channel.receive()?;
+        let chis = gen_chis::<RingT, X>(chi_seed, n);
+        let mut x = us[n];
+        let mut z = ws[n];
+        for ((&chi, &u), &w) in chis.iter().zip(us.iter()).zip(ws.iter()) {
+            x += chi * u;
+            z += chi * w;
+        }
+        x.send_to(channel)?;
+        z.send_to(channel)?;
+        channel.flush()?;
+        Ok(())
+    }
+
+    fn extend_in<X: CopeElement<RingT>, C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+    ) -> Result<CachedProver<RingT>, Error> {
+        let mut t0s = vec![X::ZERO; RingT::BIT_LENGTH];
+        // one additional VOLE to mask the consistency check
+        let mut us = Vec::with_capacity(n + 1);
+        let mut ws = Vec::with_capacity(n + 1);
+        for _ in 0..=n {
+            let (u, w) = self.gen_vole(channel, &mut t0s)?;
+            us.push(u);
+            ws.push(w);
+        }
+        channel.flush()?;
+        Self::prove_consistency(channel, &us, &ws)?;
+        Ok(CachedProver::init(
+            us[..n].iter().map(X::project).collect(),
+            ws[..n].iter().map(X::project).collect(),
+        ))
+    }
+
+    pub fn extend<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+    ) -> Result<CachedProver<RingT>, Error> {
+        assert!(self.is_init_done);
+        if RingT::IS_FIELD {
+            self.extend_in::<RingT, C>(channel, n)
+        } else {
+            self.extend_in::<Wide<RingT>, C>(channel, n)
+        }
+    }
+}
+
+pub struct BaseVoleVerifier<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    rng: AesRng,
+    delta: RingT,
+    delta_bits: Vec<bool>,
+    prgs: Vec<AesRng>,
+    is_init_done: bool,
+}
+
+impl<RingT> Default for BaseVoleVerifier<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<RingT> BaseVoleVerifier<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    pub fn new() -> Self {
+        assert!(RingT::IS_FIELD || Wide::<RingT>::BIT_LENGTH <= 64 * WIDE_LIMBS);
+        Self {
+            rng: AesRng::new(),
+            delta: Default::default(),
+            delta_bits: Vec::with_capacity(RingT::BIT_LENGTH),
+            prgs: Vec::with_capacity(RingT::BIT_LENGTH),
+            is_init_done: false,
+        }
+    }
+
+    pub fn get_delta(&self) -> RingT {
+        self.delta
+    }
+
+    pub fn init<C: AbstractChannel>(&mut self, channel: &mut C, delta: RingT) -> Result<(), Error> {
+        let mut ot_receiver = KosDeltaReceiver::init(channel, &mut self.rng)?;
+        self.init_with_ot(channel, &mut ot_receiver, delta)
+    }
+
+    pub fn init_with_ot<C: AbstractChannel, OT: OtReceiver<Msg = Block> + RandomReceiver>(
+        &mut self,
+        channel: &mut C,
+        ot_receiver: &mut OT,
+        delta: RingT,
+    ) -> Result<(), Error> {
+        self.delta = delta.reduce();
+        // little-endian bit decomposition of delta
+        let delta_bytes = self.delta.as_ref();
+        self.delta_bits = (0..RingT::BIT_LENGTH)
+            .map(|j| (delta_bytes[j / 8] >> (j % 8)) & 1 == 1)
+            .collect();
+        let keys = ot_receiver.receive_random(channel, &self.delta_bits, &mut self.rng)?;
+        let hash = cr_ot_hash();
+        self.prgs = keys
+            .into_iter()
+            .enumerate()
+            .map(|(j, k)| AesRng::from_seed(hash.tccr_hash(k, Block::from(j as u128))))
+            .collect();
+        self.is_init_done = true;
+        Ok(())
+    }
+
+    fn gen_vole<X: CopeElement<RingT>, C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        ws: &mut [X],
+    ) -> Result<X, Error> {
+        for ((prg, &delta_j), w) in self
+            .prgs
+            .iter_mut()
+            .zip(self.delta_bits.iter())
+            .zip(ws.iter_mut())
+        {
+            let tau = X::receive_from(channel)?;
+            let s = X::random(prg);
+            // w_j = t0_j + delta_j * u
+            *w = if delta_j { s + tau } else { s };
+        }
+        Ok(-combine_bits(ws))
+    }
+
+    fn extend_in<X: CopeElement<RingT>, C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+    ) -> Result<CachedVerifier<RingT>, Error> {
+        let mut ws = vec![X::ZERO; RingT::BIT_LENGTH];
+        let mut vs = Vec::with_capacity(n);
+        for _ in 0..n {
+            vs.push(self.gen_vole(channel, &mut ws)?);
+        }
+        let v_mask = self.gen_vole(channel, &mut ws)?;
+
+        let chi_seed = self.rng.gen::<Block>();
+        channel.send(&chi_seed)?;
+        channel.flush()?;
+        let chis = gen_chis::<RingT, X>(chi_seed, n);
+        let mut y = v_mask;
+        for (&chi, &v) in chis.iter().zip(vs.iter()) {
+            y += chi * v;
+        }
+        let x = X::receive_from(channel)?;
+        let z = X::receive_from(channel)?;
+        if z != X::lift(self.delta) * x + y {
+            return Err(Error::EqCheckFailed);
+        }
+
+        Ok(CachedVerifier::init(vs.iter().map(X::project).collect()))
+    }
+
+    pub fn extend<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+    ) -> Result<CachedVerifier<RingT>, Error> {
+        assert!(self.is_init_done);
+        if RingT::IS_FIELD {
+            self.extend_in::<RingT, C>(channel, n)
+        } else {
+            self.extend_in::<Wide<RingT>, C>(channel, n)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BaseVoleProver, BaseVoleVerifier, CopeElement, Wide};
+    use rand::{
+        distributions::{Distribution, Standard},
+        rngs::OsRng,
+        Rng,
+    };
+    use scuttlebutt::{
+        channel::{Receivable, Sendable},
+        ring::{z2r, Ring, R64},
+        unix_channel_pair, AbstractChannel,
+    };
+    use std::thread::spawn;
+
+    // a prover that adds 2^(l-1) to tau_0 of the first VOLE, which is not detected by a check in
+    // Z_2^l if chi is even
+    fn test_cheating_prover<RingT>()
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        const NUM_VOLES: usize = 10;
+        const NUM_RUNS: usize = 10;
+
+        // make sure that bit 0 of delta is set
+        let r = OsRng.gen::<RingT>();
+        let delta = (r + r + RingT::ONE).reduce();
+        let error = (1..RingT::BIT_LENGTH).fold(RingT::ONE, |acc, _| acc + acc);
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+
+        let prover_thread = spawn(move || {
+            let mut prover = BaseVoleProver::<RingT>::new();
+            prover.init(&mut channel_p).unwrap();
+            for _ in 0..NUM_RUNS {
+                let mut us = Vec::with_capacity(NUM_VOLES + 1);
+                let mut ws = Vec::with_capacity(NUM_VOLES + 1);
+                let mut t0s = vec![Wide::<RingT>::ZERO; RingT::BIT_LENGTH];
+                for i in 0..=NUM_VOLES {
+                    let u = Wide::<RingT>::random(&mut prover.rng);
+                    for (j, ((prg_0, prg_1), t0)) in
+                        prover.prg_pairs.iter_mut().zip(t0s.iter_mut()).enumerate()
+                    {
+                        *t0 = Wide::<RingT>::random(prg_0);
+                        let mut tau = *t0 - Wide::<RingT>::random(prg_1) + u;
+                        if i == 0 && j == 0 {
+                            tau += Wide::<RingT>::lift(error);
+                        }
+                        tau.send_to(&mut channel_p).unwrap();
+                    }
+                    us.push(u);
+                    ws.push(-super::combine_bits(&t0s));
+                }
+                channel_p.flush().unwrap();
+                BaseVoleProver::<RingT>::prove_consistency(&mut channel_p, &us, &ws).unwrap();
+            }
+        });
+
+        let mut verifier = BaseVoleVerifier::<RingT>::new();
+        verifier.init(&mut channel_v, delta).unwrap();
+        for _ in 0..NUM_RUNS {
+            assert!(verifier.extend(&mut channel_v, NUM_VOLES).is_err());
+        }
+        prover_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_cheating_prover_r64() {
+        test_cheating_prover::<R64>();
+    }
+
+    #[test]
+    fn test_cheating_prover_r144() {
+        test_cheating_prover::<z2r::R144>();
+    }
+}
@@ -1,3 +1,147 @@
+pub mod bootstrap;
 pub mod cacheinit;
+pub mod persist;
 pub mod prover;
 pub mod verifier;
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bootstrap::{BaseVoleProver, BaseVoleVerifier},
+        cacheinit::GenCache,
+        persist::read_header,
+        prover::CachedProver,
+        verifier::CachedVerifier,
+    };
+    use rand::{
+        distributions::{Distribution, Standard},
+        rngs::OsRng,
+        Rng,
+    };
+    use scuttlebutt::{
+        channel::{Receivable, Sendable},
+        ring::{z2r, Ring, R64},
+        unix_channel_pair,
+    };
+    use std::{path::PathBuf, thread::spawn};
+
+    fn test_bootstrap<RingT>()
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        const NUM_VOLES: usize = 100;
+        const NUM_ITERATIONS: usize = 2;
+
+        let delta = OsRng.gen::<RingT>().reduce();
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+
+        let prover_thread = spawn(move || {
+            let mut prover = BaseVoleProver::<RingT>::new();
+            prover.init(&mut channel_p).unwrap();
+            (0..NUM_ITERATIONS)
+                .map(|_| prover.extend(&mut channel_p, NUM_VOLES).unwrap())
+                .collect::<Vec<_>>()
+        });
+        let verifier_thread = spawn(move || {
+            let mut verifier = BaseVoleVerifier::<RingT>::new();
+            verifier.init(&mut channel_v, delta).unwrap();
+            (0..NUM_ITERATIONS)
+                .map(|_| verifier.extend(&mut channel_v, NUM_VOLES).unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let prover_caches = prover_thread.join().unwrap();
+        let verifier_caches = verifier_thread.join().unwrap();
+        for (mut cached_prover, mut cached_verifier) in
+            prover_caches.into_iter().zip(verifier_caches.into_iter())
+        {
+            assert_eq!(cached_prover.capacity(), NUM_VOLES);
+            assert_eq!(cached_verifier.capacity(), NUM_VOLES);
+            let (us, ws) = cached_prover.get(NUM_VOLES);
+            let vs = cached_verifier.get(NUM_VOLES);
+            for i in 0..NUM_VOLES {
+                assert!(us[i].is_reduced());
+                assert!(ws[i].is_reduced());
+                assert!(vs[i].is_reduced());
+                assert_eq!(ws[i], delta * us[i] + vs[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bootstrap_r64() {
+        test_bootstrap::<R64>();
+    }
+
+    #[test]
+    fn test_bootstrap_r104() {
+        test_bootstrap::<z2r::R104>();
+    }
+
+    #[test]
+    fn test_bootstrap_r144() {
+        test_bootstrap::<z2r::R144>();
+    }
+
+    #[test]
+    fn test_bootstrap_r224() {
+        test_bootstrap::<z2r::R224>();
+    }
+
+    fn temp_cache_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ocelot-{}-{}.cache", name, std::process::id()))
+    }
+
+    fn test_persist<RingT>(name: &str)
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        const NUM_VOLES: usize = 100;
+        let delta = OsRng.gen::<RingT>().reduce();
+        let (cached_prover, cached_verifier) = GenCache::new_with_size(OsRng, delta, NUM_VOLES);
+        let path_p = temp_cache_path(&format!("{}-prover", name));
+        let path_v = temp_cache_path(&format!("{}-verifier", name));
+        cached_prover.save_to_file(&path_p).unwrap();
+        cached_verifier.save_to_file(&path_v, delta).unwrap();
+
+        assert_eq!(read_header(&path_p).unwrap().num_voles, NUM_VOLES);
+        let mut loaded_prover = CachedProver::<RingT>::load_from_file(&path_p).unwrap();
+        let mut loaded_verifier = CachedVerifier::<RingT>::load_from_file(&path_v, delta).unwrap();
+        assert_eq!(
+            loaded_prover.get(NUM_VOLES),
+            cached_prover.clone().get(NUM_VOLES)
+        );
+        assert_eq!(
+            loaded_verifier.get(NUM_VOLES),
+            cached_verifier.clone().get(NUM_VOLES)
+        );
+
+        // wrong role, delta, and ring
+        assert!(CachedVerifier::<RingT>::load_from_file(&path_p, delta).is_err());
+        assert!(CachedProver::<RingT>::load_from_file(&path_v).is_err());
+        assert!(CachedVerifier::<RingT>::load_from_file(&path_v, delta + RingT::ONE).is_err());
+        assert!(CachedProver::<z2r::R72>::load_from_file(&path_p).is_err());
+
+        // truncated file
+        let data = std::fs::read(&path_p).unwrap();
+        std::fs::write(&path_p, &data[..data.len() - 1]).unwrap();
+        assert!(CachedProver::<RingT>::load_from_file(&path_p).is_err());
+
+        std::fs::remove_file(path_p).unwrap();
+        std::fs::remove_file(path_v).unwrap();
+    }
+
+    #[test]
+    fn test_persist_r64() {
+        test_persist::<R64>("r64");
+    }
+
+    #[test]
+    fn test_persist_r144() {
+        test_persist::<z2r::R144>("r144");
+    }
+}
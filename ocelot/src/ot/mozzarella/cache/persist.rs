@@ -0,0 +1,286 @@
+//! Versioned file format to store VOLE caches on disk.
+//!
+//! A cache file consists of a header followed by the cached values.  The header records which
+//! party wrote the file, whether the values are from Z_2^k or a prime field together with the bit
+//! length, and (for the verifier) a fingerprint of `delta`, such that a cache is never loaded
+//! into the wrong ring, role, or session.  The number of values is checked against the file size
+//! before anything is allocated.
+use crate::{
+    ot::mozzarella::cache::{prover::CachedProver, verifier::CachedVerifier},
+    Error,
+};
+use rand::distributions::{Distribution, Standard};
+use scuttlebutt::{
+    channel::{Receivable, Sendable},
+    ring::Ring,
+    AbstractChannel, Channel,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+const CACHE_FILE_MAGIC: [u8; 8] = *b"MOZZVOLE";
+pub const CACHE_FILE_VERSION: u32 = 2;
+// magic, version, role, ring kind, bit length, element size, delta fingerprint, number of VOLEs
+const CACHE_FILE_HEADER_SIZE: u64 = 8 + 4 + 1 + 1 + 8 + 8 + 32 + 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CacheRole {
+    Prover,
+    Verifier,
+}
+
+impl fmt::Display for CacheRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CacheRole::Prover => write!(f, "prover"),
+            CacheRole::Verifier => write!(f, "verifier"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RingKind {
+    /// integers modulo 2^k
+    Z2k,
+    /// integers modulo a prime
+    PrimeField,
+}
+
+impl RingKind {
+    fn of<RingT: Ring>() -> Self
+    where
+        Standard: Distribution<RingT>,
+    {
+        if RingT::IS_FIELD {
+            RingKind::PrimeField
+        } else {
+            RingKind::Z2k
+        }
+    }
+}
+
+impl fmt::Display for RingKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RingKind::Z2k => write!(f, "Z_2^k"),
+            RingKind::PrimeField => write!(f, "prime field"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheFileHeader {
+    pub version: u32,
+    pub role: CacheRole,
+    pub ring_kind: RingKind,
+    pub bit_length: usize,
+    pub element_size: usize,
+    pub delta_fingerprint: [u8; 32],
+    pub num_voles: usize,
+}
+
+impl CacheFileHeader {
+    fn new<RingT: Ring>(role: CacheRole, delta_fingerprint: [u8; 32], num_voles: usize) -> Self
+    where
+        Standard: Distribution<RingT>,
+    {
+        Self {
+            version: CACHE_FILE_VERSION,
+            role,
+            ring_kind: RingKind::of::<RingT>(),
+            bit_length: RingT::BIT_LENGTH,
+            element_size: std::mem::size_of::<RingT>(),
+            delta_fingerprint,
+            num_voles,
+        }
+    }
+
+    fn write<C: AbstractChannel>(&self, channel: &mut C) -> Result<(), Error> {
+        channel.write_bytes(&CACHE_FILE_MAGIC)?;
+        channel.send(self.version)?;
+        channel.send(&[match self.role {
+            CacheRole::Prover => 0u8,
+            CacheRole::Verifier => 1u8,
+        }])?;
+        channel.send(&[match self.ring_kind {
+            RingKind::Z2k => 0u8,
+            RingKind::PrimeField => 1u8,
+        }])?;
+        channel.send(self.bit_length)?;
+        channel.send(self.element_size)?;
+        channel.send(&self.delta_fingerprint)?;
+        channel.send(self.num_voles)?;
+        Ok(())
+    }
+
+    fn read<C: AbstractChannel>(channel: &mut C) -> Result<Self, Error> {
+        let magic: [u8; 8] = channel.receive()?;
+        if magic != CACHE_FILE_MAGIC {
+            return Err(Error::Other("not a VOLE cache file".to_string()));
+        }
+        let version: u32 = channel.receive()?;
+        if version != CACHE_FILE_VERSION {
+            return Err(Error::Other(format!(
+                "unsupported cache file version {} (expected {})",
+                version, CACHE_FILE_VERSION
+            )));
+        }
+        let role = match channel.receive::<[u8; 1]>()?[0] {
+            0 => CacheRole::Prover,
+            1 => CacheRole::Verifier,
+            r => return Err(Error::Other(format!("invalid role {} in cache file", r))),
+        };
+        let ring_kind = match channel.receive::<[u8; 1]>()?[0] {
+            0 => RingKind::Z2k,
+            1 => RingKind::PrimeField,
+            k => {
+                return Err(Error::Other(format!(
+                    "invalid ring kind {} in cache file",
+                    k
+                )))
+            }
+        };
+        Ok(Self {
+            version,
+            role,
+            ring_kind,
+            bit_length: channel.receive()?,
+            element_size: channel.receive()?,
+            delta_fingerprint: channel.receive()?,
+            num_voles: channel.receive()?,
+        })
+    }
+
+    fn check<RingT: Ring>(&self, role: CacheRole, delta_fingerprint: [u8; 32]) -> Result<(), Error>
+    where
+        Standard: Distribution<RingT>,
+    {
+        if self.role != role {
+            return Err(Error::Other(format!(
+                "cache file was written by the {}, but is loaded by the {}",
+                self.role, role
+            )));
+        }
+        let expected = Self::new::<RingT>(role, delta_fingerprint, self.num_voles);
+        if self.ring_kind != expected.ring_kind
+            || self.bit_length != expected.bit_length
+            || self.element_size != expected.element_size
+        {
+            return Err(Error::Other(format!(
+                "cache file contains elements of a {}-bit {}, but a {}-bit {} was expected",
+                self.bit_length, self.ring_kind, expected.bit_length, expected.ring_kind
+            )));
+        }
+        if self.delta_fingerprint != expected.delta_fingerprint {
+            return Err(Error::Other(
+                "cache file was generated for a different delta".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // make sure that the file contains exactly the announced number of values, before
+    // allocating memory for them
+    fn check_file_size(&self, path: &Path) -> Result<(), Error> {
+        let values_per_vole = match self.role {
+            CacheRole::Prover => 2,
+            CacheRole::Verifier => 1,
+        };
+        let expected_size = (self.num_voles as u64)
+            .checked_mul(values_per_vole * self.element_size as u64)
+            .and_then(|size| size.checked_add(CACHE_FILE_HEADER_SIZE));
+        if expected_size != Some(fs::metadata(path)?.len()) {
+            return Err(Error::Other(format!(
+                "cache file size does not match the {} VOLEs announced in its header",
+                self.num_voles
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Fingerprint of the verifier's `delta` stored in the cache file.
+pub fn delta_fingerprint<RingT: Ring>(delta: RingT) -> [u8; 32]
+where
+    Standard: Distribution<RingT>,
+{
+    let mut hasher = Sha256::new();
+    hasher.update(b"mozzarella delta fingerprint");
+    hasher.update((RingT::BIT_LENGTH as u64).to_le_bytes());
+    hasher.update(&delta.reduce().as_ref()[..RingT::BYTE_LENGTH]);
+    hasher.finalize().into()
+}
+
+fn file_writer(path: &Path) -> Result<Channel<io::Empty, BufWriter<File>>, Error> {
+    Ok(Channel::new(
+        io::empty(),
+        BufWriter::new(File::create(path)?),
+    ))
+}
+
+fn file_reader(path: &Path) -> Result<Channel<BufReader<File>, io::Sink>, Error> {
+    Ok(Channel::new(BufReader::new(File::open(path)?), io::sink()))
+}
+
+/// Read only the header of a cache file.
+pub fn read_header<P: AsRef<Path>>(path: P) -> Result<CacheFileHeader, Error> {
+    CacheFileHeader::read(&mut file_reader(path.as_ref())?)
+}
+
+impl<RingT> CachedProver<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut channel = file_writer(path.as_ref())?;
+        let (u, w) = self.as_slices();
+        CacheFileHeader::new::<RingT>(CacheRole::Prover, [0u8; 32], u.len()).write(&mut channel)?;
+        channel.send(u)?;
+        channel.send(w)?;
+        channel.flush()?;
+        Ok(())
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut channel = file_reader(path.as_ref())?;
+        let header = CacheFileHeader::read(&mut channel)?;
+        header.check::<RingT>(CacheRole::Prover, [0u8; 32])?;
+        header.check_file_size(path.as_ref())?;
+        let u = channel.receive_n(header.num_voles)?;
+        let w = channel.receive_n(header.num_voles)?;
+        Ok(Self::init(u, w))
+    }
+}
+
+impl<RingT> CachedVerifier<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P, delta: RingT) -> Result<(), Error> {
+        let mut channel = file_writer(path.as_ref())?;
+        let v = self.as_slice();
+        CacheFileHeader::new::<RingT>(CacheRole::Verifier, delta_fingerprint(delta), v.len())
+            .write(&mut channel)?;
+        channel.send(v)?;
+        channel.flush()?;
+        Ok(())
+    }
+
+    /// Load a cache and check that it was generated for the given `delta`.
+    pub fn load_from_file<P: AsRef<Path>>(path: P, delta: RingT) -> Result<Self, Error> {
+        let mut channel = file_reader(path.as_ref())?;
+        let header = CacheFileHeader::read(&mut channel)?;
+        header.check::<RingT>(CacheRole::Verifier, delta_fingerprint(delta))?;
+        header.check_file_size(path.as_ref())?;
+        Ok(Self::init(channel.receive_n(header.num_voles)?))
+    }
+}
@@ -16,16 +16,52 @@ impl<T: Copy + Clone> CachedProver<T> {
         )
     }
 
+    // like `get`, but moves the values into the given buffers
+    pub fn get_into(&mut self, u_out: &mut [T], w_out: &mut [T]) {
+        debug_assert_eq!(u_out.len(), w_out.len());
+        let new_len = self.u.len() - u_out.len();
+        u_out.copy_from_slice(&self.u[new_len..]);
+        w_out.copy_from_slice(&self.w[new_len..]);
+        self.u.truncate(new_len);
+        self.w.truncate(new_len);
+    }
+
     pub fn pop(&mut self) -> (T, T) {
         let u = self.u.pop();
         let w = self.w.pop();
         (u.unwrap(), w.unwrap())
     }
 
+    // drop all but the first `len` values
+    pub fn truncate(&mut self, len: usize) {
+        self.u.truncate(len);
+        self.w.truncate(len);
+    }
+
     pub fn capacity(&self) -> usize {
         self.u.len()
     }
 
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        (&self.u, &self.w)
+    }
+
+    // split the cache into `n` caches of (almost) equal size
+    pub fn partition(mut self, n: usize) -> Vec<Self> {
+        assert!(n > 0);
+        let len = self.u.len();
+        let mut parts: Vec<Self> = (0..n)
+            .rev()
+            .map(|i| {
+                let part_len = len / n + if i < len % n { 1 } else { 0 };
+                let (u, w) = self.get(part_len);
+                Self::init(u, w)
+            })
+            .collect();
+        parts.reverse();
+        parts
+    }
+
     pub fn append<I1: Iterator<Item = T>, I2: Iterator<Item = T>>(&mut self, u: I1, w: I2) {
         self.u.extend(u);
         self.w.extend(w);
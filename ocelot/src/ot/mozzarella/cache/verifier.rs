@@ -12,6 +12,18 @@ impl<T: Copy + Clone> CachedVerifier<T> {
         self.v.extend(v);
     }
 
+    // split the cache into `n` caches of (almost) equal size
+    pub fn partition(mut self, n: usize) -> Vec<Self> {
+        assert!(n > 0);
+        let len = self.v.len();
+        let mut parts: Vec<Self> = (0..n)
+            .rev()
+            .map(|i| Self::init(self.get(len / n + if i < len % n { 1 } else { 0 })))
+            .collect();
+        parts.reverse();
+        parts
+    }
+
     pub fn pop(&mut self) -> T {
         self.v.pop().unwrap()
     }
@@ -20,7 +32,23 @@ impl<T: Copy + Clone> CachedVerifier<T> {
         self.v.split_off(self.v.len() - amount)
     }
 
+    // like `get`, but moves the values into the given buffer
+    pub fn get_into(&mut self, out: &mut [T]) {
+        let new_len = self.v.len() - out.len();
+        out.copy_from_slice(&self.v[new_len..]);
+        self.v.truncate(new_len);
+    }
+
+    // drop all but the first `len` values
+    pub fn truncate(&mut self, len: usize) {
+        self.v.truncate(len);
+    }
+
     pub fn capacity(&self) -> usize {
         self.v.len()
     }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.v
+    }
 }
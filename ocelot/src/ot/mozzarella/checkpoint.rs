@@ -0,0 +1,145 @@
+//! Checkpoints to continue a session on a new connection after the old one dropped in the middle
+//! of an extension (see `Prover::resume` and `Verifier::resume`).
+//!
+//! VOLEs are only taken from the end of the base VOLE cache, and new ones are only appended at
+//! the end.  Hence, the part of the cache below its length at the start of an iteration, minus
+//! the base VOLEs that iteration uses, stays untouched until the next iteration starts.  Instead
+//! of copies of the cache, a party records this length whenever an iteration starts.  Since the
+//! connection might drop while one party has already completed an iteration that the other has
+//! not, the last two lengths are kept.  On reconnection, both parties agree on the last iteration
+//! that both of them completed and cut the cache back to the start of the next iteration, minus
+//! the base VOLEs it might have used.  These are discarded in any case, since reusing them with
+//! fresh noise would leak the noise.  For the same reason, the OT extension continues with fresh
+//! PRGs derived for a new epoch.
+use crate::{ot::mozzarella::handshake::SessionParameters, Error};
+use scuttlebutt::AbstractChannel;
+
+#[derive(Clone, Default)]
+pub(crate) struct Checkpoints {
+    // (number of completed iterations, cache length when the next iteration started), the
+    // latest one is last
+    entries: Vec<(u64, usize)>,
+}
+
+impl Checkpoints {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Record that an iteration starts after `iteration` completed ones with a cache of
+    /// `cache_len` VOLEs.
+    pub fn start_iteration(&mut self, iteration: u64, cache_len: usize) {
+        self.entries.retain(|(i, _)| *i != iteration);
+        if self.entries.len() == 2 {
+            self.entries.remove(0);
+        }
+        self.entries.push((iteration, cache_len));
+    }
+
+    /// Length to which the cache is cut back when resuming after `iteration` completed
+    /// iterations, where each iteration uses `required` base VOLEs.  `completed` is the number
+    /// of iterations this party completed, and `cache_len` the current length of its cache.
+    pub fn resume_len(
+        &self,
+        iteration: u64,
+        completed: u64,
+        cache_len: usize,
+        required: usize,
+    ) -> Result<usize, Error> {
+        let start_len = match self.entries.iter().find(|(i, _)| *i == iteration) {
+            Some(&(_, start_len)) => start_len,
+            // the next iteration has not been started yet
+            None if iteration == completed => cache_len,
+            None => {
+                return Err(Error::Other(format!(
+                    "no checkpoint after iteration {}",
+                    iteration
+                )))
+            }
+        };
+        match start_len.checked_sub(required) {
+            // VOLEs below this length have been used since
+            Some(len) if len <= cache_len => Ok(len),
+            _ => Err(Error::Other(format!(
+                "cannot resume: the cache changed since iteration {}",
+                iteration
+            ))),
+        }
+    }
+}
+
+/// Agree with the other party on the last iteration that both completed and on a fresh epoch for
+/// the OT extension.
+pub(crate) fn agree<C: AbstractChannel>(
+    channel: &mut C,
+    parameters: &SessionParameters,
+    iteration: u64,
+    epoch: u64,
+) -> Result<(u64, u64), Error> {
+    parameters.exchange(channel)?;
+    channel.send(iteration)?;
+    channel.send(epoch)?;
+    channel.flush()?;
+    let other_iteration: u64 = channel.receive()?;
+    let other_epoch: u64 = channel.receive()?;
+    if iteration.max(other_iteration) - iteration.min(other_iteration) > 1 {
+        return Err(Error::Other(format!(
+            "cannot resume: completed {} iterations, but the other party completed {}",
+            iteration, other_iteration
+        )));
+    }
+    Ok((iteration.min(other_iteration), epoch.max(other_epoch) + 1))
+}
+
+/// Agree with the other party on the length to which both cut back their caches, such that the
+/// caches stay aligned.  A party that cannot resume sends zero, such that the other one fails as
+/// well instead of waiting.
+pub(crate) fn agree_cache_len<C: AbstractChannel>(
+    channel: &mut C,
+    cache_len: Result<usize, Error>,
+) -> Result<usize, Error> {
+    channel.send(*cache_len.as_ref().unwrap_or(&0))?;
+    channel.flush()?;
+    let other_cache_len: usize = channel.receive()?;
+    Ok(cache_len?.min(other_cache_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{agree, Checkpoints};
+    use crate::ot::mozzarella::handshake::SessionParameters;
+    use scuttlebutt::unix_channel_pair;
+    use std::thread::spawn;
+
+    #[test]
+    fn test_checkpoints() {
+        let mut checkpoints = Checkpoints::new();
+        checkpoints.start_iteration(0, 100);
+        checkpoints.start_iteration(1, 90);
+        checkpoints.start_iteration(2, 80);
+        assert!(checkpoints.resume_len(0, 1, 85, 10).is_err());
+        assert_eq!(checkpoints.resume_len(1, 2, 85, 10).unwrap(), 80);
+        assert_eq!(checkpoints.resume_len(2, 2, 75, 10).unwrap(), 70);
+        // VOLEs below the start of the iteration minus the required ones have been used
+        assert!(checkpoints.resume_len(2, 2, 65, 10).is_err());
+        // the next iteration has not been started
+        assert_eq!(checkpoints.resume_len(3, 3, 75, 10).unwrap(), 65);
+        assert!(checkpoints.resume_len(3, 4, 75, 10).is_err());
+    }
+
+    #[test]
+    fn test_agree() {
+        let params = SessionParameters::new("test");
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let params_p = params.clone();
+        let handle = spawn(move || agree(&mut channel_p, &params_p, 5, 1));
+        assert_eq!(agree(&mut channel_v, &params, 4, 0).unwrap(), (4, 2));
+        assert_eq!(handle.join().unwrap().unwrap(), (4, 2));
+
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let params_p = params.clone();
+        let handle = spawn(move || agree(&mut channel_p, &params_p, 6, 0));
+        assert!(agree(&mut channel_v, &params, 4, 0).is_err());
+        assert!(handle.join().unwrap().is_err());
+    }
+}
@@ -0,0 +1,121 @@
+//! Half-tree expansion (cf. Guo et al., <https://eprint.iacr.org/2022/1431>) as a replacement
+//! for the GGM trees in `ggm::prover` and `ggm::verifier`.
+//!
+//! The verifier uses correlated OTs `(q, q ^ delta)` with the global `delta` of the OT sender.
+//! The two nodes of the first level sum to `delta`, and every node `s` is expanded into
+//! `(H(s), s ^ H(s))` with a single call to a circular correlation robust hash `H`.  Hence, every
+//! level sums to `delta` as well, and per level the verifier only sends the sum of all left
+//! children masked with the OT message `q`.  The prover obtains the sum of the left or right
+//! children by choosing the corresponding bit in the OT.
+//!
+//! Every hash call is tweaked with the iteration, the index of the tree, and the level, such that
+//! no two calls share a tweak.
+//!
+//! All leaves are correlated with `delta`, so they are hashed once more before they are turned
+//! into output blocks and check values as in the GGM trees.  The consistency check is the same.
+//!
+//! Trees whose output size is not a power of two are pruned: only nodes with leaves below them
+//! are expanded.  The right child of the last expanded node of a level may have no leaves below
+//! it.  It is not stored, but it still counts towards the level sums.
+pub mod prover;
+pub mod verifier;
+
+use scuttlebutt::{AesHash, Block};
+
+// key of the hash function that is used to break the correlation of the leaves
+const LEAF_HASH_KEY: u128 = 1;
+
+fn leaf_hash() -> AesHash {
+    AesHash::new(Block::from(LEAF_HASH_KEY))
+}
+
+// tweak of the hash function, which is unique for every iteration, tree, and level
+#[inline(always)]
+fn tweak(iteration: u64, tree_index: usize, level: usize) -> Block {
+    Block::from(((iteration as u128) << 64) | ((tree_index as u128) << 32) | level as u128)
+}
+
+// expand a node into its left and right child
+#[inline(always)]
+fn expand(hash: &AesHash, seed: Block, tweak: Block) -> (Block, Block) {
+    let left = hash.ccr_hash(tweak, seed);
+    (left, left ^ seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prover::BatchedProver, verifier::BatchedVerifier};
+    use crate::ot::{
+        FixedKeyInitializer, KosDeltaReceiver, KosDeltaSender, Receiver as OtReceiver,
+    };
+    use rand::{rngs::OsRng, Rng};
+    use scuttlebutt::{unix_channel_pair, Block};
+    use std::thread::spawn;
+
+    fn test_half_tree(output_size: usize) {
+        const NUM_INSTANCES: usize = 5;
+        const NUM_ITERATIONS: usize = 3;
+
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let mut rng = OsRng;
+        let ot_key: [u8; 16] = rng.gen();
+        let mut tree_prover = BatchedProver::new_with_output_size(NUM_INSTANCES, output_size);
+        let mut tree_verifier = BatchedVerifier::new_with_output_size(NUM_INSTANCES, output_size);
+        tree_verifier.set_ot_delta(Block::from(ot_key));
+        let alpha_s: [usize; NUM_INSTANCES] = [
+            rng.gen_range(0, output_size),
+            output_size / 2,
+            0,
+            output_size - 1,
+            output_size - 2,
+        ];
+
+        let prover_thread = spawn(move || {
+            let mut ot_receiver = KosDeltaReceiver::init(&mut channel_p, &mut OsRng).unwrap();
+            let mut prover_values = Vec::new();
+            for _ in 0..NUM_ITERATIONS {
+                tree_prover
+                    .gen_eval(&mut channel_p, &mut ot_receiver, &alpha_s)
+                    .unwrap();
+                prover_values.push(tree_prover.get_output_blocks().to_vec());
+            }
+            prover_values
+        });
+        let verifier_thread = spawn(move || {
+            let mut ot_sender =
+                KosDeltaSender::init_fixed_key(&mut channel_v, ot_key, &mut OsRng).unwrap();
+            let mut verifier_values = Vec::new();
+            for _ in 0..NUM_ITERATIONS {
+                tree_verifier
+                    .gen_tree(&mut channel_v, &mut ot_sender)
+                    .unwrap();
+                verifier_values.push(tree_verifier.get_output_blocks().to_vec());
+            }
+            verifier_values
+        });
+
+        let prover_values = prover_thread.join().unwrap();
+        let verifier_values = verifier_thread.join().unwrap();
+        for k in 0..NUM_ITERATIONS {
+            assert_eq!(prover_values[k].len(), NUM_INSTANCES * output_size);
+            assert_eq!(verifier_values[k].len(), NUM_INSTANCES * output_size);
+            for tree_j in 0..NUM_INSTANCES {
+                for i in 0..output_size {
+                    let index = tree_j * output_size + i;
+                    if i == alpha_s[tree_j] {
+                        assert_ne!(prover_values[k][index], verifier_values[k][index]);
+                    } else {
+                        assert_eq!(prover_values[k][index], verifier_values[k][index]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_batched_half_tree() {
+        for &output_size in &[2, 3, 256, 257, 300, 511] {
+            test_half_tree(output_size);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{
+    errors::Error,
+    ot::{
+        mozzarella::{
+            ggm::{
+                half_tree::{expand, leaf_hash, tweak},
+                prover::BatchedProver as GgmProver,
+                tree_height,
+            },
+            utils::{prg2, unpack_bits_into},
+        },
+        CorrelatedReceiver, RandomReceiver, Receiver as OtReceiver,
+    },
+};
+use rand::Rng;
+use rayon::prelude::*;
+use scuttlebutt::{AbstractChannel, AesHash, AesRng, Block, F128};
+
+pub struct BatchedProver {
+    num_instances: usize,
+    tree_height: usize,
+    output_size: usize,
+    rng: AesRng,
+    // number of times the trees have been generated, used in the hash tweaks
+    iteration: u64,
+    alpha_s: Vec<usize>,
+    alpha_bits_s: Vec<bool>,
+    layer_keys_s: Vec<Block>,
+    masked_sum_s: Vec<Block>,
+    final_key_s: Vec<Block>,
+    final_layer_blocks_s: Vec<Block>,
+    final_layer_check_values_s: Vec<F128>,
+    challenge_seed_s: Vec<Block>,
+    challenge_hash_s: Vec<F128>,
+}
+
+impl BatchedProver {
+    pub fn new(num_instances: usize, tree_height: usize) -> Self {
+        Self::new_with_output_size(num_instances, 1 << tree_height)
+    }
+
+    pub fn new_with_output_size(num_instances: usize, output_size: usize) -> Self {
+        let tree_height = tree_height(output_size);
+        Self {
+            num_instances,
+            tree_height,
+            output_size,
+            rng: AesRng::new(),
+            iteration: 0,
+            alpha_s: vec![0usize; num_instances],
+            alpha_bits_s: vec![false; num_instances * tree_height],
+            layer_keys_s: vec![Default::default(); num_instances * tree_height],
+            masked_sum_s: vec![Default::default(); num_instances * tree_height],
+            final_key_s: vec![Default::default(); num_instances],
+            final_layer_blocks_s: vec![Default::default(); num_instances * output_size],
+            final_layer_check_values_s: vec![Default::default(); num_instances * output_size],
+            challenge_seed_s: vec![Default::default(); num_instances],
+            challenge_hash_s: vec![Default::default(); num_instances],
+        }
+    }
+
+    pub fn get_output_blocks(&self) -> &[Block] {
+        self.final_layer_blocks_s.as_slice()
+    }
+
+    /// Receive the masked sums of the left children.  `ot_receiver` needs to output the raw
+    /// correlation `q ^ b * delta`, as `KosDeltaReceiver::receive_random` does.
+    pub fn receive_layer_keys<
+        C: AbstractChannel,
+        OT: OtReceiver<Msg = Block> + CorrelatedReceiver + RandomReceiver,
+    >(
+        &mut self,
+        channel: &mut C,
+        ot_receiver: &mut OT,
+        alpha_s: &[usize],
+    ) -> Result<(), Error> {
+        assert_eq!(alpha_s.len(), self.num_instances);
+        self.alpha_s.copy_from_slice(alpha_s);
+        for (tree_i, &alpha) in alpha_s.iter().enumerate() {
+            assert!(alpha < self.output_size);
+            unpack_bits_into(
+                alpha,
+                &mut self.alpha_bits_s[tree_i * self.tree_height..(tree_i + 1) * self.tree_height],
+            );
+        }
+        // on each level we obtain the sum of the children on the other side of the path
+        let ot_input: Vec<bool> = self.alpha_bits_s.iter().map(|x| !x).collect();
+        self.layer_keys_s = ot_receiver.receive_random(channel, &ot_input, &mut self.rng)?;
+        channel.receive_into(self.masked_sum_s.as_mut_slice())?;
+        Ok(())
+    }
+
+    pub fn receive_final_key<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        channel.receive_into(self.final_key_s.as_mut_slice())?;
+        Ok(())
+    }
+
+    pub fn receive<
+        C: AbstractChannel,
+        OT: OtReceiver<Msg = Block> + CorrelatedReceiver + RandomReceiver,
+    >(
+        &mut self,
+        channel: &mut C,
+        ot_receiver: &mut OT,
+        alpha_s: &[usize],
+    ) -> Result<(), Error> {
+        self.receive_layer_keys(channel, ot_receiver, alpha_s)?;
+        self.receive_final_key(channel)?;
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn eval_helper(
+        output_size: usize,
+        tree_height: usize,
+        iteration: u64,
+        tree_index: usize,
+        hash: &AesHash,
+        leaf_hash: &AesHash,
+        alpha: usize,
+        alpha_bits: &[bool],
+        final_layer_blocks: &mut [Block],
+        final_layer_check_values: &mut [F128],
+        layer_keys: &[Block],
+        masked_sums: &[Block],
+        final_key: &Block,
+    ) {
+        assert_eq!(alpha_bits.len(), tree_height);
+        assert_eq!(final_layer_blocks.len(), output_size);
+        assert_eq!(final_layer_check_values.len(), output_size);
+        assert_eq!(layer_keys.len(), tree_height);
+        assert_eq!(masked_sums.len(), tree_height);
+
+        let last_index = output_size - 1;
+
+        // on the first level, we obtain the neighbor of the path directly
+        final_layer_blocks[(alpha >> (tree_height - 1)) ^ 1] = layer_keys[0] ^ masked_sums[0];
+
+        // sum of the pruned nodes in all previous levels, which is missing from the level sums
+        let mut pruned_sum = Block::default();
+
+        // iterate over the tree layer by layer
+        for i in 1..tree_height {
+            let last_parent = last_index >> (tree_height - i);
+            let last_child = last_index >> (tree_height - i - 1);
+            let path_index = alpha >> (tree_height - i);
+            // sum of all children on the neighbor's side, where the parents sum to delta ^
+            // pruned_sum
+            let mut neighbor_sum = layer_keys[i] ^ masked_sums[i];
+            if !alpha_bits[i] {
+                neighbor_sum ^= pruned_sum;
+            }
+            // expand each node in this layer;
+            // we need to iterate from right to left, since we reuse the same buffer
+            for j in (0..=last_parent).rev() {
+                // skip the punctured path
+                if j == path_index {
+                    continue;
+                }
+                let (s0, s1) = expand(hash, final_layer_blocks[j], tweak(iteration, tree_index, i));
+                neighbor_sum ^= if alpha_bits[i] { s0 } else { s1 };
+                final_layer_blocks[2 * j] = s0;
+                if 2 * j < last_child {
+                    final_layer_blocks[2 * j + 1] = s1;
+                } else {
+                    pruned_sum ^= s1;
+                }
+            }
+            // store the neighbor of the node on the punctured path
+            let neighbor_index = (alpha >> (tree_height - i - 1)) ^ 1;
+            if neighbor_index <= last_child {
+                final_layer_blocks[neighbor_index] = neighbor_sum;
+            } else {
+                pruned_sum ^= neighbor_sum;
+            }
+        }
+
+        // compute the actual outputs and the checking values in the final layer
+        let mut last_layer_key = Block::default(); // key for decrypting the check value at index alpha
+        for j in 0..output_size {
+            if j == alpha {
+                continue;
+            }
+            let (s0, s1) = prg2(
+                hash,
+                leaf_hash.ccr_hash(
+                    tweak(iteration, tree_index, tree_height),
+                    final_layer_blocks[j],
+                ),
+            );
+            last_layer_key ^= s1;
+            final_layer_blocks[j] = s0;
+            final_layer_check_values[j] = F128::from(s1);
+        }
+        final_layer_blocks[alpha] = Block::default();
+        // decrypt the check value at index alpha
+        final_layer_check_values[alpha] = F128::from(last_layer_key ^ *final_key);
+    }
+
+    pub fn eval(&mut self) {
+        let output_size = self.output_size;
+        let tree_height = self.tree_height;
+        let iteration = self.iteration;
+        self.iteration += 1;
+        let hash = AesHash::new(Default::default());
+        let leaf_hash = leaf_hash();
+        (
+            self.alpha_s.par_iter(),
+            self.alpha_bits_s.par_chunks_exact(self.tree_height),
+            self.final_layer_blocks_s
+                .par_chunks_exact_mut(self.output_size),
+            self.final_layer_check_values_s
+                .par_chunks_exact_mut(self.output_size),
+            self.layer_keys_s.par_chunks_exact(self.tree_height),
+            self.masked_sum_s.par_chunks_exact(self.tree_height),
+            self.final_key_s.par_iter(),
+        )
+            .into_par_iter()
+            .enumerate()
+            .for_each(
+                |(
+                    tree_index,
+                    (
+                        &alpha,
+                        alpha_bits,
+                        final_layer_blocks,
+                        final_layer_check_values,
+                        layer_keys,
+                        masked_sums,
+                        final_key,
+                    ),
+                )| {
+                    Self::eval_helper(
+                        output_size,
+                        tree_height,
+                        iteration,
+                        tree_index,
+                        &hash,
+                        &leaf_hash,
+                        alpha,
+                        alpha_bits,
+                        final_layer_blocks,
+                        final_layer_check_values,
+                        layer_keys,
+                        masked_sums,
+                        final_key,
+                    );
+                },
+            );
+    }
+
+    pub fn send_challenge<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        for cs_i in self.challenge_seed_s.iter_mut() {
+            *cs_i = self.rng.gen();
+        }
+        // send a seed from which all the changes are derived
+        channel.send(self.challenge_seed_s.as_slice())?;
+        Ok(())
+    }
+
+    pub fn compute_hash(&mut self) {
+        (
+            self.challenge_seed_s.par_iter(),
+            self.final_layer_check_values_s
+                .par_chunks_exact(self.output_size),
+            self.challenge_hash_s.par_iter_mut(),
+        )
+            .into_par_iter()
+            .for_each(|(challenge_seed, final_layer_keys, challenge_hash)| {
+                *challenge_hash = GgmProver::compute_hash_helper(challenge_seed, final_layer_keys);
+            });
+    }
+
+    pub fn receive_response_and_check<C: AbstractChannel>(&self, channel: &mut C) -> bool {
+        let mut capital_gamma_prime_s = vec![F128::default(); self.num_instances];
+        match channel.receive_into(capital_gamma_prime_s.as_mut_slice()) {
+            Ok(()) => self.challenge_hash_s == capital_gamma_prime_s,
+            Err(_) => false,
+        }
+    }
+
+    pub fn gen_eval<
+        C: AbstractChannel,
+        OT: OtReceiver<Msg = Block> + CorrelatedReceiver + RandomReceiver,
+    >(
+        &mut self,
+        channel: &mut C,
+        ot_receiver: &mut OT,
+        alpha_s: &[usize],
+    ) -> Result<(), Error> {
+        self.receive(channel, ot_receiver, alpha_s)?;
+        self.eval();
+        self.send_challenge(channel)?;
+        self.compute_hash();
+
+        if self.receive_response_and_check(channel) {
+            Ok(())
+        } else {
+            Err(Error::Other("THE GAMMAS WERE NOT EQUAL!".to_string()))
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use crate::{
+    errors::Error,
+    ot::{
+        mozzarella::{
+            ggm::{
+                half_tree::{expand, leaf_hash, tweak},
+                tree_height,
+                verifier::BatchedVerifier as GgmVerifier,
+            },
+            utils::prg2,
+        },
+        CorrelatedSender, RandomSender, Sender as OtSender,
+    },
+};
+use rand::Rng;
+use rayon::prelude::*;
+use scuttlebutt::{AbstractChannel, AesHash, AesRng, Block, F128};
+
+pub struct BatchedVerifier {
+    num_instances: usize,
+    tree_height: usize,
+    output_size: usize,
+    rng: AesRng,
+    // number of times the trees have been generated, used in the hash tweaks
+    iteration: u64,
+    // global delta of the correlated OTs
+    ot_delta: Option<Block>,
+    left_sum_s: Vec<Block>,
+    final_layer_check_values_s: Vec<F128>,
+    final_layer_blocks_s: Vec<Block>,
+    final_key_s: Vec<Block>,
+    challenge_seed_s: Vec<Block>,
+    challenge_response_s: Vec<F128>,
+}
+
+impl BatchedVerifier {
+    pub fn new(num_instances: usize, tree_height: usize) -> Self {
+        Self::new_with_output_size(num_instances, 1 << tree_height)
+    }
+
+    pub fn new_with_output_size(num_instances: usize, output_size: usize) -> Self {
+        let tree_height = tree_height(output_size);
+        Self {
+            num_instances,
+            tree_height,
+            output_size,
+            rng: AesRng::new(),
+            iteration: 0,
+            ot_delta: None,
+            left_sum_s: vec![Default::default(); num_instances * tree_height],
+            final_layer_check_values_s: vec![Default::default(); num_instances * output_size],
+            final_layer_blocks_s: vec![Default::default(); num_instances * output_size],
+            final_key_s: vec![Default::default(); num_instances],
+            challenge_seed_s: vec![Default::default(); num_instances],
+            challenge_response_s: vec![Default::default(); num_instances],
+        }
+    }
+
+    /// Set the global delta of the OT sender, which is needed to generate the trees.
+    pub fn set_ot_delta(&mut self, ot_delta: Block) {
+        self.ot_delta = Some(ot_delta);
+    }
+
+    pub fn get_output_blocks(&self) -> &[Block] {
+        self.final_layer_blocks_s.as_slice()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn gen_helper(
+        output_size: usize,
+        tree_height: usize,
+        iteration: u64,
+        tree_index: usize,
+        hash: &AesHash,
+        leaf_hash: &AesHash,
+        ot_delta: Block,
+        final_layer_blocks: &mut [Block],
+        final_layer_check_values: &mut [F128],
+        left_sums: &mut [Block],
+        final_key: &mut Block,
+    ) {
+        assert_eq!(final_layer_blocks.len(), output_size);
+        assert_eq!(final_layer_check_values.len(), output_size);
+        assert_eq!(left_sums.len(), tree_height);
+
+        let last_index = output_size - 1;
+
+        // the first level consists of the sampled seed and its complement
+        left_sums[0] = final_layer_blocks[0];
+        final_layer_blocks[1] = final_layer_blocks[0] ^ ot_delta;
+
+        // iterate over the tree layer by layer
+        for (i, left_sum) in left_sums.iter_mut().enumerate().skip(1) {
+            let last_parent = last_index >> (tree_height - i);
+            let last_child = last_index >> (tree_height - i - 1);
+            *left_sum = Block::default();
+            // we need to iterate from right to left, since we reuse the same buffer
+            for j in (0..=last_parent).rev() {
+                let (s0, s1) = expand(hash, final_layer_blocks[j], tweak(iteration, tree_index, i));
+                *left_sum ^= s0;
+                final_layer_blocks[2 * j] = s0;
+                // the right child of the last node might be pruned
+                if 2 * j < last_child {
+                    final_layer_blocks[2 * j + 1] = s1;
+                }
+            }
+        }
+
+        *final_key = Block::default();
+        // compute the actual outputs and the checking values in the final layer
+        for j in 0..output_size {
+            let (s0, s1) = prg2(
+                hash,
+                leaf_hash.ccr_hash(
+                    tweak(iteration, tree_index, tree_height),
+                    final_layer_blocks[j],
+                ),
+            );
+            *final_key ^= s1;
+            final_layer_blocks[j] = s0;
+            final_layer_check_values[j] = s1.into();
+        }
+    }
+
+    pub fn gen(&mut self) {
+        let ot_delta = self
+            .ot_delta
+            .expect("the OT delta needs to be set before generating trees");
+        for tree_i in 0..self.num_instances {
+            // sample seeds
+            self.final_layer_blocks_s[tree_i * self.output_size] = self.rng.gen();
+        }
+        let output_size = self.output_size;
+        let tree_height = self.tree_height;
+        let iteration = self.iteration;
+        self.iteration += 1;
+        let hash = AesHash::new(Default::default());
+        let leaf_hash = leaf_hash();
+        (
+            self.final_layer_blocks_s
+                .par_chunks_exact_mut(self.output_size),
+            self.final_layer_check_values_s
+                .par_chunks_exact_mut(self.output_size),
+            self.left_sum_s.par_chunks_exact_mut(self.tree_height),
+            self.final_key_s.par_iter_mut(),
+        )
+            .into_par_iter()
+            .enumerate()
+            .for_each(
+                |(
+                    tree_index,
+                    (final_layer_blocks, final_layer_check_values, left_sums, final_key),
+                )| {
+                    Self::gen_helper(
+                        output_size,
+                        tree_height,
+                        iteration,
+                        tree_index,
+                        &hash,
+                        &leaf_hash,
+                        ot_delta,
+                        final_layer_blocks,
+                        final_layer_check_values,
+                        left_sums,
+                        final_key,
+                    );
+                },
+            );
+    }
+
+    /// Send the sums of the left children masked with correlated OTs.  `ot_sender` needs to
+    /// output the raw correlation `(q, q ^ delta)`, as `KosDeltaSender::send_random` does.
+    pub fn send_layer_keys<
+        C: AbstractChannel,
+        OT: OtSender<Msg = Block> + CorrelatedSender + RandomSender,
+    >(
+        &mut self,
+        channel: &mut C,
+        ot_sender: &mut OT,
+    ) -> Result<(), Error> {
+        let ot_delta = self.ot_delta.unwrap();
+        let cots = ot_sender.send_random(channel, self.left_sum_s.len(), &mut self.rng)?;
+        if cots.iter().any(|&(q0, q1)| q0 ^ q1 != ot_delta) {
+            return Err(Error::Other(
+                "half-tree expansion requires correlated OTs with the global delta".to_string(),
+            ));
+        }
+        let masked_sums: Vec<Block> = cots
+            .iter()
+            .zip(self.left_sum_s.iter())
+            .map(|(&(q0, _), &left_sum)| q0 ^ left_sum)
+            .collect();
+        channel.send(masked_sums.as_slice())?;
+        Ok(())
+    }
+
+    pub fn send_final_key<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        channel.send(self.final_key_s.as_slice())?;
+        Ok(())
+    }
+
+    pub fn send<C: AbstractChannel, OT: OtSender<Msg = Block> + CorrelatedSender + RandomSender>(
+        &mut self,
+        channel: &mut C,
+        ot_sender: &mut OT,
+    ) -> Result<(), Error> {
+        self.send_layer_keys(channel, ot_sender)?;
+        self.send_final_key(channel)?;
+        Ok(())
+    }
+
+    pub fn receive_challenge<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        channel.receive_into(self.challenge_seed_s.as_mut_slice())?;
+        Ok(())
+    }
+
+    pub fn compute_response(&mut self) {
+        (
+            self.challenge_seed_s.par_iter(),
+            self.final_layer_check_values_s
+                .par_chunks_exact(self.output_size),
+            self.challenge_response_s.par_iter_mut(),
+        )
+            .into_par_iter()
+            .for_each(|(challenge_seed, final_layer_keys, challenge_response)| {
+                *challenge_response =
+                    GgmVerifier::compute_response_helper(challenge_seed, final_layer_keys);
+            });
+    }
+
+    pub fn send_response<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        channel.send(self.challenge_response_s.as_slice())?;
+        Ok(())
+    }
+
+    pub fn gen_tree<
+        C: AbstractChannel,
+        OT: OtSender<Msg = Block> + CorrelatedSender + RandomSender,
+    >(
+        &mut self,
+        channel: &mut C,
+        ot_sender: &mut OT,
+    ) -> Result<(), Error> {
+        self.gen();
+        self.send(channel, ot_sender)?;
+        self.receive_challenge(channel)?;
+        self.compute_response();
+        self.send_response(channel)?;
+        Ok(())
+    }
+}
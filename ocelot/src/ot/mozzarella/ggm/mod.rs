@@ -1,10 +1,215 @@
 mod generator;
+pub mod half_tree;
 pub mod prover;
 pub mod verifier;
 
+use crate::{
+    errors::Error,
+    ot::{
+        CorrelatedReceiver, CorrelatedSender, RandomReceiver, RandomSender, Receiver as OtReceiver,
+        Sender as OtSender,
+    },
+};
+use scuttlebutt::{AbstractChannel, Block};
+use serde::Serialize;
+use std::fmt;
+
+/// Height of a tree with `output_size` leaves, i.e., `ceil(log2(output_size))`.  If
+/// `output_size` is not a power of two, only the subtrees with leaves below them are expanded.
+pub fn tree_height(output_size: usize) -> usize {
+    assert!(output_size >= 2);
+    (usize::BITS - (output_size - 1).leading_zeros()) as usize
+}
+
+/// How the trees of the SP-VOLEs are expanded.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum TreeExpansion {
+    /// GGM trees with one OT per level (see `prover` and `verifier`)
+    #[default]
+    Ggm,
+    /// Half-tree expansion with one correlated OT and one block per level (see `half_tree`)
+    HalfTree,
+}
+
+impl fmt::Display for TreeExpansion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeExpansion::Ggm => write!(f, "ggm"),
+            TreeExpansion::HalfTree => write!(f, "half-tree"),
+        }
+    }
+}
+
+/// Prover side of either tree expansion.
+pub enum TreeProver {
+    Ggm(prover::BatchedProver),
+    HalfTree(half_tree::prover::BatchedProver),
+}
+
+impl TreeProver {
+    pub fn new_with_output_size(
+        expansion: TreeExpansion,
+        num_instances: usize,
+        output_size: usize,
+    ) -> Self {
+        match expansion {
+            TreeExpansion::Ggm => TreeProver::Ggm(prover::BatchedProver::new_with_output_size(
+                num_instances,
+                output_size,
+            )),
+            TreeExpansion::HalfTree => TreeProver::HalfTree(
+                half_tree::prover::BatchedProver::new_with_output_size(num_instances, output_size),
+            ),
+        }
+    }
+
+    pub fn expansion(&self) -> TreeExpansion {
+        match self {
+            TreeProver::Ggm(_) => TreeExpansion::Ggm,
+            TreeProver::HalfTree(_) => TreeExpansion::HalfTree,
+        }
+    }
+
+    pub fn get_output_blocks(&self) -> &[Block] {
+        match self {
+            TreeProver::Ggm(p) => p.get_output_blocks(),
+            TreeProver::HalfTree(p) => p.get_output_blocks(),
+        }
+    }
+
+    pub fn receive<
+        C: AbstractChannel,
+        OT: OtReceiver<Msg = Block> + CorrelatedReceiver + RandomReceiver,
+    >(
+        &mut self,
+        channel: &mut C,
+        ot_receiver: &mut OT,
+        alpha_s: &[usize],
+    ) -> Result<(), Error> {
+        match self {
+            TreeProver::Ggm(p) => p.receive(channel, ot_receiver, alpha_s),
+            TreeProver::HalfTree(p) => p.receive(channel, ot_receiver, alpha_s),
+        }
+    }
+
+    pub fn eval(&mut self) {
+        match self {
+            TreeProver::Ggm(p) => p.eval(),
+            TreeProver::HalfTree(p) => p.eval(),
+        }
+    }
+
+    pub fn send_challenge<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        match self {
+            TreeProver::Ggm(p) => p.send_challenge(channel),
+            TreeProver::HalfTree(p) => p.send_challenge(channel),
+        }
+    }
+
+    pub fn compute_hash(&mut self) {
+        match self {
+            TreeProver::Ggm(p) => p.compute_hash(),
+            TreeProver::HalfTree(p) => p.compute_hash(),
+        }
+    }
+
+    pub fn receive_response_and_check<C: AbstractChannel>(&self, channel: &mut C) -> bool {
+        match self {
+            TreeProver::Ggm(p) => p.receive_response_and_check(channel),
+            TreeProver::HalfTree(p) => p.receive_response_and_check(channel),
+        }
+    }
+}
+
+/// Verifier side of either tree expansion.
+pub enum TreeVerifier {
+    Ggm(verifier::BatchedVerifier),
+    HalfTree(half_tree::verifier::BatchedVerifier),
+}
+
+impl TreeVerifier {
+    pub fn new_with_output_size(
+        expansion: TreeExpansion,
+        num_instances: usize,
+        output_size: usize,
+    ) -> Self {
+        match expansion {
+            TreeExpansion::Ggm => TreeVerifier::Ggm(
+                verifier::BatchedVerifier::new_with_output_size(num_instances, output_size),
+            ),
+            TreeExpansion::HalfTree => {
+                TreeVerifier::HalfTree(half_tree::verifier::BatchedVerifier::new_with_output_size(
+                    num_instances,
+                    output_size,
+                ))
+            }
+        }
+    }
+
+    pub fn expansion(&self) -> TreeExpansion {
+        match self {
+            TreeVerifier::Ggm(_) => TreeExpansion::Ggm,
+            TreeVerifier::HalfTree(_) => TreeExpansion::HalfTree,
+        }
+    }
+
+    /// Set the global delta of the OT sender (only used by the half-tree expansion).
+    pub fn set_ot_delta(&mut self, ot_delta: Block) {
+        if let TreeVerifier::HalfTree(v) = self {
+            v.set_ot_delta(ot_delta);
+        }
+    }
+
+    pub fn get_output_blocks(&self) -> &[Block] {
+        match self {
+            TreeVerifier::Ggm(v) => v.get_output_blocks(),
+            TreeVerifier::HalfTree(v) => v.get_output_blocks(),
+        }
+    }
+
+    pub fn gen(&mut self) {
+        match self {
+            TreeVerifier::Ggm(v) => v.gen(),
+            TreeVerifier::HalfTree(v) => v.gen(),
+        }
+    }
+
+    pub fn send<C: AbstractChannel, OT: OtSender<Msg = Block> + CorrelatedSender + RandomSender>(
+        &mut self,
+        channel: &mut C,
+        ot_sender: &mut OT,
+    ) -> Result<(), Error> {
+        match self {
+            TreeVerifier::Ggm(v) => v.send(channel, ot_sender),
+            TreeVerifier::HalfTree(v) => v.send(channel, ot_sender),
+        }
+    }
+
+    pub fn receive_challenge<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        match self {
+            TreeVerifier::Ggm(v) => v.receive_challenge(channel),
+            TreeVerifier::HalfTree(v) => v.receive_challenge(channel),
+        }
+    }
+
+    pub fn compute_response(&mut self) {
+        match self {
+            TreeVerifier::Ggm(v) => v.compute_response(),
+            TreeVerifier::HalfTree(v) => v.compute_response(),
+        }
+    }
+
+    pub fn send_response<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        match self {
+            TreeVerifier::Ggm(v) => v.send_response(channel),
+            TreeVerifier::HalfTree(v) => v.send_response(channel),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{prover::BatchedProver, verifier::BatchedVerifier};
+    use super::{prover::BatchedProver, tree_height, verifier::BatchedVerifier};
     use crate::ot::{
         FixedKeyInitializer, KosDeltaReceiver, KosDeltaSender, Receiver as OtReceiver,
     };
@@ -91,7 +296,8 @@ mod tests {
                 BatchedVerifier::new_with_output_size(NUM_INSTANCES, OUTPUT_SIZE);
             let mut rng = OsRng;
             let ot_key: [u8; 16] = rng.gen();
-            let alpha_s: [usize; NUM_INSTANCES] = [0, 1, 2, 3, 4];
+            let alpha_s: [usize; NUM_INSTANCES] =
+                [0, 1, OUTPUT_SIZE / 2, OUTPUT_SIZE - 2, OUTPUT_SIZE - 1];
 
             let prover_thread = spawn(move || {
                 let mut rng = OsRng;
@@ -141,6 +347,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tree_height() {
+        assert_eq!(tree_height(2), 1);
+        assert_eq!(tree_height(3), 2);
+        assert_eq!(tree_height(4), 2);
+        assert_eq!(tree_height(256), 8);
+        assert_eq!(tree_height(257), 9);
+    }
+
+    #[test]
+    fn test_batched_ggm_tree_no_power_of_two_two() {
+        test_batched_ggm_tree_no_power_of_two::<2>();
+    }
+
     #[test]
     fn test_batched_ggm_tree_no_power_of_two_odd() {
         test_batched_ggm_tree_no_power_of_two::<5>();
@@ -150,4 +370,11 @@ mod tests {
     fn test_batched_ggm_tree_no_power_of_two_even() {
         test_batched_ggm_tree_no_power_of_two::<6>();
     }
+
+    #[test]
+    fn test_batched_ggm_tree_no_power_of_two_large() {
+        test_batched_ggm_tree_no_power_of_two::<257>();
+        test_batched_ggm_tree_no_power_of_two::<300>();
+        test_batched_ggm_tree_no_power_of_two::<383>();
+    }
 }
@@ -2,8 +2,8 @@ use crate::{
     errors::Error,
     ot::{
         mozzarella::{
-            ggm::generator::BiasedGen,
-            utils::{log2, prg2, unpack_bits_into},
+            ggm::{generator::BiasedGen, tree_height},
+            utils::{prg2, unpack_bits_into},
         },
         CorrelatedReceiver, RandomReceiver, Receiver as OtReceiver,
     },
@@ -49,8 +49,7 @@ impl BatchedProver {
     }
 
     pub fn new_with_output_size(num_instances: usize, output_size: usize) -> Self {
-        let tree_height = log2(output_size);
-        assert!(output_size <= 1 << tree_height);
+        let tree_height = tree_height(output_size);
 
         Self {
             num_instances,
@@ -141,74 +140,41 @@ impl BatchedProver {
         // path is easily computable: pack the bits again and use the result as an index
         // compute keyed index using this path: if 1 - alpha[i] = 0 : key = path - 1 else key = path + 1
 
-        // compute the index corresponding to the first key we obtained via OT
-        let keyed_index = (alpha >> (tree_height - 1)) ^ 1;
-        final_layer_blocks[keyed_index] = layer_keys[0]; // set initial key
-
         let last_index = output_size - 1;
 
         // iterate over the tree layer by layer
-        for i in 1..(tree_height - 1) {
+        for i in 0..tree_height {
+            let last_parent = last_index >> (tree_height - i);
+            let last_child = last_index >> (tree_height - i - 1);
             // collect XOR of all even/odd keys (depending on the current bit of alpha) to decrypt
             // the key received via OT
             let mut mask = Block::default();
             // expand each node in this layer;
             // we need to iterate from right to left, since we reuse the same buffer
-            for j in (0..(last_index >> (tree_height - i)) + 1).rev() {
+            for j in (0..=last_parent).rev() {
                 // skip the punctured path
                 if j == (alpha >> (tree_height - i)) {
                     continue;
                 }
                 let (s0, s1) = prg2(hash, final_layer_blocks[j]);
-                // update the mask
-                if !alpha_bits[i] {
-                    mask ^= s1;
-                } else {
+                final_layer_blocks[2 * j] = s0;
+                if alpha_bits[i] {
                     mask ^= s0;
                 }
-                final_layer_blocks[2 * j] = s0;
-                final_layer_blocks[2 * j + 1] = s1;
-            }
-            // decrypt and store neighbor of the node on the punctured path
-            let keyed_index = (alpha >> (tree_height - (i + 1))) ^ 1;
-            final_layer_blocks[keyed_index] = layer_keys[i] ^ mask;
-        }
-        // evaluate the last layer
-        let mut mask = Block::default();
-        // if the last node of the current layer is on the punctured path, we cannot expand
-        if (alpha >> 1) != (last_index >> 1) {
-            let (s0, s1) = prg2(hash, final_layer_blocks[last_index >> 1]);
-            final_layer_blocks[2 * (last_index >> 1)] = s0;
-            if alpha_bits[tree_height - 1] {
-                mask ^= s0;
-            }
-            // if the last index is odd, we have to expand the right child
-            if last_index & 1 == 1 {
-                final_layer_blocks[last_index] = s1;
-                if !alpha_bits[tree_height - 1] {
-                    mask ^= s1;
+                // the right child of the last node is pruned if there are no leaves below it
+                if 2 * j < last_child {
+                    final_layer_blocks[2 * j + 1] = s1;
+                    if !alpha_bits[i] {
+                        mask ^= s1;
+                    }
                 }
             }
-        }
-        // handle the first nodes normally
-        for j in (0..(last_index >> 1)).rev() {
-            if j == (alpha >> 1) {
-                continue;
-            }
-            let (s0, s1) = prg2(hash, final_layer_blocks[j]);
-            if !alpha_bits[tree_height - 1] {
-                mask ^= s1; // keep track of the complete XORs of each layer
-            } else {
-                mask ^= s0; // keep track of the complete XORs of each layer
+            // decrypt and store the neighbor of the node on the punctured path, unless it is
+            // pruned
+            let keyed_index = (alpha >> (tree_height - (i + 1))) ^ 1;
+            if keyed_index <= last_child {
+                final_layer_blocks[keyed_index] = layer_keys[i] ^ mask;
             }
-
-            final_layer_blocks[2 * j] = s0;
-            final_layer_blocks[2 * j + 1] = s1;
-        }
-        // decrypt the neighbor of index alpha if it is in bounds
-        if alpha < last_index || alpha & 1 == 1 {
-            let keyed_index = alpha ^ 1;
-            final_layer_blocks[keyed_index] = layer_keys[tree_height - 1] ^ mask;
         }
 
         // compute the actual outputs and the checking values in the final layer
@@ -276,7 +242,7 @@ impl BatchedProver {
         Ok(())
     }
 
-    fn compute_hash_helper(challenge_seed: &Block, final_layer_check_values: &[F128]) -> F128 {
+    pub fn compute_hash_helper(challenge_seed: &Block, final_layer_check_values: &[F128]) -> F128 {
         let mut gen = BiasedGen::new(*challenge_seed);
         /*
         TODO: Can we do this for all the ggm trees at once, so we gen n GGM trees
@@ -2,8 +2,8 @@ use crate::{
     errors::Error,
     ot::{
         mozzarella::{
-            ggm::generator::BiasedGen,
-            utils::{log2, prg2},
+            ggm::{generator::BiasedGen, tree_height},
+            utils::prg2,
         },
         CorrelatedSender, RandomSender, Sender as OtSender,
     },
@@ -46,7 +46,7 @@ impl BatchedVerifier {
     }
 
     pub fn new_with_output_size(num_instances: usize, output_size: usize) -> Self {
-        let tree_height = log2(output_size);
+        let tree_height = tree_height(output_size);
 
         Self {
             num_instances,
@@ -94,40 +94,24 @@ impl BatchedVerifier {
         let last_index = output_size - 1;
 
         // iterate over the tree layer by layer
-        for i in 0..(tree_height - 1) {
+        for i in 0..tree_height {
+            let last_parent = last_index >> (tree_height - i);
+            let last_child = last_index >> (tree_height - i - 1);
             layer_key_pairs[i].0 = Block::default();
             layer_key_pairs[i].1 = Block::default();
             // expand each node in this layer;
             // we need to iterate from right to left, since we reuse the same buffer
-            for j in (0..(last_index >> (tree_height - i)) + 1).rev() {
-                let res = prg2(hash, final_layer_blocks[j]);
-                layer_key_pairs[i].0 ^= res.0; // keep track of the XORs of all keys with even indices
-                layer_key_pairs[i].1 ^= res.1; // same for all keys with odd indices
-                final_layer_blocks[2 * j] = res.0;
-                final_layer_blocks[2 * j + 1] = res.1;
+            for j in (0..=last_parent).rev() {
+                let (s0, s1) = prg2(hash, final_layer_blocks[j]);
+                layer_key_pairs[i].0 ^= s0; // keep track of the XORs of all keys with even indices
+                final_layer_blocks[2 * j] = s0;
+                // the right child of the last node is pruned if there are no leaves below it
+                if 2 * j < last_child {
+                    layer_key_pairs[i].1 ^= s1; // same for all keys with odd indices
+                    final_layer_blocks[2 * j + 1] = s1;
+                }
             }
         }
-        // when creating the last layer of the tree, we need to handle the right-most node
-        {
-            layer_key_pairs[tree_height - 1].0 = Block::default();
-            layer_key_pairs[tree_height - 1].1 = Block::default();
-            let (s0, s1) = prg2(hash, final_layer_blocks[last_index >> 1]);
-            layer_key_pairs[tree_height - 1].0 ^= s0; // keep track of the XORs of all keys with even indices
-            final_layer_blocks[2 * (last_index >> 1)] = s0;
-            // expand the right child only if it is needed
-            if last_index & 1 == 1 {
-                layer_key_pairs[tree_height - 1].1 ^= s1; // keep track of the XORs of all keys with odd indices
-                final_layer_blocks[last_index] = s1;
-            }
-        }
-        // handle the first nodes normally
-        for j in (0..(last_index >> 1)).rev() {
-            let (s0, s1) = prg2(hash, final_layer_blocks[j]);
-            layer_key_pairs[tree_height - 1].0 ^= s0; // keep track of the XORs of all keys with even indices
-            layer_key_pairs[tree_height - 1].1 ^= s1; // same for all keys with odd indices
-            final_layer_blocks[2 * j] = s0;
-            final_layer_blocks[2 * j + 1] = s1;
-        }
 
         *final_key = Block::default();
         // compute the actual outputs and the checking values in the final layer
@@ -0,0 +1,154 @@
+//! Init-time check that both parties use the same parameters.
+//!
+//! Both parties hash their parameters and exchange the digests.  Only if the digests differ, the
+//! full list of parameters is exchanged to produce a descriptive error message.
+use crate::Error;
+use scuttlebutt::AbstractChannel;
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+
+// upper bound on the serialized parameters accepted from the other party
+const MAX_SERIALIZED_LEN: usize = 4096;
+
+#[derive(Clone, Debug)]
+pub struct SessionParameters {
+    protocol: &'static str,
+    values: Vec<(&'static str, String)>,
+}
+
+impl SessionParameters {
+    pub fn new(protocol: &'static str) -> Self {
+        Self {
+            protocol,
+            values: Vec::new(),
+        }
+    }
+
+    pub fn add<T: Display>(mut self, name: &'static str, value: T) -> Self {
+        self.values.push((name, value.to_string()));
+        self
+    }
+
+    fn serialize(&self) -> String {
+        let mut out = self.protocol.to_string();
+        for (name, value) in self.values.iter() {
+            out.push_str(&format!("\n{}={}", name, value));
+        }
+        out
+    }
+
+    pub fn digest(&self) -> [u8; 32] {
+        Sha256::digest(self.serialize().as_bytes()).into()
+    }
+
+    /// Exchange the parameters with the other party and return an error if they differ.
+    pub fn exchange<C: AbstractChannel>(&self, channel: &mut C) -> Result<(), Error> {
+        channel.send(&self.digest())?;
+        channel.flush()?;
+        let other_digest: [u8; 32] = channel.receive()?;
+        if other_digest == self.digest() {
+            return Ok(());
+        }
+
+        // the digests do not match, so find out which parameters differ
+        let serialized = self.serialize();
+        channel.send(serialized.len())?;
+        channel.write_bytes(serialized.as_bytes())?;
+        channel.flush()?;
+        let other_len: usize = channel.receive()?;
+        if other_len > MAX_SERIALIZED_LEN {
+            return Err(Error::Other(format!(
+                "parameter mismatch: the other party sent {} bytes of parameters",
+                other_len
+            )));
+        }
+        let other_serialized = String::from_utf8_lossy(&channel.read_vec(other_len)?).to_string();
+
+        let mut other_lines = other_serialized.lines();
+        let other_protocol = other_lines.next().unwrap_or_default();
+        if other_protocol != self.protocol {
+            return Err(Error::Other(format!(
+                "parameter mismatch: running {}, but the other party runs {}",
+                self.protocol, other_protocol
+            )));
+        }
+        let other_values: Vec<(&str, &str)> = other_lines
+            .map(|line| line.split_once('=').unwrap_or((line, "")))
+            .collect();
+        let mismatches: Vec<String> = self
+            .values
+            .iter()
+            .map(|(name, value)| {
+                let other_value = other_values
+                    .iter()
+                    .find(|(other_name, _)| other_name == name)
+                    .map_or("<missing>", |(_, v)| v);
+                (name, value, other_value)
+            })
+            .filter(|(_, value, other_value)| value != other_value)
+            .map(|(name, value, other_value)| {
+                format!("{} (local: {}, remote: {})", name, value, other_value)
+            })
+            .collect();
+        Err(Error::Other(format!(
+            "parameter mismatch in {}: {}",
+            self.protocol,
+            if mismatches.is_empty() {
+                "parameters differ".to_string()
+            } else {
+                mismatches.join(", ")
+            }
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SessionParameters;
+    use scuttlebutt::{unix_channel_pair, AbstractChannel};
+    use std::thread::spawn;
+
+    #[test]
+    fn test_handshake() {
+        let params = SessionParameters::new("test")
+            .add("k", 40)
+            .add("nightly", false);
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let params_p = params.clone();
+        let handle = spawn(move || params_p.exchange(&mut channel_p));
+        assert!(params.exchange(&mut channel_v).is_ok());
+        assert!(handle.join().unwrap().is_ok());
+
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let handle = spawn(move || {
+            SessionParameters::new("test")
+                .add("k", 40)
+                .add("nightly", true)
+                .exchange(&mut channel_p)
+        });
+        let err = params.exchange(&mut channel_v).unwrap_err().to_string();
+        assert!(err.contains("nightly (local: false, remote: true)"));
+        assert!(!err.contains("k ("));
+        assert!(handle.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_handshake_oversized() {
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let handle = spawn(move || {
+            // a wrong digest followed by a huge length
+            channel_p.send(&[0u8; 32]).unwrap();
+            channel_p.send(usize::MAX).unwrap();
+            channel_p.flush().unwrap();
+            // consume everything until the other party hangs up
+            while channel_p.receive::<[u8; 1]>().is_ok() {}
+        });
+        let err = SessionParameters::new("test")
+            .exchange(&mut channel_v)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("bytes of parameters"));
+        drop(channel_v);
+        handle.join().unwrap();
+    }
+}
@@ -1,17 +1,129 @@
+use crate::Error;
 use rand::{
     distributions::{Distribution, Standard},
     CryptoRng, Rng, SeedableRng,
 };
 use rayon::prelude::*;
-use scuttlebutt::{ring::Ring, AesRng, Block};
+use scuttlebutt::{cointoss, ring::Ring, AbstractChannel, AesRng, Block};
+use sha2::{Digest, Sha256};
+use std::marker::PhantomData;
+
+/// Linear code used for the LPN expansion, mapping vectors of length `rows()` to vectors of
+/// length `columns()`.
+pub trait LpnCode<RingT>: Send + Sync
+where
+    RingT: Ring,
+    Standard: Distribution<RingT>,
+{
+    fn rows(&self) -> usize;
+
+    fn columns(&self) -> usize;
+
+    /// Digest identifying the code, used to check that both parties use the same one.
+    fn digest(&self) -> [u8; 32];
+
+    /// A code of the same family and dimensions generated from `seed`.
+    fn with_seed(&self, seed: Block) -> Box<dyn LpnCode<RingT>>;
+
+    fn mul_into(&self, v: &[RingT], out: &mut [RingT]);
+
+    // computes A*v + a
+    fn mul_add_into(&self, v: &[RingT], a: &[RingT], out: &mut [RingT]);
+
+    fn mul(&self, v: &[RingT]) -> Vec<RingT> {
+        let mut out = vec![RingT::default(); self.columns()];
+        self.mul_into(v, &mut out);
+        out
+    }
+
+    fn mul_add(&self, v: &[RingT], a: &[RingT]) -> Vec<RingT> {
+        let mut out = vec![RingT::default(); self.columns()];
+        self.mul_add_into(v, a, &mut out);
+        out
+    }
+}
 
 // Z64 Local Linear Code with parameter D
+//
+// The columns are grouped into blocks of `LLCODE_BLOCK_COLUMNS` consecutive columns.  Within a
+// block, the entries are sorted by row, such that a multiplication reads `v` sequentially (instead
+// of jumping around in a vector that does not fit into the cache) and only the accumulators of the
+// block need to stay in the cache.
 // pub struct LLCode<const ROWS: usize, const COLS: usize, const D: usize> {
 pub struct LLCode<RingT> {
     pub rows: usize,
     pub columns: usize,
     pub nonzero_entries_per_column: usize,
-    indices: Vec<(usize, RingT)>,
+    seed: Option<Block>,
+    // digest of all entries, only computed for codes without seed
+    entries_digest: Option<[u8; 32]>,
+    blocks: Vec<CodeBlock<RingT>>,
+}
+
+/// Number of columns in one block of an `LLCode`.
+pub const LLCODE_BLOCK_COLUMNS: usize = 1 << 14;
+
+// number of entries that are multiplied in one batch before accumulating them
+const MUL_BATCH_SIZE: usize = 64;
+
+// entries of a block of columns, sorted by row
+struct CodeBlock<RingT> {
+    rows: Vec<u32>,
+    // column relative to the start of the block
+    columns: Vec<u16>,
+    coefficients: Vec<RingT>,
+}
+
+impl<RingT: Ring> CodeBlock<RingT>
+where
+    Standard: Distribution<RingT>,
+{
+    fn new(mut entries: Vec<(u32, u16, RingT)>) -> Self {
+        entries.sort_unstable_by_key(|e| e.0);
+        Self {
+            rows: entries.iter().map(|e| e.0).collect(),
+            columns: entries.iter().map(|e| e.1).collect(),
+            coefficients: entries.iter().map(|e| e.2).collect(),
+        }
+    }
+
+    // accumulate the (unreduced) products of the block's column with `v` into `acc`
+    #[inline]
+    fn mul_acc(&self, v: &[RingT], acc: &mut [RingT]) {
+        // multiply a batch of entries with the corresponding entries of `v`, then add them to
+        // their columns; the multiplication loop runs over contiguous arrays, which helps the
+        // multi-limb rings (Z2rU128, Z2rU192, Z2rU256)
+        let mut products = [RingT::ZERO; MUL_BATCH_SIZE];
+        for ((rows, columns), coefficients) in self
+            .rows
+            .chunks(MUL_BATCH_SIZE)
+            .zip(self.columns.chunks(MUL_BATCH_SIZE))
+            .zip(self.coefficients.chunks(MUL_BATCH_SIZE))
+        {
+            for ((p, &r), &c) in products.iter_mut().zip(rows).zip(coefficients) {
+                *p = c * v[r as usize];
+            }
+            for (p, &j) in products.iter().zip(columns) {
+                acc[j as usize] += *p;
+            }
+        }
+    }
+}
+
+/// Jointly sample a fresh seed for a code via coin tossing, such that neither party alone chooses
+/// the code.  The prover acts as the coin tossing sender.
+pub fn cointoss_code_seed<C: AbstractChannel, R: Rng + CryptoRng>(
+    channel: &mut C,
+    rng: &mut R,
+    is_prover: bool,
+) -> Result<Block, Error> {
+    let seed = rng.gen::<Block>();
+    let out = if is_prover {
+        cointoss::send(channel, &[seed])?
+    } else {
+        cointoss::receive(channel, &[seed])?
+    };
+    Ok(out[0])
 }
 
 // columns have length of rows
@@ -28,7 +140,34 @@ where
         seed: Block,
     ) -> Self {
         let mut rng = AesRng::from_seed(seed);
-        Self::gen(rows, columns, nonzero_entries_per_column, &mut rng)
+        let mut code =
+            Self::gen_internal(rows, columns, nonzero_entries_per_column, &mut rng, false);
+        code.seed = Some(seed);
+        code
+    }
+
+    pub fn get_seed(&self) -> Option<Block> {
+        self.seed
+    }
+
+    // digest identifying the code: covers the seed if known, otherwise all entries
+    pub fn digest(&self) -> [u8; 32] {
+        match self.seed {
+            Some(seed) => {
+                let mut hasher = self.header_hasher();
+                hasher.update(seed.as_ref());
+                hasher.finalize().into()
+            }
+            None => self.entries_digest.unwrap(),
+        }
+    }
+
+    fn header_hasher(&self) -> Sha256 {
+        let mut hasher = Sha256::new();
+        hasher.update((self.rows as u64).to_le_bytes());
+        hasher.update((self.columns as u64).to_le_bytes());
+        hasher.update((self.nonzero_entries_per_column as u64).to_le_bytes());
+        hasher
     }
 
     #[inline]
@@ -58,54 +197,348 @@ where
         nonzero_entries_per_column: usize,
         rng: &mut R,
     ) -> Self {
+        Self::gen_internal(rows, columns, nonzero_entries_per_column, rng, true)
+    }
+
+    fn gen_internal<R: Rng + CryptoRng>(
+        rows: usize,
+        columns: usize,
+        nonzero_entries_per_column: usize,
+        rng: &mut R,
+        compute_digest: bool,
+    ) -> Self {
+        assert!(rows <= u32::MAX as usize);
         let mut code = LLCode {
             rows,
             columns,
             nonzero_entries_per_column,
-            indices: vec![(0, RingT::default()); columns * nonzero_entries_per_column],
+            seed: None,
+            entries_digest: None,
+            blocks: Vec::new(),
         };
+        let mut hasher = code.header_hasher();
+        let mut column = vec![(0, RingT::default()); nonzero_entries_per_column];
+        // generate the columns in order, such that the code only depends on the rng
+        let mut block_entries: Vec<Vec<(u32, u16, RingT)>> = Vec::new();
         for col_i in 0..columns {
-            Self::gen_column(
-                rng,
-                code.rows,
-                &mut code.indices[col_i * code.nonzero_entries_per_column
-                    ..(col_i + 1) * code.nonzero_entries_per_column],
-            );
+            if col_i % LLCODE_BLOCK_COLUMNS == 0 {
+                let block_len =
+                    usize::min(LLCODE_BLOCK_COLUMNS, columns - col_i) * nonzero_entries_per_column;
+                block_entries.push(Vec::with_capacity(block_len));
+            }
+            Self::gen_column(rng, rows, &mut column);
+            let entries = block_entries.last_mut().unwrap();
+            for &(i, x) in column.iter() {
+                if compute_digest {
+                    hasher.update((i as u64).to_le_bytes());
+                    hasher.update(&x.reduce().as_ref()[..RingT::BYTE_LENGTH]);
+                }
+                entries.push((i as u32, (col_i % LLCODE_BLOCK_COLUMNS) as u16, x));
+            }
+        }
+        code.blocks = block_entries.into_par_iter().map(CodeBlock::new).collect();
+        if compute_digest {
+            code.entries_digest = Some(hasher.finalize().into());
         }
         code
     }
+}
 
-    // TODO: Can likely be made more efficient somehow?
-    pub fn mul(&self, v: &[RingT]) -> Vec<RingT> {
+impl<RingT> LpnCode<RingT> for LLCode<RingT>
+where
+    RingT: Ring,
+    Standard: Distribution<RingT>,
+{
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn columns(&self) -> usize {
+        self.columns
+    }
+
+    fn digest(&self) -> [u8; 32] {
+        LLCode::digest(self)
+    }
+
+    fn with_seed(&self, seed: Block) -> Box<dyn LpnCode<RingT>> {
+        Box::new(Self::from_seed(
+            self.rows,
+            self.columns,
+            self.nonzero_entries_per_column,
+            seed,
+        ))
+    }
+
+    fn mul_into(&self, v: &[RingT], out: &mut [RingT]) {
         assert_eq!(v.len(), self.rows);
-        (self
-            .indices
-            .par_chunks_exact(self.nonzero_entries_per_column)
-            .map(|col| {
-                let mut cord: RingT = RingT::default();
-                for i in col {
-                    cord += i.1 * v[i.0];
+        assert_eq!(out.len(), self.columns);
+        self.blocks
+            .par_iter()
+            .zip(out.par_chunks_mut(LLCODE_BLOCK_COLUMNS))
+            .for_each(|(block, out)| {
+                for out_j in out.iter_mut() {
+                    *out_j = RingT::ZERO;
+                }
+                block.mul_acc(v, out);
+                for out_j in out.iter_mut() {
+                    *out_j = out_j.reduce();
+                }
+            });
+    }
+
+    fn mul_add_into(&self, v: &[RingT], a: &[RingT], out: &mut [RingT]) {
+        assert_eq!(v.len(), self.rows);
+        assert_eq!(a.len(), self.columns);
+        assert_eq!(out.len(), self.columns);
+        self.blocks
+            .par_iter()
+            .zip(out.par_chunks_mut(LLCODE_BLOCK_COLUMNS))
+            .zip(a.par_chunks(LLCODE_BLOCK_COLUMNS))
+            .for_each(|((block, out), a)| {
+                out.copy_from_slice(a);
+                block.mul_acc(v, out);
+                for out_j in out.iter_mut() {
+                    *out_j = out_j.reduce();
+                }
+            });
+    }
+}
+
+/// Expand-accumulate code (Boyle et al., CRYPTO 2022): a sparse binary expanding matrix with
+/// `weight` ones per column followed by an accumulator, i.e., the output is the prefix sum of the
+/// expanded vector.  Since the expanding matrix has no coefficients and a small weight suffices
+/// (see `EA_CODE_D`), this is much cheaper to evaluate than `LLCode`.
+pub struct EACode<RingT> {
+    pub rows: usize,
+    pub columns: usize,
+    pub weight: usize,
+    seed: Option<Block>,
+    indices: Vec<usize>,
+    _ring: PhantomData<RingT>,
+}
+
+impl<RingT> EACode<RingT>
+where
+    RingT: Ring,
+    Standard: Distribution<RingT>,
+{
+    pub fn from_seed(rows: usize, columns: usize, weight: usize, seed: Block) -> Self {
+        let mut rng = AesRng::from_seed(seed);
+        let mut code = Self::gen(rows, columns, weight, &mut rng);
+        code.seed = Some(seed);
+        code
+    }
+
+    pub fn gen<R: Rng + CryptoRng>(
+        rows: usize,
+        columns: usize,
+        weight: usize,
+        rng: &mut R,
+    ) -> Self {
+        assert!(weight > 0 && weight <= rows);
+        let mut indices = vec![0; columns * weight];
+        for column in indices.chunks_exact_mut(weight) {
+            let mut count = 0;
+            while count < weight {
+                let new_index = rng.gen_range(0, rows);
+                if column[..count].iter().all(|&i| i != new_index) {
+                    column[count] = new_index;
+                    count += 1;
                 }
-                cord.reduce()
-            }))
-        .collect()
+            }
+            column.sort_unstable();
+        }
+        Self {
+            rows,
+            columns,
+            weight,
+            seed: None,
+            indices,
+            _ring: PhantomData,
+        }
+    }
+
+    pub fn get_seed(&self) -> Option<Block> {
+        self.seed
     }
 
-    // takes the indices of the code (A) and adds them to elements of a.
-    pub fn mul_add(&self, v: &[RingT], a: &[RingT]) -> Vec<RingT> {
+    // expand v into out (without accumulating)
+    fn expand_into(&self, v: &[RingT], out: &mut [RingT]) {
         assert_eq!(v.len(), self.rows);
+        assert_eq!(out.len(), self.columns);
+        self.indices
+            .par_chunks_exact(self.weight)
+            .zip(out.par_iter_mut())
+            .for_each(|(col, out_j)| {
+                *out_j = col.iter().map(|&i| v[i]).sum();
+            });
+    }
+}
+
+impl<RingT> LpnCode<RingT> for EACode<RingT>
+where
+    RingT: Ring,
+    Standard: Distribution<RingT>,
+{
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn columns(&self) -> usize {
+        self.columns
+    }
+
+    fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"EACode");
+        hasher.update((self.rows as u64).to_le_bytes());
+        hasher.update((self.columns as u64).to_le_bytes());
+        hasher.update((self.weight as u64).to_le_bytes());
+        match self.seed {
+            Some(seed) => hasher.update(seed.as_ref()),
+            None => {
+                for i in self.indices.iter() {
+                    hasher.update((*i as u64).to_le_bytes());
+                }
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    fn with_seed(&self, seed: Block) -> Box<dyn LpnCode<RingT>> {
+        Box::new(Self::from_seed(self.rows, self.columns, self.weight, seed))
+    }
+
+    fn mul_into(&self, v: &[RingT], out: &mut [RingT]) {
+        self.expand_into(v, out);
+        // the accumulator is inherently sequential, but only costs one addition per entry
+        let mut acc = RingT::ZERO;
+        for out_j in out.iter_mut() {
+            acc = (acc + *out_j).reduce();
+            *out_j = acc;
+        }
+    }
+
+    fn mul_add_into(&self, v: &[RingT], a: &[RingT], out: &mut [RingT]) {
         assert_eq!(a.len(), self.columns);
-        (self
-            .indices
-            .par_chunks_exact(self.nonzero_entries_per_column)
-            .enumerate()
-            .map(|(j, col)| {
-                let mut cord: RingT = RingT::default();
-                for i in col {
-                    cord += i.1 * v[i.0];
+        self.expand_into(v, out);
+        let mut acc = RingT::ZERO;
+        for (out_j, a_j) in out.iter_mut().zip(a.iter()) {
+            acc = (acc + *out_j).reduce();
+            *out_j = (acc + *a_j).reduce();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EACode, LLCode, LpnCode, LLCODE_BLOCK_COLUMNS};
+    use rand::{
+        distributions::{Distribution, Standard},
+        rngs::OsRng,
+        Rng, SeedableRng,
+    };
+    use scuttlebutt::{
+        ring::{z2r, F61p, Ring, R64},
+        AesRng, Block,
+    };
+    use sha2::{Digest, Sha256};
+
+    // straightforward column by column multiplication
+    fn test_llcode_kernel<RingT>()
+    where
+        RingT: Ring,
+        Standard: Distribution<RingT>,
+    {
+        const ROWS: usize = 5000;
+        const COLUMNS: usize = 2 * LLCODE_BLOCK_COLUMNS + 1000;
+        const D: usize = 10;
+        let seed = OsRng.gen::<Block>();
+        let code = LLCode::<RingT>::from_seed(ROWS, COLUMNS, D, seed);
+
+        // regenerate the columns in the order of the rng
+        let mut rng = AesRng::from_seed(seed);
+        let mut columns = vec![(0, RingT::default()); COLUMNS * D];
+        for column in columns.chunks_exact_mut(D) {
+            LLCode::gen_column(&mut rng, ROWS, column);
+        }
+
+        let v: Vec<RingT> = (0..ROWS).map(|_| OsRng.gen()).collect();
+        let a: Vec<RingT> = (0..COLUMNS).map(|_| OsRng.gen()).collect();
+        let expected: Vec<RingT> = columns
+            .chunks_exact(D)
+            .map(|column| {
+                let mut cord = RingT::default();
+                for &(i, x) in column {
+                    cord += x * v[i];
                 }
-                (cord + a[j]).reduce()
-            }))
-        .collect()
+                cord
+            })
+            .collect();
+        let out = code.mul(&v);
+        let out_add = code.mul_add(&v, &a);
+        for j in 0..COLUMNS {
+            assert_eq!(out[j], expected[j].reduce());
+            assert_eq!(out_add[j], (expected[j] + a[j]).reduce());
+        }
+
+        // the digest of a code without seed covers all entries in column order
+        let code = LLCode::<RingT>::gen(ROWS, COLUMNS, D, &mut AesRng::from_seed(seed));
+        let mut hasher = Sha256::new();
+        hasher.update((ROWS as u64).to_le_bytes());
+        hasher.update((COLUMNS as u64).to_le_bytes());
+        hasher.update((D as u64).to_le_bytes());
+        for (i, x) in columns.iter() {
+            hasher.update((*i as u64).to_le_bytes());
+            hasher.update(&x.reduce().as_ref()[..RingT::BYTE_LENGTH]);
+        }
+        assert_eq!(code.digest(), <[u8; 32]>::from(hasher.finalize()));
+        assert_eq!(code.mul(&v), out);
+    }
+
+    #[test]
+    fn test_llcode_kernel_r64() {
+        test_llcode_kernel::<R64>();
+    }
+
+    #[test]
+    fn test_llcode_kernel_r144() {
+        test_llcode_kernel::<z2r::R144>();
+    }
+
+    #[test]
+    fn test_llcode_kernel_r244() {
+        test_llcode_kernel::<z2r::R244>();
+    }
+
+    #[test]
+    fn test_llcode_kernel_f61p() {
+        test_llcode_kernel::<F61p>();
+    }
+
+    #[test]
+    fn test_ea_code() {
+        const ROWS: usize = 20;
+        const COLUMNS: usize = 100;
+        let code = EACode::<R64>::from_seed(ROWS, COLUMNS, 5, Block::default());
+        let v: Vec<R64> = (0..ROWS).map(|_| OsRng.gen()).collect();
+        let a: Vec<R64> = (0..COLUMNS).map(|_| OsRng.gen()).collect();
+
+        // expand, then compute the prefix sums
+        let mut expected = Vec::with_capacity(COLUMNS);
+        let mut acc = R64::default();
+        for col in code.indices.chunks_exact(code.weight) {
+            acc += col.iter().map(|&i| v[i]).sum();
+            expected.push(acc);
+        }
+        assert_eq!(code.mul(&v), expected);
+        let out = code.mul_add(&v, &a);
+        for j in 0..COLUMNS {
+            assert_eq!(out[j], expected[j] + a[j]);
+        }
+
+        assert_eq!(code.digest(), code.with_seed(Block::default()).digest());
+        assert_ne!(code.digest(), code.with_seed(Block::from(1u128)).digest());
     }
 }
@@ -0,0 +1,204 @@
+//! Bit security estimates for the (leaky) regular LPN parameters used by Mozzarella and Ferret.
+//!
+//! The estimate follows the usual methodology (e.g., Ferret, Sec. 5):
+//!
+//! - The regular structure of the noise lets the attacker reduce the dimension and the number of
+//!   samples by the number of noise coordinates, i.e., we consider LPN with `k = K - T` and
+//!   `n = N - T`.
+//! - Over Z_2^l, the attacker can reduce the samples modulo 2.  Since the noise values are
+//!   uniformly random, only about half of them survive, so we consider noise weight `T / 2`.
+//! - We take the minimum over the pooled Gauss attack and information set decoding (Prange and
+//!   Stern/Dumer, where the attacker may also use only a subset of the samples).
+//! - The selective failure leakage of the SP-VOLE costs at most one bit: guessing `l` bits of
+//!   the noise positions succeeds only with probability `2^-l`.
+//!
+//! Codes with fewer than `MIN_CODE_D` non-zero entries per column might have low-weight dual
+//! codewords, which we do not model.  For such codes the estimate is zero.
+
+/// Minimal number of non-zero entries per column of the code.
+pub const MIN_CODE_D: usize = 10;
+
+/// Bits of security lost due to the selective failure leakage.
+pub const LEAKAGE_LOSS_BITS: f64 = 1.0;
+
+// exponent of Gaussian elimination
+const GAUSS_EXPONENT: f64 = 2.8;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SecurityEstimate {
+    /// log2 of the cost of the pooled Gauss attack
+    pub pooled_gauss: f64,
+    /// log2 of the cost of the best information set decoding attack
+    pub isd: f64,
+}
+
+impl SecurityEstimate {
+    /// Estimated bit security, i.e., the cost of the best attack minus the leakage.
+    pub fn bits(&self) -> f64 {
+        f64::max(0.0, self.pooled_gauss.min(self.isd) - LEAKAGE_LOSS_BITS)
+    }
+}
+
+// log2(x!) via Stirling's series
+fn log2_factorial(x: f64) -> f64 {
+    if x < 1.0 {
+        return 0.0;
+    }
+    (x * x.ln() - x + 0.5 * (2.0 * std::f64::consts::PI * x).ln() + 1.0 / (12.0 * x))
+        / std::f64::consts::LN_2
+}
+
+// log2(binomial(n, k))
+fn log2_binomial(n: f64, k: f64) -> f64 {
+    if k < 0.0 || k > n {
+        return f64::NEG_INFINITY;
+    }
+    log2_factorial(n) - log2_factorial(k) - log2_factorial(n - k)
+}
+
+// log2(2^a + 2^b + 2^c)
+fn log2_sum(a: f64, b: f64, c: f64) -> f64 {
+    let m = a.max(b).max(c);
+    m + (2f64.powf(a - m) + 2f64.powf(b - m) + 2f64.powf(c - m)).log2()
+}
+
+// pooled Gauss: solve k samples until all of them are noise-free
+fn pooled_gauss(n: f64, k: f64, t: f64) -> f64 {
+    GAUSS_EXPONENT * k.log2() - k * (1.0 - t / n).log2()
+}
+
+// Stern/Dumer ISD (Prange for p = l = 0), minimized over the parameters and the number of used
+// samples
+fn isd(n: f64, k: f64, t: f64) -> f64 {
+    let mut best = f64::INFINITY;
+    let mut n_used = n;
+    while n_used > 1.01 * k {
+        let w = t * n_used / n;
+        for p in (0..=10).step_by(2).map(|p| p as f64) {
+            if w < p {
+                break;
+            }
+            for l in (0..=80).step_by(4).map(|l| l as f64) {
+                if n_used - k - l < w - p {
+                    break;
+                }
+                let list_size = log2_binomial((k + l) / 2.0, p / 2.0);
+                let log_success = 2.0 * list_size + log2_binomial(n_used - k - l, w - p)
+                    - log2_binomial(n_used, w);
+                let log_iteration = log2_sum(
+                    ((n_used - k) * n_used).log2(),
+                    list_size + 1.0,
+                    2.0 * list_size - l,
+                );
+                best = best.min(log_iteration - log_success);
+            }
+        }
+        n_used /= 1.25;
+    }
+    best
+}
+
+/// Estimate the security of regular LPN with the given parameters (`K` = `base_vole_len`, `N` =
+/// `extension_size`, `T` = `num_noise_coordinates`) over a ring with `ring_bit_length` bits
+/// (1 for GF(2)).
+pub fn estimate_security(
+    base_vole_len: usize,
+    extension_size: usize,
+    num_noise_coordinates: usize,
+    code_d: usize,
+    ring_bit_length: usize,
+) -> SecurityEstimate {
+    if code_d < MIN_CODE_D
+        || num_noise_coordinates == 0
+        || base_vole_len <= num_noise_coordinates
+        || extension_size <= base_vole_len
+    {
+        return SecurityEstimate {
+            pooled_gauss: 0.0,
+            isd: 0.0,
+        };
+    }
+    let n = (extension_size - num_noise_coordinates) as f64;
+    let k = (base_vole_len - num_noise_coordinates) as f64;
+    let t = if ring_bit_length > 1 {
+        num_noise_coordinates as f64 / 2.0
+    } else {
+        num_noise_coordinates as f64
+    };
+    SecurityEstimate {
+        pooled_gauss: pooled_gauss(n, k, t),
+        isd: isd(n, k, t),
+    }
+}
+
+/// Propose `(base_vole_len, num_noise_coordinates)` for the given extension size such that the
+/// estimated security is at least `security_level` bits.  Among all such parameters, the ones
+/// that consume the fewest base VOLEs per extension (`K + 2T`) are chosen.
+pub fn select_parameters(
+    extension_size: usize,
+    security_level: usize,
+    code_d: usize,
+    ring_bit_length: usize,
+) -> Option<(usize, usize)> {
+    let is_secure = |k: usize, t: usize| {
+        estimate_security(k, extension_size, t, code_d, ring_bit_length).bits()
+            >= security_level as f64
+    };
+    let mut best = None;
+    let mut best_cost = usize::MAX;
+    // candidates for K between N/512 and N/2
+    let mut k = (extension_size / 512).max(16) as f64;
+    while k <= (extension_size / 2) as f64 {
+        let base_vole_len = k as usize;
+        k *= 1.1;
+        // smallest secure T by binary search
+        let (mut lo, mut hi) = (1, base_vole_len / 2);
+        if hi < lo || !is_secure(base_vole_len, hi) {
+            continue;
+        }
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if is_secure(base_vole_len, mid) {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        let cost = base_vole_len + 2 * lo;
+        if cost < best_cost {
+            best = Some((base_vole_len, lo));
+            best_cost = cost;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_security() {
+        // Ferret's parameters over GF(2)
+        let estimate = estimate_security(589_760, 10_805_248, 1_319, 10, 1);
+        assert!(estimate.bits() >= 128.0);
+        assert!(estimate.bits() < 160.0);
+        // reducing modulo 2 halves the noise
+        let estimate_ring = estimate_security(589_760, 10_805_248, 1_319, 10, 64);
+        assert!(estimate_ring.bits() < estimate.bits());
+        // too sparse codes are rejected
+        assert_eq!(
+            estimate_security(589_760, 10_805_248, 1_319, 4, 1).bits(),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_select_parameters() {
+        const N: usize = 1 << 20;
+        let (k, t) = select_parameters(N, 100, 10, 64).unwrap();
+        assert!(k + 2 * t < N);
+        assert!(estimate_security(k, N, t, 10, 64).bits() >= 100.0);
+        assert!(select_parameters(1 << 10, 128, 10, 64).is_none());
+    }
+}
@@ -1,15 +1,33 @@
+//! Mozzarella: VOLE extension over Z_2^k based on (leaky) regular LPN.
+//!
+//! Subfield VOLE (`u` in Z_2^l, MACs in Z_2^k) is not supported: Z_2^l is a quotient and not a
+//! subring of Z_2^k, so the LPN expansion cannot be reduced modulo 2^l.  Use a smaller ring
+//! instead.
 use crate::ot::mozzarella::{
     prover::{Prover, ProverStats},
     verifier::{Verifier, VerifierStats},
 };
 use lazy_static::lazy_static;
 
-use crate::ot::mozzarella::lpn::LLCode;
-use scuttlebutt::{ring::R64, Block};
+use crate::ot::mozzarella::{
+    ggm::TreeExpansion,
+    handshake::SessionParameters,
+    lpn::{LLCode, LpnCode},
+};
+use rand::distributions::{Distribution, Standard};
+use scuttlebutt::{
+    ring::{Ring, R64},
+    Block,
+};
 
 pub mod cache;
+mod checkpoint;
 pub mod ggm;
+pub mod handshake;
 pub mod lpn;
+pub mod lpn_estimator;
+pub mod multi;
+pub mod pool;
 mod prover;
 pub mod spvole;
 pub mod utils;
@@ -25,6 +43,8 @@ pub const fn reg_vole_required(k: usize, t: usize) -> usize {
 }
 
 pub const CODE_D: usize = 10;
+// number of ones per column of the expanding matrix of an expand-accumulate code
+pub const EA_CODE_D: usize = 7;
 
 // benchmarking parameters
 //pub const REG_MAIN_K: usize = 589_760; // TODO: remove this eventually, when cache works
@@ -51,14 +71,54 @@ lazy_static! {
     static ref REG_TEST_CODE: LLCode<R64> = LLCode::<R64>::from_seed(10, 64, 4, Block::default());
 }
 
+// parameters that prover and verifier need to agree on
+fn session_parameters<RingT: Ring>(
+    code: &dyn LpnCode<RingT>,
+    base_vole_len: usize,
+    num_sp_voles: usize,
+    sp_vole_single_len: usize,
+    sp_vole_sub_batches: usize,
+    tree_expansion: TreeExpansion,
+    nightly_version: bool,
+) -> SessionParameters
+where
+    Standard: Distribution<RingT>,
+{
+    let code_digest: String = code.digest().iter().map(|b| format!("{:02x}", b)).collect();
+    SessionParameters::new("Mozzarella")
+        .add("ring", std::any::type_name::<RingT>())
+        .add("ring_bit_length", RingT::BIT_LENGTH)
+        .add("code", code_digest)
+        .add("base_vole_len", base_vole_len)
+        .add("num_sp_voles", num_sp_voles)
+        .add("sp_vole_single_len", sp_vole_single_len)
+        .add("sp_vole_sub_batches", sp_vole_sub_batches)
+        .add("tree_expansion", tree_expansion)
+        .add("nightly_version", nightly_version)
+}
+
+// parameters of a switch to a fresh delta (see `Verifier::rekey`)
+fn rekey_parameters(num_base_voles: usize) -> SessionParameters {
+    SessionParameters::new("MozzarellaRekey").add("num_base_voles", num_base_voles)
+}
+
 pub fn init_lpn() {
     lazy_static::initialize(&REG_MAIN_CODE);
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{LLCode, MozzarellaProver, MozzarellaVerifier, CODE_D};
-    use crate::ot::mozzarella::cache::cacheinit::GenCache;
+    use super::{
+        lpn::{cointoss_code_seed, EACode},
+        multi::{MultiProver, MultiVerifier},
+        pool::{ProverPool, VerifierPool},
+        reg_vole_required, LLCode, LpnCode, MozzarellaProver, MozzarellaVerifier, CODE_D,
+        EA_CODE_D,
+    };
+    use crate::ot::{
+        mozzarella::cache::{cacheinit::GenCache, prover::CachedProver, verifier::CachedVerifier},
+        VoleReceiver, VoleSender,
+    };
     use rand::{
         distributions::{Distribution, Standard},
         rngs::OsRng,
@@ -66,12 +126,120 @@ mod tests {
     };
     use scuttlebutt::{
         channel::{Receivable, Sendable},
-        ring::{z2r, Ring, R64},
+        ring::{z2r, F61p, Ring, R64},
         unix_channel_pair, Block,
     };
     use std::{sync::Arc, thread::spawn};
 
-    fn test_vole_extension<RingT, const NIGHTLY: bool>()
+    // parameters of the small LPN instances used by most tests
+    const BASE_VOLE_LEN: usize = 10;
+    const NUM_SP_VOLES: usize = 4;
+    const SINGLE_SP_OUTPUT_SIZE: usize = 16;
+    const OUTPUT_SIZE: usize = NUM_SP_VOLES * SINGLE_SP_OUTPUT_SIZE;
+    const REQUIRED: usize = reg_vole_required(BASE_VOLE_LEN, NUM_SP_VOLES);
+
+    // code, base VOLE caches of `cache_size` VOLEs and their delta
+    fn gen_code_and_caches<RingT>(
+        cache_size: usize,
+    ) -> (
+        Arc<LLCode<RingT>>,
+        CachedProver<RingT>,
+        CachedVerifier<RingT>,
+        RingT,
+    )
+    where
+        RingT: Ring,
+        Standard: Distribution<RingT>,
+    {
+        let code = Arc::new(LLCode::<RingT>::from_seed(
+            BASE_VOLE_LEN,
+            OUTPUT_SIZE,
+            CODE_D,
+            Block::default(),
+        ));
+        let delta = OsRng.gen::<RingT>().reduce();
+        let (cached_prover, cached_verifier) = GenCache::new_with_size(OsRng, delta, cache_size);
+        (code, cached_prover, cached_verifier, delta)
+    }
+
+    fn new_prover<RingT>(
+        cache: CachedProver<RingT>,
+        code: &dyn LpnCode<RingT>,
+    ) -> MozzarellaProver<'_, RingT>
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        MozzarellaProver::new(
+            cache,
+            code,
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES,
+            SINGLE_SP_OUTPUT_SIZE,
+            false,
+        )
+    }
+
+    fn new_verifier<RingT>(
+        cache: CachedVerifier<RingT>,
+        code: &dyn LpnCode<RingT>,
+    ) -> MozzarellaVerifier<'_, RingT>
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        MozzarellaVerifier::new(
+            cache,
+            code,
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES,
+            SINGLE_SP_OUTPUT_SIZE,
+            false,
+        )
+    }
+
+    fn spawn_pools(watermark: usize) -> (ProverPool<R64>, VerifierPool<R64>, R64) {
+        let (code, cached_prover, cached_verifier, delta) = gen_code_and_caches::<R64>(REQUIRED);
+        let (channel_p, channel_v) = unix_channel_pair();
+        let prover_pool = ProverPool::<R64>::spawn(
+            channel_p,
+            cached_prover,
+            code.clone(),
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES,
+            SINGLE_SP_OUTPUT_SIZE,
+            false,
+            watermark,
+        );
+        let verifier_pool = VerifierPool::<R64>::spawn(
+            channel_v,
+            cached_verifier,
+            code,
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES,
+            SINGLE_SP_OUTPUT_SIZE,
+            false,
+            delta,
+            watermark,
+        );
+        (prover_pool, verifier_pool, delta)
+    }
+
+    fn assert_correlated<RingT>(us: &[RingT], ws: &[RingT], vs: &[RingT], delta: RingT)
+    where
+        RingT: Ring,
+        Standard: Distribution<RingT>,
+    {
+        assert_eq!(us.len(), vs.len());
+        assert_eq!(ws.len(), vs.len());
+        for i in 0..vs.len() {
+            assert_eq!(ws[i], delta * us[i] + vs[i]);
+        }
+    }
+
+    fn test_vole_extension<RingT, const NIGHTLY: bool, const SUB_BATCHES: usize>()
     where
         RingT: Ring + Receivable,
         Standard: Distribution<RingT>,
@@ -112,12 +280,13 @@ mod tests {
             let prover_thread = spawn(move || {
                 let mut prover = MozzarellaProver::<RingT>::new(
                     cached_prover,
-                    &code_p,
+                    &*code_p,
                     BASE_VOLE_LEN,
                     NUM_SP_VOLES,
                     SINGLE_SP_OUTPUT_SIZE,
                     NIGHTLY,
                 );
+                prover.set_sp_vole_sub_batches(SUB_BATCHES);
                 prover.init(&mut channel_p).unwrap();
                 prover.base_extend(&mut channel_p).unwrap()
             });
@@ -125,12 +294,13 @@ mod tests {
             let verifier_thread = spawn(move || {
                 let mut verifier = MozzarellaVerifier::<RingT>::new(
                     cached_verifier,
-                    &code_v,
+                    &*code_v,
                     BASE_VOLE_LEN,
                     NUM_SP_VOLES,
                     SINGLE_SP_OUTPUT_SIZE,
                     NIGHTLY,
                 );
+                verifier.set_sp_vole_sub_batches(SUB_BATCHES);
                 verifier.init(&mut channel_v, delta).unwrap();
                 verifier.base_extend(&mut channel_v).unwrap()
             });
@@ -150,18 +320,546 @@ mod tests {
         }
     }
 
+    fn test_vole_extension_with_setup<RingT>()
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        const SETUP_BASE_VOLE_LEN: usize = 10;
+        const SETUP_NUM_SP_VOLES: usize = 4;
+        const SETUP_SINGLE_SP_OUTPUT_SIZE: usize = 16;
+        const BASE_VOLE_LEN: usize = 20;
+        const NUM_SP_VOLES: usize = 4;
+        const SINGLE_SP_OUTPUT_SIZE: usize = 32;
+        const OUTPUT_SIZE: usize = NUM_SP_VOLES * SINGLE_SP_OUTPUT_SIZE;
+        // only enough for the setup iteration
+        const CACHE_SIZE: usize = reg_vole_required(SETUP_BASE_VOLE_LEN, SETUP_NUM_SP_VOLES);
+
+        let setup_code = Arc::new(LLCode::<RingT>::from_seed(
+            SETUP_BASE_VOLE_LEN,
+            SETUP_NUM_SP_VOLES * SETUP_SINGLE_SP_OUTPUT_SIZE,
+            4,
+            Block::default(),
+        ));
+        let code = Arc::new(LLCode::<RingT>::from_seed(
+            BASE_VOLE_LEN,
+            OUTPUT_SIZE,
+            CODE_D,
+            Block::default(),
+        ));
+        let mut rng = OsRng;
+        let delta = rng.gen::<RingT>().reduce();
+        let (cached_prover, cached_verifier) = GenCache::new_with_size(rng, delta, CACHE_SIZE);
+
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let (setup_code_p, code_p) = (setup_code.clone(), code.clone());
+        let (setup_code_v, code_v) = (setup_code.clone(), code.clone());
+
+        let prover_thread = spawn(move || {
+            let mut prover = MozzarellaProver::<RingT>::new(
+                cached_prover,
+                &*code_p,
+                BASE_VOLE_LEN,
+                NUM_SP_VOLES,
+                SINGLE_SP_OUTPUT_SIZE,
+                false,
+            );
+            prover.init(&mut channel_p).unwrap();
+            prover
+                .setup(
+                    &mut channel_p,
+                    &*setup_code_p,
+                    SETUP_NUM_SP_VOLES,
+                    SETUP_SINGLE_SP_OUTPUT_SIZE,
+                )
+                .unwrap();
+            prover.base_extend(&mut channel_p).unwrap()
+        });
+
+        let verifier_thread = spawn(move || {
+            let mut verifier = MozzarellaVerifier::<RingT>::new(
+                cached_verifier,
+                &*code_v,
+                BASE_VOLE_LEN,
+                NUM_SP_VOLES,
+                SINGLE_SP_OUTPUT_SIZE,
+                false,
+            );
+            verifier.init(&mut channel_v, delta).unwrap();
+            verifier
+                .setup(
+                    &mut channel_v,
+                    &*setup_code_v,
+                    SETUP_NUM_SP_VOLES,
+                    SETUP_SINGLE_SP_OUTPUT_SIZE,
+                )
+                .unwrap();
+            verifier.base_extend(&mut channel_v).unwrap()
+        });
+
+        let (out_u, out_w) = prover_thread.join().unwrap();
+        let out_v = verifier_thread.join().unwrap();
+
+        assert_eq!(out_u.len(), OUTPUT_SIZE);
+        assert_eq!(out_v.len(), OUTPUT_SIZE);
+        for i in 0..OUTPUT_SIZE {
+            assert_eq!(out_w[i], delta * out_u[i] + out_v[i]);
+        }
+    }
+
+    fn test_vole_extension_with_fresh_code<RingT>()
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        // both parties start with the default code, but replace it in each session
+        let (code, cached_prover, cached_verifier, delta) = gen_code_and_caches::<RingT>(REQUIRED);
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let code_p = code.clone();
+        let code_v = code.clone();
+
+        let prover_thread = spawn(move || {
+            let mut prover = new_prover::<RingT>(cached_prover, &*code_p);
+            let code_seed = cointoss_code_seed(&mut channel_p, &mut OsRng, true).unwrap();
+            prover
+                .init_with_code_seed(&mut channel_p, code_seed)
+                .unwrap();
+            assert_eq!(
+                prover.get_code().digest(),
+                code_p.with_seed(code_seed).digest()
+            );
+            let out = prover.base_extend(&mut channel_p).unwrap();
+            (out, code_seed)
+        });
+
+        let verifier_thread = spawn(move || {
+            let mut verifier = new_verifier::<RingT>(cached_verifier, &*code_v);
+            let code_seed = cointoss_code_seed(&mut channel_v, &mut OsRng, false).unwrap();
+            verifier
+                .init_with_code_seed(&mut channel_v, delta, code_seed)
+                .unwrap();
+            let out = verifier.base_extend(&mut channel_v).unwrap();
+            (out, code_seed)
+        });
+
+        let ((out_u, out_w), code_seed_p) = prover_thread.join().unwrap();
+        let (out_v, code_seed_v) = verifier_thread.join().unwrap();
+
+        assert_eq!(code_seed_p, code_seed_v);
+        assert_ne!(code_seed_p, Block::default());
+        assert_eq!(out_v.len(), OUTPUT_SIZE);
+        assert_correlated(&out_u, &out_w, &out_v, delta);
+    }
+
+    #[test]
+    fn test_vole_extension_ea_code() {
+        let (_, cached_prover, cached_verifier, delta) = gen_code_and_caches::<R64>(REQUIRED);
+        let code: Arc<dyn LpnCode<R64>> = Arc::new(EACode::<R64>::from_seed(
+            BASE_VOLE_LEN,
+            OUTPUT_SIZE,
+            EA_CODE_D,
+            Block::default(),
+        ));
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let code_p = code.clone();
+
+        let prover_thread = spawn(move || {
+            let mut prover = new_prover::<R64>(cached_prover, &*code_p);
+            prover.init(&mut channel_p).unwrap();
+            prover.base_extend(&mut channel_p).unwrap()
+        });
+        let mut verifier = new_verifier::<R64>(cached_verifier, &*code);
+        verifier.init(&mut channel_v, delta).unwrap();
+        let vs = verifier.base_extend(&mut channel_v).unwrap();
+        let (us, ws) = prover_thread.join().unwrap();
+
+        assert_eq!(vs.len(), OUTPUT_SIZE);
+        assert_correlated(&us, &ws, &vs, delta);
+    }
+
+    #[test]
+    fn test_vole_traits() {
+        const NUM_VOLES: usize = 100;
+
+        let (code, cached_prover, cached_verifier, delta) = gen_code_and_caches::<R64>(REQUIRED);
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let code_p = code.clone();
+
+        let prover_thread = spawn(move || {
+            let mut prover = new_prover::<R64>(cached_prover, &*code_p);
+            prover.init(&mut channel_p).unwrap();
+            prover
+                .receive_vole(&mut channel_p, NUM_VOLES, &mut OsRng)
+                .unwrap()
+        });
+        let mut verifier = new_verifier::<R64>(cached_verifier, &*code);
+        verifier.init(&mut channel_v, delta).unwrap();
+        let vs = verifier
+            .send_vole(&mut channel_v, NUM_VOLES, &mut OsRng)
+            .unwrap();
+        let (us, ws) = prover_thread.join().unwrap();
+
+        assert_eq!(verifier.get_delta(), delta);
+        assert_eq!(vs.len(), NUM_VOLES);
+        assert_correlated(&us, &ws, &vs, delta);
+    }
+
+    #[test]
+    fn test_vole_stream() {
+        const NUM_VOLES: usize = 100;
+        const CHUNK_SIZE: usize = 7;
+
+        let (code, cached_prover, cached_verifier, delta) = gen_code_and_caches::<R64>(REQUIRED);
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let code_p = code.clone();
+
+        let prover_thread = spawn(move || {
+            let mut prover = new_prover::<R64>(cached_prover, &*code_p);
+            prover.init(&mut channel_p).unwrap();
+            let mut us = vec![R64::default(); NUM_VOLES];
+            let mut ws = vec![R64::default(); NUM_VOLES];
+            prover
+                .extend_into(&mut channel_p, &mut us, &mut ws)
+                .unwrap();
+            let mut count = 0;
+            for chunk in prover.stream(&mut channel_p, CHUNK_SIZE) {
+                let (u, w) = chunk.unwrap();
+                assert!(!u.is_empty() && u.len() <= CHUNK_SIZE);
+                count += u.len();
+                us.extend(u);
+                ws.extend(w);
+                if count >= NUM_VOLES {
+                    break;
+                }
+            }
+            (us, ws)
+        });
+        let mut verifier = new_verifier::<R64>(cached_verifier, &*code);
+        verifier.init(&mut channel_v, delta).unwrap();
+        let mut vs = vec![R64::default(); NUM_VOLES];
+        verifier.extend_into(&mut channel_v, &mut vs).unwrap();
+        let mut count = 0;
+        for chunk in verifier.stream(&mut channel_v, CHUNK_SIZE) {
+            let v = chunk.unwrap();
+            count += v.len();
+            vs.extend(v);
+            if count >= NUM_VOLES {
+                break;
+            }
+        }
+        let (us, ws) = prover_thread.join().unwrap();
+
+        assert_correlated(&us, &ws, &vs, delta);
+    }
+
+    #[test]
+    fn test_multi_instance() {
+        const NUM_INSTANCES: usize = 3;
+        const NUM_VOLES: usize = 200;
+
+        let (code, cached_prover, cached_verifier, delta) =
+            gen_code_and_caches::<R64>(NUM_INSTANCES * REQUIRED);
+        let (mut channels_p, mut channels_v): (Vec<_>, Vec<_>) =
+            (0..NUM_INSTANCES).map(|_| unix_channel_pair()).unzip();
+        let code_p = code.clone();
+
+        let prover_thread = spawn(move || {
+            let provers = cached_prover
+                .partition(NUM_INSTANCES)
+                .into_iter()
+                .map(|cache| new_prover::<R64>(cache, &*code_p))
+                .collect();
+            let mut prover = MultiProver::<R64>::new(provers);
+            prover.init(&mut channels_p).unwrap();
+            let (mut us, mut ws) = prover.extend(&mut channels_p, NUM_VOLES).unwrap();
+            let (u, w) = prover.extend(&mut channels_p, 1).unwrap();
+            us.extend(u);
+            ws.extend(w);
+            (us, ws)
+        });
+        let verifiers = cached_verifier
+            .partition(NUM_INSTANCES)
+            .into_iter()
+            .map(|cache| new_verifier::<R64>(cache, &*code))
+            .collect();
+        let mut verifier = MultiVerifier::<R64>::new(verifiers);
+        verifier.init(&mut channels_v, delta).unwrap();
+        let mut vs = verifier.extend(&mut channels_v, NUM_VOLES).unwrap();
+        vs.extend(verifier.extend(&mut channels_v, 1).unwrap());
+        let (us, ws) = prover_thread.join().unwrap();
+
+        assert_eq!(verifier.get_delta(), delta);
+        assert_eq!(vs.len(), NUM_VOLES + 1);
+        assert_correlated(&us, &ws, &vs, delta);
+    }
+
+    #[test]
+    fn test_vole_pool() {
+        const WATERMARK: usize = 50;
+        const CHUNK_SIZES: [usize; 4] = [1, 10, 200, 33];
+
+        let (prover_pool, verifier_pool, delta) = spawn_pools(WATERMARK);
+        assert_eq!(verifier_pool.get_delta(), delta);
+
+        // consume the prover's VOLEs on another thread
+        let prover_thread = spawn(move || {
+            CHUNK_SIZES
+                .iter()
+                .map(|&n| prover_pool.extend(n).unwrap())
+                .collect::<Vec<_>>()
+        });
+        let vs: Vec<Vec<R64>> = CHUNK_SIZES
+            .iter()
+            .map(|&n| verifier_pool.extend(n).unwrap())
+            .collect();
+        let uws = prover_thread.join().unwrap();
+
+        for ((us, ws), vs) in uws.iter().zip(vs.iter()) {
+            assert_correlated(us, ws, vs, delta);
+        }
+    }
+
+    #[test]
+    fn test_vole_pool_drop() {
+        const WATERMARK: usize = 50;
+
+        let (prover_pool, verifier_pool, _) = spawn_pools(WATERMARK);
+        prover_pool.extend(1).unwrap();
+        verifier_pool.extend(1).unwrap();
+
+        // the verifier asks for more VOLEs than the prover's worker will ever produce
+        let verifier_thread = spawn(move || verifier_pool.extend(10 * WATERMARK).is_err());
+        drop(prover_pool);
+        assert!(verifier_thread.join().unwrap());
+    }
+
+    fn test_leakage_budget_with_sub_batches<const SUB_BATCHES: usize>() {
+        let (code, cached_prover, cached_verifier, delta) =
+            gen_code_and_caches::<R64>(3 * REQUIRED);
+        let (fresh_cached_prover, fresh_cached_verifier) =
+            GenCache::new_with_size(OsRng, delta, REQUIRED);
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let code_v = code.clone();
+
+        let verifier_thread = spawn(move || {
+            let mut verifier = new_verifier::<R64>(cached_verifier, &*code_v);
+            verifier.set_sp_vole_sub_batches(SUB_BATCHES);
+            verifier.init(&mut channel_v, delta).unwrap();
+            let mut outputs = vec![];
+            for _ in 0..2 {
+                outputs.push(verifier.base_extend(&mut channel_v).unwrap());
+            }
+            // the prover aborts instead of leaving the verifier waiting
+            assert!(verifier.base_extend(&mut channel_v).is_err());
+            verifier.refresh_cache(fresh_cached_verifier);
+            outputs.push(verifier.base_extend(&mut channel_v).unwrap());
+            outputs
+        });
+
+        let mut prover = new_prover::<R64>(cached_prover, &*code);
+        prover.set_leakage_budget(Some(2 * SUB_BATCHES));
+        prover.set_sp_vole_sub_batches(SUB_BATCHES);
+        prover.init(&mut channel_p).unwrap();
+        let mut outputs = vec![];
+        for _ in 0..2 {
+            outputs.push(prover.base_extend(&mut channel_p).unwrap());
+        }
+        assert_eq!(prover.get_stats().sp_stats.leaked_bits, 2 * SUB_BATCHES);
+        assert_eq!(
+            prover.get_stats().sp_stats.remaining_leakage_budget,
+            Some(0)
+        );
+        // the budget is exhausted, although there are enough base VOLEs left
+        assert!(prover.base_extend(&mut channel_p).is_err());
+        prover.refresh_cache(fresh_cached_prover);
+        outputs.push(prover.base_extend(&mut channel_p).unwrap());
+        assert_eq!(
+            prover.get_stats().sp_stats.remaining_leakage_budget,
+            Some(SUB_BATCHES)
+        );
+
+        let vs = verifier_thread.join().unwrap();
+        for ((us, ws), vs) in outputs.iter().zip(vs.iter()) {
+            assert_correlated(us, ws, vs, delta);
+        }
+    }
+
+    #[test]
+    fn test_leakage_budget() {
+        test_leakage_budget_with_sub_batches::<1>();
+        test_leakage_budget_with_sub_batches::<2>();
+    }
+
+    #[test]
+    fn test_rekey() {
+        const SUB_BATCHES: usize = 2;
+
+        let (code, cached_prover, cached_verifier, delta) = gen_code_and_caches::<R64>(REQUIRED);
+        let deltas = [delta, OsRng.gen::<R64>()];
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let code_v = code.clone();
+
+        let verifier_thread = spawn(move || {
+            let mut verifier = new_verifier::<R64>(cached_verifier, &*code_v);
+            verifier.set_sp_vole_sub_batches(SUB_BATCHES);
+            verifier.init(&mut channel_v, deltas[0]).unwrap();
+            let mut outputs = vec![verifier.base_extend(&mut channel_v).unwrap()];
+            verifier.rekey(&mut channel_v, deltas[1], REQUIRED).unwrap();
+            assert_eq!(verifier.get_delta(), deltas[1]);
+            outputs.push(verifier.base_extend(&mut channel_v).unwrap());
+            outputs
+        });
+
+        let mut prover = new_prover::<R64>(cached_prover, &*code);
+        prover.set_leakage_budget(Some(SUB_BATCHES));
+        prover.set_sp_vole_sub_batches(SUB_BATCHES);
+        prover.init(&mut channel_p).unwrap();
+        let mut outputs = vec![prover.base_extend(&mut channel_p).unwrap()];
+        // the new base VOLEs come with a fresh leakage budget
+        prover.rekey(&mut channel_p, REQUIRED).unwrap();
+        outputs.push(prover.base_extend(&mut channel_p).unwrap());
+
+        let vs = verifier_thread.join().unwrap();
+        for (((us, ws), vs), &delta) in outputs.iter().zip(vs.iter()).zip(deltas.iter()) {
+            assert_correlated(us, ws, vs, delta);
+        }
+    }
+
+    #[test]
+    fn test_resume() {
+        const SUB_BATCHES: usize = 2;
+
+        let (code, cached_prover, cached_verifier, delta) =
+            gen_code_and_caches::<R64>(4 * REQUIRED);
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let (mut channel_p2, mut channel_v2) = unix_channel_pair();
+        let code_v = code.clone();
+
+        let verifier_thread = spawn(move || {
+            let mut verifier = new_verifier::<R64>(cached_verifier, &*code_v);
+            verifier.set_sp_vole_sub_batches(SUB_BATCHES);
+            verifier.init(&mut channel_v, delta).unwrap();
+            verifier.enable_checkpoints();
+            let mut outputs = vec![verifier.base_extend(&mut channel_v).unwrap()];
+            // the connection drops before the second iteration
+            drop(channel_v);
+            assert_eq!(verifier.resume(&mut channel_v2).unwrap(), 1);
+            outputs.push(verifier.base_extend(&mut channel_v2).unwrap());
+            assert_eq!(verifier.completed_iterations(), 2);
+            outputs
+        });
+
+        let mut prover = new_prover::<R64>(cached_prover, &*code);
+        prover.set_sp_vole_sub_batches(SUB_BATCHES);
+        prover.init(&mut channel_p).unwrap();
+        prover.enable_checkpoints();
+        let mut outputs = vec![prover.base_extend(&mut channel_p).unwrap()];
+        assert!(prover.base_extend(&mut channel_p).is_err());
+        assert_eq!(prover.completed_iterations(), 1);
+        assert_eq!(prover.resume(&mut channel_p2).unwrap(), 1);
+        outputs.push(prover.base_extend(&mut channel_p2).unwrap());
+
+        let vs = verifier_thread.join().unwrap();
+        for ((us, ws), vs) in outputs.iter().zip(vs.iter()) {
+            assert_correlated(us, ws, vs, delta);
+        }
+    }
+
+    #[test]
+    fn test_resume_after_extend() {
+        // more VOLEs than an iteration requires are taken from the cache between iterations
+        const N1: usize = REQUIRED + 10;
+        const N2: usize = OUTPUT_SIZE;
+        // taken from the cache that remains after resuming, without running an iteration
+        const N3: usize = REQUIRED;
+
+        let (code, cached_prover, cached_verifier, delta) =
+            gen_code_and_caches::<R64>(2 * REQUIRED);
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let (mut channel_p2, mut channel_v2) = unix_channel_pair();
+        let code_v = code.clone();
+
+        let verifier_thread = spawn(move || {
+            let mut verifier = new_verifier::<R64>(cached_verifier, &*code_v);
+            verifier.init(&mut channel_v, delta).unwrap();
+            verifier.enable_checkpoints();
+            let mut vs = verifier.extend(&mut channel_v, N1).unwrap();
+            // the connection drops while the prover replenishes the cache
+            drop(channel_v);
+            assert_eq!(verifier.resume(&mut channel_v2).unwrap(), 1);
+            for &n in &[N3, N2] {
+                vs.extend(verifier.extend(&mut channel_v2, n).unwrap());
+            }
+            vs
+        });
+
+        let mut prover = new_prover::<R64>(cached_prover, &*code);
+        prover.init(&mut channel_p).unwrap();
+        prover.enable_checkpoints();
+        let (mut us, mut ws) = prover.extend(&mut channel_p, N1).unwrap();
+        assert!(prover.extend(&mut channel_p, N2).is_err());
+        assert_eq!(prover.resume(&mut channel_p2).unwrap(), 1);
+        for &n in &[N3, N2] {
+            let (us2, ws2) = prover.extend(&mut channel_p2, n).unwrap();
+            us.extend(us2);
+            ws.extend(ws2);
+        }
+
+        let vs = verifier_thread.join().unwrap();
+        assert_eq!(vs.len(), N1 + N2 + N3);
+        assert_correlated(&us, &ws, &vs, delta);
+        // no VOLE is returned twice
+        for i in 0..vs.len() {
+            for j in 0..i {
+                assert!(us[i] != us[j] || ws[i] != ws[j]);
+            }
+        }
+    }
+
     #[test]
     fn test_vole_extension_r64() {
-        test_vole_extension::<R64, false>();
+        test_vole_extension::<R64, false, 1>();
     }
 
     #[test]
     fn test_vole_extension_r104() {
-        test_vole_extension::<z2r::R104, false>();
+        test_vole_extension::<z2r::R104, false, 1>();
     }
 
     #[test]
     fn test_vole_extension_r144() {
-        test_vole_extension::<z2r::R144, false>();
+        test_vole_extension::<z2r::R144, false, 1>();
+    }
+
+    #[test]
+    fn test_vole_extension_f61p() {
+        test_vole_extension::<F61p, false, 1>();
+    }
+
+    #[test]
+    fn test_vole_extension_pipelined_r64() {
+        test_vole_extension::<R64, false, 3>();
+    }
+
+    #[test]
+    fn test_vole_extension_with_fresh_code_r64() {
+        test_vole_extension_with_fresh_code::<R64>();
+    }
+
+    #[test]
+    fn test_vole_extension_with_setup_r64() {
+        test_vole_extension_with_setup::<R64>();
+    }
+
+    #[test]
+    fn test_vole_extension_with_setup_r144() {
+        test_vole_extension_with_setup::<z2r::R144>();
+    }
+
+    #[test]
+    fn test_vole_extension_with_setup_f61p() {
+        test_vole_extension_with_setup::<F61p>();
     }
 }
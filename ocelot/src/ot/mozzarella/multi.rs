@@ -0,0 +1,231 @@
+//! Run several independent Mozzarella instances in parallel, each on its own channel.
+//!
+//! All instances use the same `delta`, but each needs its own partition of the base VOLE cache
+//! (see `CachedProver::partition` and `CachedVerifier::partition`).  The outputs are merged into
+//! one stream: a request for `n` VOLEs is split into `num_instances` consecutive parts, where the
+//! `i`-th part is taken from instance `i`.
+use crate::{
+    ot::mozzarella::{Prover, Verifier},
+    Error,
+};
+use rand::distributions::{Distribution, Standard};
+use scuttlebutt::{
+    channel::{Receivable, Sendable},
+    ring::Ring,
+    AbstractChannel,
+};
+
+// sizes of the parts that are taken from each instance
+fn part_sizes(n: usize, num_instances: usize) -> Vec<usize> {
+    (0..num_instances)
+        .map(|i| n / num_instances + if i < n % num_instances { 1 } else { 0 })
+        .collect()
+}
+
+// split `buffer` into consecutive chunks of the given sizes
+fn split_parts<'b, T>(mut buffer: &'b mut [T], sizes: &[usize]) -> Vec<&'b mut [T]> {
+    let mut parts = Vec::with_capacity(sizes.len());
+    for &size in sizes {
+        let (part, rest) = buffer.split_at_mut(size);
+        parts.push(part);
+        buffer = rest;
+    }
+    parts
+}
+
+// run `f` on every item in its own thread (not on the rayon pool, since the instances block on
+// their channels)
+fn run_parallel<T, F>(items: Vec<T>, f: F) -> Result<(), Error>
+where
+    T: Send,
+    F: Fn(T) -> Result<(), Error> + Sync,
+{
+    let f = &f;
+    crossbeam::scope(|scope| {
+        let handles: Vec<_> = items
+            .into_iter()
+            .map(|item| scope.spawn(move |_| f(item)))
+            .collect();
+        handles
+            .into_iter()
+            .try_for_each(|handle| handle.join().unwrap())
+    })
+    .unwrap()
+}
+
+pub struct MultiProver<'a, RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    instances: Vec<Prover<'a, RingT>>,
+}
+
+impl<'a, RingT> MultiProver<'a, RingT>
+where
+    RingT: Ring + Receivable + Send + Sync,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    pub fn new(instances: Vec<Prover<'a, RingT>>) -> Self {
+        assert!(!instances.is_empty());
+        Self { instances }
+    }
+
+    pub fn num_instances(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn get_instances(&self) -> &[Prover<'a, RingT>] {
+        &self.instances
+    }
+
+    pub fn init<C: AbstractChannel + Send>(&mut self, channels: &mut [C]) -> Result<(), Error> {
+        assert_eq!(channels.len(), self.instances.len());
+        run_parallel(
+            self.instances.iter_mut().zip(channels.iter_mut()).collect(),
+            |(prover, channel)| prover.init(channel),
+        )
+    }
+
+    /// Counterpart of `MultiVerifier::rekey`.
+    pub fn rekey<C: AbstractChannel + Send>(
+        &mut self,
+        channels: &mut [C],
+        num_base_voles: usize,
+    ) -> Result<(), Error> {
+        assert_eq!(channels.len(), self.instances.len());
+        run_parallel(
+            self.instances.iter_mut().zip(channels.iter_mut()).collect(),
+            |(prover, channel)| prover.rekey(channel, num_base_voles),
+        )
+    }
+
+    pub fn extend_into<C: AbstractChannel + Send>(
+        &mut self,
+        channels: &mut [C],
+        u_out: &mut [RingT],
+        w_out: &mut [RingT],
+    ) -> Result<(), Error> {
+        assert_eq!(channels.len(), self.instances.len());
+        assert_eq!(u_out.len(), w_out.len());
+        let sizes = part_sizes(u_out.len(), self.instances.len());
+        run_parallel(
+            self.instances
+                .iter_mut()
+                .zip(channels.iter_mut())
+                .zip(split_parts(u_out, &sizes))
+                .zip(split_parts(w_out, &sizes))
+                .collect(),
+            |(((prover, channel), u), w)| prover.extend_into(channel, u, w),
+        )
+    }
+
+    pub fn extend<C: AbstractChannel + Send>(
+        &mut self,
+        channels: &mut [C],
+        n: usize,
+    ) -> Result<(Vec<RingT>, Vec<RingT>), Error> {
+        let mut u = vec![Default::default(); n];
+        let mut w = vec![Default::default(); n];
+        self.extend_into(channels, &mut u, &mut w)?;
+        Ok((u, w))
+    }
+}
+
+pub struct MultiVerifier<'a, RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    instances: Vec<Verifier<'a, RingT>>,
+    delta: RingT,
+}
+
+impl<'a, RingT> MultiVerifier<'a, RingT>
+where
+    RingT: Ring + Receivable + Send + Sync,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    pub fn new(instances: Vec<Verifier<'a, RingT>>) -> Self {
+        assert!(!instances.is_empty());
+        Self {
+            instances,
+            delta: Default::default(),
+        }
+    }
+
+    pub fn num_instances(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn get_instances(&self) -> &[Verifier<'a, RingT>] {
+        &self.instances
+    }
+
+    pub fn get_delta(&self) -> RingT {
+        self.delta
+    }
+
+    /// Initialize all instances with the same `delta`.
+    pub fn init<C: AbstractChannel + Send>(
+        &mut self,
+        channels: &mut [C],
+        delta: RingT,
+    ) -> Result<(), Error> {
+        assert_eq!(channels.len(), self.instances.len());
+        run_parallel(
+            self.instances.iter_mut().zip(channels.iter_mut()).collect(),
+            |(verifier, channel)| verifier.init(channel, delta),
+        )?;
+        self.delta = delta.reduce();
+        Ok(())
+    }
+
+    /// Switch all instances to the same fresh `delta`, each with `num_base_voles` new base VOLEs
+    /// (see `Verifier::rekey`).
+    pub fn rekey<C: AbstractChannel + Send>(
+        &mut self,
+        channels: &mut [C],
+        delta: RingT,
+        num_base_voles: usize,
+    ) -> Result<(), Error> {
+        assert_eq!(channels.len(), self.instances.len());
+        run_parallel(
+            self.instances.iter_mut().zip(channels.iter_mut()).collect(),
+            |(verifier, channel)| verifier.rekey(channel, delta, num_base_voles),
+        )?;
+        self.delta = delta.reduce();
+        Ok(())
+    }
+
+    pub fn extend_into<C: AbstractChannel + Send>(
+        &mut self,
+        channels: &mut [C],
+        v_out: &mut [RingT],
+    ) -> Result<(), Error> {
+        assert_eq!(channels.len(), self.instances.len());
+        let sizes = part_sizes(v_out.len(), self.instances.len());
+        run_parallel(
+            self.instances
+                .iter_mut()
+                .zip(channels.iter_mut())
+                .zip(split_parts(v_out, &sizes))
+                .collect(),
+            |((verifier, channel), v)| verifier.extend_into(channel, v),
+        )
+    }
+
+    pub fn extend<C: AbstractChannel + Send>(
+        &mut self,
+        channels: &mut [C],
+        n: usize,
+    ) -> Result<Vec<RingT>, Error> {
+        let mut v = vec![Default::default(); n];
+        self.extend_into(channels, &mut v)?;
+        Ok(v)
+    }
+}
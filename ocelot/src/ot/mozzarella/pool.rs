@@ -0,0 +1,279 @@
+//! Pools of VOLEs that are refilled in the background.
+//!
+//! A pool wraps a Mozzarella prover or verifier that runs on a worker thread with its own
+//! channel.  Whenever fewer than `watermark` VOLEs are ready, the worker runs another extension,
+//! so consumers (possibly on several threads) rarely have to wait for a full extension round
+//! trip.  The prover and verifier pool need to be created with the same parameters, and VOLEs are
+//! handed out in the same (FIFO) order on both sides.
+//!
+//! Dropping a pool shuts down its worker and waits for it.  A worker in the middle of an extension
+//! only returns once the other party's worker takes part in it or exits, so if consumers on one
+//! side still wait for VOLEs, drop the pool of that side last (or on another thread).  The other
+//! side's consumers then get an error instead of blocking.
+use crate::{
+    ot::mozzarella::{
+        cache::{prover::CachedProver, verifier::CachedVerifier},
+        handshake::SessionParameters,
+        lpn::LpnCode,
+        Prover, Verifier,
+    },
+    Error,
+};
+use rand::distributions::{Distribution, Standard};
+use scuttlebutt::{
+    channel::{Receivable, Sendable},
+    ring::Ring,
+    AbstractChannel,
+};
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+};
+
+struct PoolState<T> {
+    ready: VecDeque<T>,
+    // number of VOLEs requested by waiting consumers
+    demand: usize,
+    error: Option<String>,
+    shutdown: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<PoolState<T>>,
+    cond: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(PoolState {
+                ready: VecDeque::new(),
+                demand: 0,
+                error: None,
+                shutdown: false,
+            }),
+            cond: Condvar::new(),
+        }
+    }
+
+    // block the worker until a refill is needed, returns false if the pool is shut down
+    fn wait_for_demand(&self, watermark: usize) -> bool {
+        let mut state = self.state.lock().unwrap();
+        while !state.shutdown && state.ready.len() >= usize::max(watermark, state.demand) {
+            state = self.cond.wait(state).unwrap();
+        }
+        !state.shutdown
+    }
+
+    fn push<I: Iterator<Item = T>>(&self, items: I) {
+        let mut state = self.state.lock().unwrap();
+        state.ready.extend(items);
+        self.cond.notify_all();
+    }
+
+    fn fail(&self, error: Error) {
+        let mut state = self.state.lock().unwrap();
+        state.error = Some(error.to_string());
+        self.cond.notify_all();
+    }
+
+    fn take(&self, n: usize) -> Result<Vec<T>, Error> {
+        let mut state = self.state.lock().unwrap();
+        state.demand += n;
+        self.cond.notify_all();
+        while state.ready.len() < n && state.error.is_none() {
+            state = self.cond.wait(state).unwrap();
+        }
+        state.demand -= n;
+        if state.ready.len() < n {
+            return Err(Error::Other(format!(
+                "VOLE pool worker failed: {}",
+                state.error.as_ref().unwrap()
+            )));
+        }
+        let out = state.ready.drain(..n).collect();
+        // wake up the worker if we went below the watermark
+        self.cond.notify_all();
+        Ok(out)
+    }
+
+    fn available(&self) -> usize {
+        self.state.lock().unwrap().ready.len()
+    }
+
+    fn shutdown(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.shutdown = true;
+        self.cond.notify_all();
+    }
+}
+
+fn pool_parameters(watermark: usize) -> SessionParameters {
+    SessionParameters::new("MozzarellaPool").add("watermark", watermark)
+}
+
+pub struct ProverPool<RingT> {
+    shared: Arc<Shared<(RingT, RingT)>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<RingT> ProverPool<RingT>
+where
+    RingT: Ring + Receivable + Send + Sync + 'static,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    /// Start a worker thread that runs a prover with the given parameters over `channel`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn<C: AbstractChannel + Send + 'static>(
+        mut channel: C,
+        cache: CachedProver<RingT>,
+        code: Arc<dyn LpnCode<RingT>>,
+        base_vole_len: usize,
+        num_sp_voles: usize,
+        sp_vole_single_len: usize,
+        nightly_version: bool,
+        watermark: usize,
+    ) -> Self {
+        let shared = Arc::new(Shared::new());
+        let worker_shared = shared.clone();
+        let worker = thread::spawn(move || {
+            let mut prover = Prover::new(
+                cache,
+                &*code,
+                base_vole_len,
+                num_sp_voles,
+                sp_vole_single_len,
+                nightly_version,
+            );
+            let result = (|| -> Result<(), Error> {
+                prover.init(&mut channel)?;
+                pool_parameters(watermark).exchange(&mut channel)?;
+                let mut stream = prover.stream(&mut channel, usize::MAX);
+                while worker_shared.wait_for_demand(watermark) {
+                    let (u, w) = stream.next().unwrap()?;
+                    worker_shared.push(u.into_iter().zip(w));
+                }
+                Ok(())
+            })();
+            if let Err(e) = result {
+                worker_shared.fail(e);
+            }
+        });
+        Self {
+            shared,
+            worker: Some(worker),
+        }
+    }
+
+    pub fn vole(&self) -> Result<(RingT, RingT), Error> {
+        Ok(self.shared.take(1)?[0])
+    }
+
+    /// Take `n` VOLEs, blocking until enough of them are ready.
+    pub fn extend(&self, n: usize) -> Result<(Vec<RingT>, Vec<RingT>), Error> {
+        Ok(self.shared.take(n)?.into_iter().unzip())
+    }
+
+    /// Number of VOLEs that can be taken without blocking.
+    pub fn available(&self) -> usize {
+        self.shared.available()
+    }
+}
+
+impl<RingT> Drop for ProverPool<RingT> {
+    fn drop(&mut self) {
+        self.shared.shutdown();
+        if let Some(worker) = self.worker.take() {
+            // a panic of the worker has already been reported
+            let _ = worker.join();
+        }
+    }
+}
+
+pub struct VerifierPool<RingT> {
+    shared: Arc<Shared<RingT>>,
+    worker: Option<JoinHandle<()>>,
+    delta: RingT,
+}
+
+impl<RingT> VerifierPool<RingT>
+where
+    RingT: Ring + Receivable + Send + Sync + 'static,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    /// Start a worker thread that runs a verifier with the given parameters over `channel`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn<C: AbstractChannel + Send + 'static>(
+        mut channel: C,
+        cache: CachedVerifier<RingT>,
+        code: Arc<dyn LpnCode<RingT>>,
+        base_vole_len: usize,
+        num_sp_voles: usize,
+        sp_vole_single_len: usize,
+        nightly_version: bool,
+        delta: RingT,
+        watermark: usize,
+    ) -> Self {
+        let shared = Arc::new(Shared::new());
+        let worker_shared = shared.clone();
+        let worker = thread::spawn(move || {
+            let mut verifier = Verifier::new(
+                cache,
+                &*code,
+                base_vole_len,
+                num_sp_voles,
+                sp_vole_single_len,
+                nightly_version,
+            );
+            let result = (|| -> Result<(), Error> {
+                verifier.init(&mut channel, delta)?;
+                pool_parameters(watermark).exchange(&mut channel)?;
+                let mut stream = verifier.stream(&mut channel, usize::MAX);
+                while worker_shared.wait_for_demand(watermark) {
+                    let v = stream.next().unwrap()?;
+                    worker_shared.push(v.into_iter());
+                }
+                Ok(())
+            })();
+            if let Err(e) = result {
+                worker_shared.fail(e);
+            }
+        });
+        Self {
+            shared,
+            worker: Some(worker),
+            delta,
+        }
+    }
+
+    pub fn get_delta(&self) -> RingT {
+        self.delta
+    }
+
+    pub fn vole(&self) -> Result<RingT, Error> {
+        Ok(self.shared.take(1)?[0])
+    }
+
+    /// Take `n` VOLEs, blocking until enough of them are ready.
+    pub fn extend(&self, n: usize) -> Result<Vec<RingT>, Error> {
+        self.shared.take(n)
+    }
+
+    /// Number of VOLEs that can be taken without blocking.
+    pub fn available(&self) -> usize {
+        self.shared.available()
+    }
+}
+
+impl<RingT> Drop for VerifierPool<RingT> {
+    fn drop(&mut self) {
+        self.shared.shutdown();
+        if let Some(worker) = self.worker.take() {
+            // a panic of the worker has already been reported
+            let _ = worker.join();
+        }
+    }
+}
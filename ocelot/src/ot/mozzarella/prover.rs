@@ -1,12 +1,21 @@
 use super::*;
 use crate::{
-    ot::mozzarella::{
-        cache::prover::CachedProver,
-        spvole::prover::{BatchedProver as SpProver, BatchedProverStats as SpProverStats},
+    ot::{
+        mozzarella::{
+            cache::{bootstrap::BaseVoleProver, prover::CachedProver},
+            checkpoint::{self, Checkpoints},
+            spvole::{
+                pipeline::PipelinedProver as SpProver, prover::BatchedProverStats as SpProverStats,
+            },
+        },
+        VoleReceiver,
     },
     Error,
 };
-use rand::distributions::{Distribution, Standard};
+use rand::{
+    distributions::{Distribution, Standard},
+    CryptoRng, Rng,
+};
 use scuttlebutt::{
     channel::{Receivable, Sendable},
     ring::Ring,
@@ -25,16 +34,48 @@ where
     base_vole_len: usize,
     num_sp_voles: usize,
     sp_vole_single_len: usize,
-    sp_vole_total_len: usize,
     cache: CachedProver<RingT>,
-    code: &'a LLCode<RingT>,
+    buffers: ExtendBuffers<RingT>,
+    code: &'a dyn LpnCode<RingT>,
+    // code generated from a per-session seed, replaces `code`
+    session_code: Option<Box<dyn LpnCode<RingT>>>,
     nightly_version: bool, // with extra protocol optimizations
     is_init_done: bool,
+    // number of completed iterations and epoch of the OT extension PRGs
+    iteration: u64,
+    ot_epoch: u64,
+    checkpoints: Option<Checkpoints>,
     stats: ProverStats,
 }
 
+// buffers reused across iterations of the LPN extension
+struct ExtendBuffers<RingT> {
+    alphas: Vec<usize>,
+    e: Vec<RingT>,
+    c: Vec<RingT>,
+    u_old: Vec<RingT>,
+    w_old: Vec<RingT>,
+    x: Vec<RingT>,
+    z: Vec<RingT>,
+}
+
+impl<RingT: Copy + Default> ExtendBuffers<RingT> {
+    fn new(base_vole_len: usize, num_sp_voles: usize, sp_vole_total_len: usize) -> Self {
+        Self {
+            alphas: vec![0; num_sp_voles],
+            e: vec![Default::default(); sp_vole_total_len],
+            c: vec![Default::default(); sp_vole_total_len],
+            u_old: vec![Default::default(); base_vole_len],
+            w_old: vec![Default::default(); base_vole_len],
+            x: vec![Default::default(); sp_vole_total_len],
+            z: vec![Default::default(); sp_vole_total_len],
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Default, Serialize)]
 pub struct ProverStats {
+    pub setup_run_time: Duration,
     pub expansion_1_run_time: Duration,
     pub expansion_2_run_time: Duration,
     pub sp_stats: SpProverStats,
@@ -46,32 +87,39 @@ where
     Standard: Distribution<RingT>,
     for<'b> &'b RingT: Sendable,
 {
-    pub fn new_with_default_params(cache: CachedProver<RingT>, code: &'a LLCode<RingT>) -> Self {
+    pub fn new_with_default_params(
+        cache: CachedProver<RingT>,
+        code: &'a dyn LpnCode<RingT>,
+    ) -> Self {
         Self::new(cache, code, REG_MAIN_K, REG_MAIN_T, REG_MAIN_SPLEN, false)
     }
 
     pub fn new(
         cache: CachedProver<RingT>,
-        code: &'a LLCode<RingT>,
+        code: &'a dyn LpnCode<RingT>,
         base_vole_len: usize,
         num_sp_voles: usize,
         sp_vole_single_len: usize,
         nightly_version: bool,
     ) -> Self {
-        let spvole = SpProver::<RingT>::new(num_sp_voles, sp_vole_single_len, nightly_version);
+        let spvole = SpProver::<RingT>::new(num_sp_voles, sp_vole_single_len, 1, nightly_version);
         let sp_vole_total_len = sp_vole_single_len * num_sp_voles;
-        assert_eq!(code.rows, base_vole_len);
-        assert_eq!(code.columns, sp_vole_total_len);
+        assert_eq!(code.rows(), base_vole_len);
+        assert_eq!(code.columns(), sp_vole_total_len);
         Self {
             spvole,
             base_vole_len,
             num_sp_voles,
             sp_vole_single_len,
-            sp_vole_total_len,
             cache,
+            buffers: ExtendBuffers::new(base_vole_len, num_sp_voles, sp_vole_total_len),
             code,
+            session_code: None,
             nightly_version,
             is_init_done: false,
+            iteration: 0,
+            ot_epoch: 0,
+            checkpoints: None,
             stats: Default::default(),
         }
     }
@@ -80,12 +128,154 @@ where
         self.stats
     }
 
+    /// Run the SP-VOLEs in `num_sub_batches` pipelined sub-batches, such that communication and
+    /// computation overlap.  Needs to be called before `init`.
+    pub fn set_sp_vole_sub_batches(&mut self, num_sub_batches: usize) {
+        assert!(!self.is_init_done);
+        let leakage = self.spvole.get_leakage();
+        let tree_expansion = self.spvole.get_tree_expansion();
+        self.spvole = SpProver::<RingT>::new(
+            self.num_sp_voles,
+            self.sp_vole_single_len,
+            num_sub_batches,
+            self.nightly_version,
+        );
+        self.spvole.set_leakage(leakage);
+        self.spvole.set_tree_expansion(tree_expansion);
+    }
+
+    /// Select how the SP-VOLE trees are expanded.  Needs to be called before `init`, and the
+    /// verifier needs to use the same expansion.
+    pub fn set_tree_expansion(&mut self, expansion: TreeExpansion) {
+        assert!(!self.is_init_done);
+        self.spvole.set_tree_expansion(expansion);
+    }
+
+    /// Limit the number of bits about the noise positions that a malicious verifier may learn
+    /// via selective failures in this session (`None` means unlimited).  Once the budget is used
+    /// up, extensions fail until `refresh_cache` is called with freshly bootstrapped base VOLEs.
+    pub fn set_leakage_budget(&mut self, budget: Option<usize>) {
+        let mut leakage = self.spvole.get_leakage();
+        leakage.set_budget(budget);
+        self.spvole.set_leakage(leakage);
+        self.stats.sp_stats = self.spvole.get_stats();
+    }
+
+    /// Replace the base VOLE cache by freshly bootstrapped base VOLEs and reset the leakage.
+    /// The verifier needs to call `Verifier::refresh_cache` with the corresponding cache.
+    pub fn refresh_cache(&mut self, cache: CachedProver<RingT>) {
+        let mut leakage = self.spvole.get_leakage();
+        leakage.reset();
+        self.spvole.set_leakage(leakage);
+        self.stats.sp_stats = self.spvole.get_stats();
+        self.cache = cache;
+        if self.checkpoints.is_some() {
+            self.checkpoints = Some(Checkpoints::new());
+        }
+    }
+
+    /// Counterpart of `Verifier::rekey`: bootstrap `num_base_voles` base VOLEs for the
+    /// verifier's fresh `delta`, replace the cache by them and reset the leakage.
+    pub fn rekey<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        num_base_voles: usize,
+    ) -> Result<(), Error> {
+        assert!(self.is_init_done);
+        rekey_parameters(num_base_voles).exchange(channel)?;
+        let mut base_vole_prover = BaseVoleProver::<RingT>::new();
+        base_vole_prover.init(channel)?;
+        let cache = base_vole_prover.extend(channel, num_base_voles)?;
+        self.refresh_cache(cache);
+        Ok(())
+    }
+
+    pub fn get_code(&self) -> &dyn LpnCode<RingT> {
+        self.session_code.as_deref().unwrap_or(self.code)
+    }
+
+    /// Like `init`, but replace the code by a code of the same dimensions generated from
+    /// `code_seed` (e.g., obtained with `lpn::cointoss_code_seed`).
+    pub fn init_with_code_seed<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        code_seed: Block,
+    ) -> Result<(), Error> {
+        self.session_code = Some(self.code.with_seed(code_seed));
+        self.init(channel)
+    }
+
+    fn session_parameters(&self) -> SessionParameters {
+        session_parameters(
+            self.get_code(),
+            self.base_vole_len,
+            self.num_sp_voles,
+            self.sp_vole_single_len,
+            self.spvole.num_sub_batches(),
+            self.spvole.get_tree_expansion(),
+            self.nightly_version,
+        )
+    }
+
     pub fn init<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        self.session_parameters().exchange(channel)?;
         self.spvole.init(channel)?;
         self.is_init_done = true;
         Ok(())
     }
 
+    /// Record the length of the cache whenever an iteration starts, such that the session can be
+    /// continued with `resume` if the connection drops (see `checkpoint`).  Needs to be called
+    /// after `init` and `setup`.
+    pub fn enable_checkpoints(&mut self) {
+        assert!(self.is_init_done);
+        self.checkpoints = Some(Checkpoints::new());
+    }
+
+    /// Number of iterations completed in this session (counting from `enable_checkpoints`).
+    pub fn completed_iterations(&self) -> u64 {
+        self.iteration
+    }
+
+    /// Continue the session on a new `channel` after the previous connection dropped.  Returns
+    /// the number of iterations both parties completed; outputs of later iterations (at most one)
+    /// have to be discarded.  The cache is reset to the state after that iteration, minus the
+    /// base VOLEs the interrupted iteration might have used.  The leakage is not reset.  The
+    /// verifier needs to call `Verifier::resume`.
+    pub fn resume<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<u64, Error> {
+        assert!(self.is_init_done);
+        let checkpoints = self
+            .checkpoints
+            .as_ref()
+            .expect("checkpoints need to be enabled to resume");
+        let (iteration, epoch) = checkpoint::agree(
+            channel,
+            &self.session_parameters(),
+            self.iteration,
+            self.ot_epoch,
+        )?;
+        let required = reg_vole_required(self.base_vole_len, self.num_sp_voles);
+        let cache_len =
+            checkpoints.resume_len(iteration, self.iteration, self.cache.capacity(), required);
+        let cache_len = checkpoint::agree_cache_len(channel, cache_len)?;
+        if cache_len < required {
+            return Err(Error::Other(
+                "not enough base voles in cache to resume".to_string(),
+            ));
+        }
+        self.cache.truncate(cache_len);
+        self.spvole.reseed_ot(epoch);
+        self.iteration = iteration;
+        self.ot_epoch = epoch;
+        self.checkpoints = Some(Checkpoints::new());
+        Ok(iteration)
+    }
+
+    // count a completed iteration, called once its output is stored
+    fn complete_iteration(&mut self) {
+        self.iteration += 1;
+    }
+
     fn enough_voles_cached(&self, n: usize) -> bool {
         self.cache.capacity() >= n + reg_vole_required(self.base_vole_len, self.num_sp_voles)
     }
@@ -96,10 +286,14 @@ where
         }
 
         // replenish using main iteration
-        let (x, z) = self.base_extend(channel)?;
+        self.run_extension(channel)?;
 
         // store voles in the cache
-        self.cache.append(x.into_iter(), z.into_iter());
+        self.cache.append(
+            self.buffers.x.iter().copied(),
+            self.buffers.z.iter().copied(),
+        );
+        self.complete_iteration();
         Ok(())
     }
 
@@ -138,37 +332,215 @@ where
         Ok(self.cache.get(n))
     }
 
+    /// Like `extend`, but write the VOLEs into the given buffers instead of allocating new
+    /// vectors.
+    pub fn extend_into<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        u_out: &mut [RingT],
+        w_out: &mut [RingT],
+    ) -> Result<(), Error> {
+        assert_eq!(u_out.len(), w_out.len());
+        self.ensure(channel, u_out.len())?;
+        self.cache.get_into(u_out, w_out);
+        Ok(())
+    }
+
+    /// Iterate over chunks of at most `chunk_size` VOLEs.  A new extension is only run once the
+    /// cache is used up, so at most one extension is held in memory at any time.
+    pub fn stream<'p, 'c, C: AbstractChannel>(
+        &'p mut self,
+        channel: &'c mut C,
+        chunk_size: usize,
+    ) -> ProverStream<'p, 'a, 'c, RingT, C> {
+        assert!(chunk_size > 0);
+        ProverStream {
+            prover: self,
+            channel,
+            chunk_size,
+            failed: false,
+        }
+    }
+
+    /// Run a setup iteration with smaller LPN parameters (given by `setup_code`,
+    /// `setup_num_sp_voles` and `setup_sp_vole_single_len`) to expand a small cache of base
+    /// VOLEs into one that is large enough for the main iteration.
+    pub fn setup<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        setup_code: &dyn LpnCode<RingT>,
+        setup_num_sp_voles: usize,
+        setup_sp_vole_single_len: usize,
+    ) -> Result<(), Error> {
+        assert!(self.is_init_done);
+        assert_eq!(
+            setup_code.columns(),
+            setup_num_sp_voles * setup_sp_vole_single_len
+        );
+        let setup_required = reg_vole_required(setup_code.rows(), setup_num_sp_voles);
+        if self.cache.capacity() < setup_required {
+            return Err(Error::Other(
+                "not enough base voles in cache for the setup iteration".to_string(),
+            ));
+        }
+        if self.cache.capacity() - setup_required + setup_code.columns()
+            < reg_vole_required(self.base_vole_len, self.num_sp_voles)
+        {
+            return Err(Error::Other(
+                "setup iteration does not produce enough base voles for the main iteration"
+                    .to_string(),
+            ));
+        }
+
+        let t_start = Instant::now();
+        let mut setup_spvole = SpProver::<RingT>::new(
+            setup_num_sp_voles,
+            setup_sp_vole_single_len,
+            1,
+            self.nightly_version,
+        );
+        setup_spvole.set_tree_expansion(self.spvole.get_tree_expansion());
+        // the setup iteration counts against the leakage budget of the session
+        setup_spvole.set_leakage(self.spvole.get_leakage());
+        setup_spvole.init(channel)?;
+        let mut setup_buffers =
+            ExtendBuffers::new(setup_code.rows(), setup_num_sp_voles, setup_code.columns());
+        Self::lpn_extend(
+            channel,
+            &mut setup_spvole,
+            &mut self.cache,
+            setup_code,
+            &mut setup_buffers,
+            &mut self.stats,
+        )?;
+        self.spvole.set_leakage(setup_spvole.get_leakage());
+        self.cache
+            .append(setup_buffers.x.into_iter(), setup_buffers.z.into_iter());
+        self.stats.setup_run_time = t_start.elapsed();
+        Ok(())
+    }
+
     pub fn base_extend<C: AbstractChannel>(
         &mut self,
         channel: &mut C,
     ) -> Result<(Vec<RingT>, Vec<RingT>), Error> {
+        self.run_extension(channel)?;
+        self.complete_iteration();
+        Ok((self.buffers.x.clone(), self.buffers.z.clone()))
+    }
+
+    // run the main iteration, the output is left in `self.buffers.x` and `self.buffers.z`
+    fn run_extension<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
         assert!(self.is_init_done);
+        if let Some(checkpoints) = self.checkpoints.as_mut() {
+            checkpoints.start_iteration(self.iteration, self.cache.capacity());
+        }
+        Self::lpn_extend(
+            channel,
+            &mut self.spvole,
+            &mut self.cache,
+            self.session_code.as_deref().unwrap_or(self.code),
+            &mut self.buffers,
+            &mut self.stats,
+        )
+    }
 
-        // TODO: move allocations
-        let mut c = vec![Default::default(); self.sp_vole_total_len];
-        let mut e = vec![Default::default(); self.sp_vole_total_len];
-        let mut alphas = vec![0; self.num_sp_voles];
+    fn lpn_extend<C: AbstractChannel>(
+        channel: &mut C,
+        spvole: &mut SpProver<RingT>,
+        cache: &mut CachedProver<RingT>,
+        code: &dyn LpnCode<RingT>,
+        buffers: &mut ExtendBuffers<RingT>,
+        stats: &mut ProverStats,
+    ) -> Result<(), Error> {
+        let sp_vole_single_len = buffers.x.len() / buffers.alphas.len();
 
-        self.spvole
-            .extend(channel, &mut self.cache, &mut alphas, &mut e, &mut c)?;
-        self.stats.sp_stats = self.spvole.get_stats();
+        // the sp-vole expects e to be all zeros except for the positions alpha
+        for e_i in buffers.e.iter_mut() {
+            *e_i = Default::default();
+        }
+        spvole.extend(
+            channel,
+            cache,
+            &mut buffers.alphas,
+            &mut buffers.e,
+            &mut buffers.c,
+        )?;
+        stats.sp_stats = spvole.get_stats();
 
-        let (u_old, w_old) = self.cache.get(self.base_vole_len);
+        cache.get_into(&mut buffers.u_old, &mut buffers.w_old);
 
         let t_start = Instant::now();
         // compute x = A*u (and saves into x)
-        let mut x = self.code.mul(&u_old);
-        self.stats.expansion_1_run_time = t_start.elapsed();
+        code.mul_into(&buffers.u_old, &mut buffers.x);
+        stats.expansion_1_run_time = t_start.elapsed();
 
-        for (i, alpha_i) in alphas.iter().enumerate() {
-            let index = i * self.sp_vole_single_len + alpha_i;
-            x[index] = (x[index] + e[index]).reduce();
+        for (i, alpha_i) in buffers.alphas.iter().enumerate() {
+            let index = i * sp_vole_single_len + alpha_i;
+            buffers.x[index] = (buffers.x[index] + buffers.e[index]).reduce();
         }
 
         let t_start = Instant::now();
-        let z = self.code.mul_add(&w_old, &c);
-        self.stats.expansion_2_run_time = t_start.elapsed();
+        code.mul_add_into(&buffers.w_old, &buffers.c, &mut buffers.z);
+        stats.expansion_2_run_time = t_start.elapsed();
 
-        return Ok((x, z));
+        Ok(())
+    }
+}
+
+/// Iterator over chunks of VOLEs returned by `Prover::stream`.
+pub struct ProverStream<'p, 'a, 'c, RingT, C>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    prover: &'p mut Prover<'a, RingT>,
+    channel: &'c mut C,
+    chunk_size: usize,
+    failed: bool,
+}
+
+impl<'p, 'a, 'c, RingT, C> Iterator for ProverStream<'p, 'a, 'c, RingT, C>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+    C: AbstractChannel,
+{
+    type Item = Result<(Vec<RingT>, Vec<RingT>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if !self.prover.enough_voles_cached(1) {
+            if let Err(e) = self.prover.replenish_cache(self.channel) {
+                self.failed = true;
+                return Some(Err(e));
+            }
+        }
+        let required = reg_vole_required(self.prover.base_vole_len, self.prover.num_sp_voles);
+        let n = usize::min(self.chunk_size, self.prover.cache.capacity() - required);
+        Some(Ok(self.prover.cache.get(n)))
+    }
+}
+
+impl<'a, RingT> VoleReceiver for Prover<'a, RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    type Value = RingT;
+    type Mac = RingT;
+
+    fn receive_vole<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+        _rng: &mut RNG,
+    ) -> Result<(Vec<RingT>, Vec<RingT>), Error> {
+        self.extend(channel, n)
     }
 }
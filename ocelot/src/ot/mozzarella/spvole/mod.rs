@@ -1,10 +1,15 @@
+pub mod pipeline;
 pub mod prover;
 pub mod verifier;
 
 #[cfg(test)]
 mod tests {
-    use super::{prover::BatchedProver, verifier::BatchedVerifier};
-    use crate::ot::mozzarella::cache::cacheinit::GenCache;
+    use super::{
+        pipeline::{PipelinedProver, PipelinedVerifier},
+        prover::BatchedProver,
+        verifier::BatchedVerifier,
+    };
+    use crate::ot::mozzarella::{cache::cacheinit::GenCache, ggm::TreeExpansion};
     use rand::{
         distributions::{Distribution, Standard},
         rngs::OsRng,
@@ -22,6 +27,22 @@ mod tests {
         RingT: Ring + Receivable,
         Standard: Distribution<RingT>,
         for<'a> &'a RingT: Sendable,
+    {
+        test_batched_sp_vole_with_expansion::<RingT, NIGHTLY, SINGLE_OUTPUT_SIZE>(
+            TreeExpansion::Ggm,
+        );
+    }
+
+    fn test_batched_sp_vole_with_expansion<
+        RingT,
+        const NIGHTLY: bool,
+        const SINGLE_OUTPUT_SIZE: usize,
+    >(
+        expansion: TreeExpansion,
+    ) where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
     {
         const TEST_REPETITIONS: usize = 10;
 
@@ -45,6 +66,8 @@ mod tests {
                 BatchedProver::<RingT>::new(NUM_SP_VOLES, SINGLE_OUTPUT_SIZE, NIGHTLY);
             let mut sp_verifier =
                 BatchedVerifier::<RingT>::new(NUM_SP_VOLES, SINGLE_OUTPUT_SIZE, NIGHTLY);
+            sp_prover.set_tree_expansion(expansion);
+            sp_verifier.set_tree_expansion(expansion);
             let (mut channel_p, mut channel_v) = unix_channel_pair();
             let mut alphas = [0usize; NUM_ITERATIONS * NUM_SP_VOLES];
             let mut out_u = vec![RingT::default(); NUM_ITERATIONS * output_size];
@@ -103,6 +126,103 @@ mod tests {
         }
     }
 
+    fn test_pipelined_sp_vole<RingT, const NIGHTLY: bool>()
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        test_pipelined_sp_vole_with_expansion::<RingT, NIGHTLY>(TreeExpansion::Ggm);
+    }
+
+    fn test_pipelined_sp_vole_with_expansion<RingT, const NIGHTLY: bool>(expansion: TreeExpansion)
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        const NUM_SP_VOLES: usize = 16;
+        const NUM_SUB_BATCHES: usize = 3;
+        const SINGLE_OUTPUT_SIZE: usize = 64;
+        const NUM_ITERATIONS: usize = 2;
+        const OUTPUT_SIZE: usize = SINGLE_OUTPUT_SIZE * NUM_SP_VOLES;
+        const CACHE_SIZE: usize = 2 * NUM_SP_VOLES * NUM_ITERATIONS;
+        let mut rng = OsRng;
+
+        let delta = rng.gen::<RingT>();
+        let (mut cached_prover, mut cached_verifier) =
+            GenCache::new::<RingT, _, 0, CACHE_SIZE>(&mut rng, delta);
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+
+        let prover_thread = spawn(move || {
+            let mut sp_prover = PipelinedProver::<RingT>::new(
+                NUM_SP_VOLES,
+                SINGLE_OUTPUT_SIZE,
+                NUM_SUB_BATCHES,
+                NIGHTLY,
+            );
+            sp_prover.set_tree_expansion(expansion);
+            let mut alphas = vec![0usize; NUM_ITERATIONS * NUM_SP_VOLES];
+            let mut out_u = vec![RingT::default(); NUM_ITERATIONS * OUTPUT_SIZE];
+            let mut out_w = vec![RingT::default(); NUM_ITERATIONS * OUTPUT_SIZE];
+            sp_prover.init(&mut channel_p).unwrap();
+            for i in 0..NUM_ITERATIONS {
+                sp_prover
+                    .extend(
+                        &mut channel_p,
+                        &mut cached_prover,
+                        &mut alphas[i * NUM_SP_VOLES..(i + 1) * NUM_SP_VOLES],
+                        &mut out_u[i * OUTPUT_SIZE..(i + 1) * OUTPUT_SIZE],
+                        &mut out_w[i * OUTPUT_SIZE..(i + 1) * OUTPUT_SIZE],
+                    )
+                    .unwrap();
+            }
+            (out_u, out_w, alphas)
+        });
+
+        let mut sp_verifier = PipelinedVerifier::<RingT>::new(
+            NUM_SP_VOLES,
+            SINGLE_OUTPUT_SIZE,
+            NUM_SUB_BATCHES,
+            NIGHTLY,
+        );
+        sp_verifier.set_tree_expansion(expansion);
+        let mut out_v = vec![RingT::default(); NUM_ITERATIONS * OUTPUT_SIZE];
+        sp_verifier.init(&mut channel_v, delta).unwrap();
+        for i in 0..NUM_ITERATIONS {
+            sp_verifier
+                .extend(
+                    &mut channel_v,
+                    &mut cached_verifier,
+                    &mut out_v[i * OUTPUT_SIZE..(i + 1) * OUTPUT_SIZE],
+                )
+                .unwrap();
+        }
+        let (out_u, out_w, alphas) = prover_thread.join().unwrap();
+
+        for (j, &alpha) in alphas.iter().enumerate() {
+            let base = j * SINGLE_OUTPUT_SIZE;
+            for i in 0..SINGLE_OUTPUT_SIZE {
+                if i == alpha {
+                    assert_eq!(out_w[base + i], delta * out_u[base + i] + out_v[base + i]);
+                } else {
+                    assert_eq!(out_u[base + i], RingT::default());
+                    assert_eq!(out_w[base + i], out_v[base + i]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pipelined_sp_vole_r64() {
+        test_pipelined_sp_vole::<R64, false>();
+    }
+
+    #[test]
+    fn test_pipelined_sp_vole_r144_nightly() {
+        test_pipelined_sp_vole::<z2r::R144, true>();
+    }
+
     #[test]
     fn test_batched_sp_vole_r64() {
         test_batched_sp_vole::<R64, false, 256>();
@@ -167,4 +287,19 @@ mod tests {
     fn test_batched_sp_vole_r144_nightly_no_power_of_two() {
         test_batched_sp_vole::<z2r::R144, true, 147>();
     }
+
+    #[test]
+    fn test_batched_sp_vole_r64_half_tree() {
+        test_batched_sp_vole_with_expansion::<R64, false, 256>(TreeExpansion::HalfTree);
+    }
+
+    #[test]
+    fn test_batched_sp_vole_r144_nightly_half_tree_no_power_of_two() {
+        test_batched_sp_vole_with_expansion::<z2r::R144, true, 147>(TreeExpansion::HalfTree);
+    }
+
+    #[test]
+    fn test_pipelined_sp_vole_r64_half_tree() {
+        test_pipelined_sp_vole_with_expansion::<R64, false>(TreeExpansion::HalfTree);
+    }
 }
@@ -0,0 +1,478 @@
+//! Pipelined execution of the SP-VOLE stages.
+//!
+//! The SP-VOLEs of one extension are split into sub-batches, each of which is run by its own
+//! `BatchedProver`/`BatchedVerifier`.  Stage `s` of sub-batch `j` is executed in tick `j + s - 1`.
+//! In every tick, the communication stages (2, 4, 6) are run on the calling thread, while the
+//! computation stages (1, 3, 5) of other sub-batches are run on a second thread.  Hence, the
+//! computation of one sub-batch overlaps with the communication of another one.
+use crate::{
+    ot::mozzarella::{
+        cache::{prover::CachedProver, verifier::CachedVerifier},
+        ggm::TreeExpansion,
+        spvole::{
+            prover::{BatchedProver, BatchedProverStats, LeakageBudget},
+            verifier::{receive_leakage_announcement, BatchedVerifier, BatchedVerifierStats},
+        },
+    },
+    Error,
+};
+use rand::{
+    distributions::{Distribution, Standard},
+    rngs::OsRng,
+};
+use scuttlebutt::{
+    channel::{Receivable, Sendable},
+    ring::Ring,
+    AbstractChannel,
+};
+use std::time::{Duration, Instant};
+
+const NUM_STAGES: usize = 6;
+
+fn is_communication_stage(stage: usize) -> bool {
+    matches!(stage, 2 | 4 | 6)
+}
+
+// number of SP-VOLEs in each sub-batch
+fn sub_batch_sizes(num_instances: usize, num_sub_batches: usize) -> Vec<usize> {
+    let size = num_instances / num_sub_batches;
+    let remainder = num_instances % num_sub_batches;
+    (0..num_sub_batches)
+        .map(|i| if i < remainder { size + 1 } else { size })
+        .collect()
+}
+
+// split `buffer` into consecutive chunks of the given sizes
+fn split_parts<'b, T>(mut buffer: &'b mut [T], sizes: &[usize]) -> Vec<&'b mut [T]> {
+    let mut parts = Vec::with_capacity(sizes.len());
+    for &size in sizes {
+        let (part, rest) = buffer.split_at_mut(size);
+        parts.push(part);
+        buffer = rest;
+    }
+    parts
+}
+
+// base VOLEs for the given sub-batch: the i-th SP-VOLE uses entries i and num_instances + i
+fn sub_batch_base_vole<T: Copy>(base_vole: &[T], offset: usize, size: usize) -> Vec<T> {
+    let num_instances = base_vole.len() / 2;
+    let mut out = Vec::with_capacity(2 * size);
+    out.extend_from_slice(&base_vole[offset..offset + size]);
+    out.extend_from_slice(&base_vole[num_instances + offset..num_instances + offset + size]);
+    out
+}
+
+// run the pipeline over all sub-batches, `communicate` is called on the current thread and
+// `compute` on a second thread
+fn run_pipeline<T, Comm, Comp>(
+    sub_batches: &mut [T],
+    mut communicate: Comm,
+    compute: Comp,
+) -> Result<(), Error>
+where
+    T: Send,
+    Comm: FnMut(&mut T, usize) -> Result<(), Error>,
+    Comp: Fn(&mut T, usize) + Sync,
+{
+    let num_sub_batches = sub_batches.len();
+    let compute = &compute;
+    for tick in 0..num_sub_batches + NUM_STAGES - 1 {
+        // sub-batch j runs stage tick - j + 1
+        let mut comm_jobs = Vec::new();
+        let mut comp_jobs = Vec::new();
+        for (j, sub_batch) in sub_batches.iter_mut().enumerate() {
+            if j > tick || tick - j >= NUM_STAGES {
+                continue;
+            }
+            let stage = tick - j + 1;
+            if is_communication_stage(stage) {
+                comm_jobs.push((sub_batch, stage));
+            } else {
+                comp_jobs.push((sub_batch, stage));
+            }
+        }
+        // both parties need to run the communication stages in the same order
+        comm_jobs.sort_by_key(|(_, stage)| *stage);
+        crossbeam::scope(|scope| {
+            scope.spawn(move |_| {
+                for (sub_batch, stage) in comp_jobs {
+                    compute(sub_batch, stage);
+                }
+            });
+            comm_jobs
+                .into_iter()
+                .try_for_each(|(sub_batch, stage)| communicate(sub_batch, stage))
+        })
+        .unwrap()?;
+    }
+    Ok(())
+}
+
+struct ProverSubBatch<'o, RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    prover: &'o mut BatchedProver<RingT>,
+    base_vole: (Vec<RingT>, Vec<RingT>),
+    out_u: &'o mut [RingT],
+    out_w: &'o mut [RingT],
+    run_times: [Duration; NUM_STAGES],
+}
+
+/// SP-VOLE prover that runs its instances in pipelined sub-batches.
+pub struct PipelinedProver<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    num_instances: usize,
+    output_size: usize,
+    sub_batches: Vec<BatchedProver<RingT>>,
+    alpha_s: Vec<usize>,
+    // every sub-batch runs its own consistency checks
+    leakage: LeakageBudget,
+    stats: BatchedProverStats,
+}
+
+impl<RingT> PipelinedProver<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    pub fn new(
+        num_instances: usize,
+        output_size: usize,
+        num_sub_batches: usize,
+        nightly_version: bool,
+    ) -> Self {
+        assert!(num_sub_batches > 0 && num_sub_batches <= num_instances);
+        Self {
+            num_instances,
+            output_size,
+            sub_batches: sub_batch_sizes(num_instances, num_sub_batches)
+                .into_iter()
+                .map(|n| BatchedProver::new(n, output_size, nightly_version))
+                .collect(),
+            alpha_s: vec![0; num_instances],
+            leakage: Default::default(),
+            stats: Default::default(),
+        }
+    }
+
+    pub fn get_stats(&self) -> BatchedProverStats {
+        self.stats
+    }
+
+    pub fn get_leakage(&self) -> LeakageBudget {
+        self.leakage
+    }
+
+    pub fn set_leakage(&mut self, leakage: LeakageBudget) {
+        self.leakage = leakage;
+        self.stats.leaked_bits = leakage.leaked_bits();
+        self.stats.remaining_leakage_budget = leakage.remaining();
+    }
+
+    pub fn num_sub_batches(&self) -> usize {
+        self.sub_batches.len()
+    }
+
+    pub fn get_tree_expansion(&self) -> TreeExpansion {
+        self.sub_batches[0].get_tree_expansion()
+    }
+
+    /// Select how the trees are expanded.  Needs to be called before `init`.
+    pub fn set_tree_expansion(&mut self, expansion: TreeExpansion) {
+        for sub_batch in self.sub_batches.iter_mut() {
+            sub_batch.set_tree_expansion(expansion);
+        }
+    }
+
+    pub fn init<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        for sub_batch in self.sub_batches.iter_mut() {
+            sub_batch.init(channel)?;
+        }
+        Ok(())
+    }
+
+    pub fn reseed_ot(&mut self, epoch: u64) {
+        for sub_batch in self.sub_batches.iter_mut() {
+            sub_batch.reseed_ot(epoch);
+        }
+    }
+
+    pub fn extend<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        cache: &mut CachedProver<RingT>,
+        alphas: &mut [usize],
+        out_u: &mut [RingT],
+        out_w: &mut [RingT],
+    ) -> Result<(), Error> {
+        assert_eq!(alphas.len(), self.num_instances);
+        if self.sub_batches.len() == 1 {
+            // the sub-batch does the accounting for us
+            let sub_batch = &mut self.sub_batches[0];
+            sub_batch.set_leakage(self.leakage);
+            let result = sub_batch.extend(channel, cache, alphas, out_u, out_w);
+            self.leakage = sub_batch.get_leakage();
+            self.stats = sub_batch.get_stats();
+            return result;
+        }
+        self.leakage
+            .consume_and_announce(channel, self.sub_batches.len())?;
+        assert_eq!(out_u.len(), self.num_instances * self.output_size);
+        assert_eq!(out_w.len(), self.num_instances * self.output_size);
+
+        let base_vole = cache.get(2 * self.num_instances);
+        let sizes = sub_batch_sizes(self.num_instances, self.sub_batches.len());
+        let output_sizes: Vec<usize> = sizes.iter().map(|n| n * self.output_size).collect();
+        let mut offset = 0;
+        let mut sub_batches: Vec<ProverSubBatch<RingT>> = self
+            .sub_batches
+            .iter_mut()
+            .zip(split_parts(out_u, &output_sizes))
+            .zip(split_parts(out_w, &output_sizes))
+            .zip(sizes.iter())
+            .map(|(((prover, out_u), out_w), &size)| {
+                let sub_base_vole = (
+                    sub_batch_base_vole(&base_vole.0, offset, size),
+                    sub_batch_base_vole(&base_vole.1, offset, size),
+                );
+                offset += size;
+                ProverSubBatch {
+                    prover,
+                    base_vole: sub_base_vole,
+                    out_u,
+                    out_w,
+                    run_times: Default::default(),
+                }
+            })
+            .collect();
+
+        run_pipeline(
+            &mut sub_batches,
+            |sb, stage| {
+                let t_start = Instant::now();
+                match stage {
+                    2 => sb.prover.stage_2_communication(channel)?,
+                    4 => sb.prover.stage_4_communication(channel)?,
+                    6 => sb.prover.stage_6_communication(channel)?,
+                    _ => unreachable!(),
+                }
+                sb.run_times[stage - 1] += t_start.elapsed();
+                Ok(())
+            },
+            |sb, stage| {
+                let t_start = Instant::now();
+                let base_vole = (&sb.base_vole.0[..], &sb.base_vole.1[..]);
+                match stage {
+                    1 => sb.prover.stage_1_computation(sb.out_u, base_vole),
+                    3 => sb.prover.stage_3_computation(sb.out_w),
+                    5 => sb.prover.stage_5_computation(sb.out_w, base_vole),
+                    _ => unreachable!(),
+                }
+                sb.run_times[stage - 1] += t_start.elapsed();
+            },
+        )?;
+
+        let mut run_times = [Duration::default(); NUM_STAGES];
+        let mut offset = 0;
+        for sb in sub_batches.iter() {
+            for (total, t) in run_times.iter_mut().zip(sb.run_times.iter()) {
+                *total += *t;
+            }
+            let sub_alphas = sb.prover.get_alphas();
+            self.alpha_s[offset..offset + sub_alphas.len()].copy_from_slice(sub_alphas);
+            offset += sub_alphas.len();
+        }
+        self.stats = BatchedProverStats {
+            stage_1_run_time: run_times[0],
+            stage_2_run_time: run_times[1],
+            stage_3_run_time: run_times[2],
+            stage_4_run_time: run_times[3],
+            stage_5_run_time: run_times[4],
+            stage_6_run_time: run_times[5],
+            leaked_bits: self.leakage.leaked_bits(),
+            remaining_leakage_budget: self.leakage.remaining(),
+        };
+        alphas.copy_from_slice(&self.alpha_s);
+        Ok(())
+    }
+}
+
+struct VerifierSubBatch<'o, RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    verifier: &'o mut BatchedVerifier<RingT>,
+    base_vole: Vec<RingT>,
+    out_v: &'o mut [RingT],
+    run_times: [Duration; NUM_STAGES],
+}
+
+/// SP-VOLE verifier that runs its instances in pipelined sub-batches.
+pub struct PipelinedVerifier<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    num_instances: usize,
+    output_size: usize,
+    sub_batches: Vec<BatchedVerifier<RingT>>,
+    stats: BatchedVerifierStats,
+}
+
+impl<RingT> PipelinedVerifier<RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'a> &'a RingT: Sendable,
+{
+    pub fn new(
+        num_instances: usize,
+        output_size: usize,
+        num_sub_batches: usize,
+        nightly_version: bool,
+    ) -> Self {
+        assert!(num_sub_batches > 0 && num_sub_batches <= num_instances);
+        Self {
+            num_instances,
+            output_size,
+            sub_batches: sub_batch_sizes(num_instances, num_sub_batches)
+                .into_iter()
+                .map(|n| BatchedVerifier::new(n, output_size, nightly_version))
+                .collect(),
+            stats: Default::default(),
+        }
+    }
+
+    pub fn get_stats(&self) -> BatchedVerifierStats {
+        self.stats
+    }
+
+    pub fn num_sub_batches(&self) -> usize {
+        self.sub_batches.len()
+    }
+
+    pub fn get_tree_expansion(&self) -> TreeExpansion {
+        self.sub_batches[0].get_tree_expansion()
+    }
+
+    /// Select how the trees are expanded.  Needs to be called before `init`.
+    pub fn set_tree_expansion(&mut self, expansion: TreeExpansion) {
+        for sub_batch in self.sub_batches.iter_mut() {
+            sub_batch.set_tree_expansion(expansion);
+        }
+    }
+
+    #[allow(non_snake_case)]
+    pub fn init<C: AbstractChannel>(&mut self, channel: &mut C, Delta: RingT) -> Result<(), Error> {
+        for sub_batch in self.sub_batches.iter_mut() {
+            sub_batch.init(channel, Delta)?;
+        }
+        Ok(())
+    }
+
+    pub fn reseed_ot(&mut self, epoch: u64) {
+        for sub_batch in self.sub_batches.iter_mut() {
+            sub_batch.reseed_ot(epoch);
+        }
+    }
+
+    /// Switch to a new `Delta` while keeping the OTs (see `BatchedVerifier::set_delta`).
+    #[allow(non_snake_case)]
+    pub fn set_delta(&mut self, Delta: RingT) {
+        for sub_batch in self.sub_batches.iter_mut() {
+            sub_batch.set_delta(Delta);
+        }
+    }
+
+    pub fn extend<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        cache: &mut CachedVerifier<RingT>,
+        out_v: &mut [RingT],
+    ) -> Result<(), Error> {
+        if self.sub_batches.len() == 1 {
+            let sub_batch = &mut self.sub_batches[0];
+            sub_batch.extend(channel, cache, out_v)?;
+            self.stats = sub_batch.get_stats();
+            return Ok(());
+        }
+        assert_eq!(out_v.len(), self.num_instances * self.output_size);
+        receive_leakage_announcement(channel)?;
+
+        let base_vole = cache.get(2 * self.num_instances);
+        let sizes = sub_batch_sizes(self.num_instances, self.sub_batches.len());
+        let output_sizes: Vec<usize> = sizes.iter().map(|n| n * self.output_size).collect();
+        let mut offset = 0;
+        let mut sub_batches: Vec<VerifierSubBatch<RingT>> = self
+            .sub_batches
+            .iter_mut()
+            .zip(split_parts(out_v, &output_sizes))
+            .zip(sizes.iter())
+            .map(|((verifier, out_v), &size)| {
+                let sub_base_vole = sub_batch_base_vole(&base_vole, offset, size);
+                offset += size;
+                VerifierSubBatch {
+                    verifier,
+                    base_vole: sub_base_vole,
+                    out_v,
+                    run_times: Default::default(),
+                }
+            })
+            .collect();
+
+        let mut rng = OsRng;
+        run_pipeline(
+            &mut sub_batches,
+            |sb, stage| {
+                let t_start = Instant::now();
+                match stage {
+                    2 => sb.verifier.stage_2_communication(channel)?,
+                    4 => sb.verifier.stage_4_communication(channel)?,
+                    6 => sb
+                        .verifier
+                        .stage_6_communication(channel, &sb.base_vole, &mut rng)?,
+                    _ => unreachable!(),
+                }
+                sb.run_times[stage - 1] += t_start.elapsed();
+                Ok(())
+            },
+            |sb, stage| {
+                let t_start = Instant::now();
+                match stage {
+                    1 => sb.verifier.stage_1_computation(sb.out_v, &sb.base_vole),
+                    3 => sb.verifier.stage_3_computation(),
+                    5 => sb.verifier.stage_5_computation(sb.out_v),
+                    _ => unreachable!(),
+                }
+                sb.run_times[stage - 1] += t_start.elapsed();
+            },
+        )?;
+
+        let mut run_times = [Duration::default(); NUM_STAGES];
+        for sb in sub_batches.iter() {
+            for (total, t) in run_times.iter_mut().zip(sb.run_times.iter()) {
+                *total += *t;
+            }
+        }
+        self.stats = BatchedVerifierStats {
+            stage_1_run_time: run_times[0],
+            stage_2_run_time: run_times[1],
+            stage_3_run_time: run_times[2],
+            stage_4_run_time: run_times[3],
+            stage_5_run_time: run_times[4],
+            stage_6_run_time: run_times[5],
+        };
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
 use crate::{
     ot::{
-        mozzarella::{cache::prover::CachedProver, ggm::prover as ggmProver},
+        mozzarella::{
+            cache::prover::CachedProver,
+            ggm::{TreeExpansion, TreeProver},
+        },
         KosDeltaReceiver, Receiver as OtReceiver,
     },
     Error,
@@ -19,6 +22,78 @@ use scuttlebutt::{
 use serde::Serialize;
 use std::time::{Duration, Instant};
 
+/// Upper bound on the number of bits about the noise positions `alpha_s` that a malicious
+/// verifier learns from one batch of consistency checks (it passes only if the verifier's guess
+/// was correct).
+pub const LEAKAGE_BITS_PER_CHECK: usize = 1;
+
+/// Accounts for the selective failure leakage of the leaky regular LPN assumption within one
+/// session.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LeakageBudget {
+    // `None` means unlimited
+    budget: Option<usize>,
+    leaked_bits: usize,
+}
+
+impl LeakageBudget {
+    pub fn new(budget: Option<usize>) -> Self {
+        Self {
+            budget,
+            leaked_bits: 0,
+        }
+    }
+
+    pub fn leaked_bits(&self) -> usize {
+        self.leaked_bits
+    }
+
+    /// Remaining number of bits, or `None` if the budget is unlimited.
+    pub fn remaining(&self) -> Option<usize> {
+        self.budget.map(|b| b.saturating_sub(self.leaked_bits))
+    }
+
+    pub fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+    }
+
+    /// Account for `num_checks` consistency checks, fails if this exceeds the budget.
+    pub fn consume(&mut self, num_checks: usize) -> Result<(), Error> {
+        let bits = num_checks * LEAKAGE_BITS_PER_CHECK;
+        if let Some(remaining) = self.remaining() {
+            if bits > remaining {
+                return Err(Error::Other(format!(
+                    "leakage budget exhausted ({} bits leaked, {} remaining, {} required): \
+                     a fresh bootstrap is needed",
+                    self.leaked_bits, remaining, bits
+                )));
+            }
+        }
+        self.leaked_bits += bits;
+        Ok(())
+    }
+
+    /// Like `consume`, but also tell the verifier whether the checks are run, such that it does
+    /// not wait for them if the budget is exhausted.
+    pub fn consume_and_announce<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        num_checks: usize,
+    ) -> Result<(), Error> {
+        let result = self.consume(num_checks);
+        channel.send(&[result.is_ok() as u8])?;
+        if result.is_err() {
+            channel.flush()?;
+        }
+        result
+    }
+
+    /// Reset the leakage, e.g., after switching to freshly bootstrapped base VOLEs.
+    pub fn reset(&mut self) {
+        self.leaked_bits = 0;
+    }
+}
+
 #[allow(non_snake_case)]
 pub struct BatchedProver<RingT>
 where
@@ -29,7 +104,7 @@ where
     num_instances: usize,
     output_size: usize,
     total_output_size: usize,
-    ggm_prover: ggmProver::BatchedProver,
+    ggm_prover: TreeProver,
     ot_receiver: Option<KosDeltaReceiver>,
     rng: AesRng,
     alpha_s: Vec<usize>,
@@ -43,6 +118,7 @@ where
     committed_VV_s: Vec<[u8; 32]>,
     nightly_version: bool, // with extra protocol optimizations
     is_init_done: bool,
+    leakage: LeakageBudget,
     stats: BatchedProverStats,
 }
 
@@ -54,6 +130,8 @@ pub struct BatchedProverStats {
     pub stage_4_run_time: Duration,
     pub stage_5_run_time: Duration,
     pub stage_6_run_time: Duration,
+    pub leaked_bits: usize,
+    pub remaining_leakage_budget: Option<usize>,
 }
 
 impl<RingT> BatchedProver<RingT>
@@ -67,7 +145,11 @@ where
             num_instances,
             output_size,
             total_output_size: num_instances * output_size,
-            ggm_prover: ggmProver::BatchedProver::new_with_output_size(num_instances, output_size),
+            ggm_prover: TreeProver::new_with_output_size(
+                Default::default(),
+                num_instances,
+                output_size,
+            ),
             ot_receiver: None,
             rng: AesRng::new(),
             alpha_s: vec![Default::default(); num_instances],
@@ -85,6 +167,7 @@ where
             committed_VV_s: vec![Default::default(); num_instances],
             is_init_done: false,
             nightly_version,
+            leakage: Default::default(),
             stats: Default::default(),
         }
     }
@@ -93,6 +176,29 @@ where
         self.stats
     }
 
+    pub fn get_leakage(&self) -> LeakageBudget {
+        self.leakage
+    }
+
+    /// Replace the leakage accounting, e.g., to set a budget or to carry it over from another
+    /// prover of the same session.
+    pub fn set_leakage(&mut self, leakage: LeakageBudget) {
+        self.leakage = leakage;
+        self.stats.leaked_bits = leakage.leaked_bits();
+        self.stats.remaining_leakage_budget = leakage.remaining();
+    }
+
+    pub fn get_tree_expansion(&self) -> TreeExpansion {
+        self.ggm_prover.expansion()
+    }
+
+    /// Select how the trees are expanded.  Needs to be called before `init`.
+    pub fn set_tree_expansion(&mut self, expansion: TreeExpansion) {
+        assert!(!self.is_init_done);
+        self.ggm_prover =
+            TreeProver::new_with_output_size(expansion, self.num_instances, self.output_size);
+    }
+
     pub fn get_alphas(&self) -> &[usize] {
         self.alpha_s.as_slice()
     }
@@ -104,6 +210,12 @@ where
         Ok(())
     }
 
+    /// Derive fresh OT extension PRGs for the given `epoch` (see `KosDeltaReceiver::reseed`).
+    pub fn reseed_ot(&mut self, epoch: u64) {
+        assert!(self.is_init_done);
+        self.ot_receiver.as_mut().unwrap().reseed(epoch);
+    }
+
     pub fn stage_1_computation(&mut self, out_u: &mut [RingT], base_vole: (&[RingT], &[RingT])) {
         assert_eq!(out_u.len(), self.num_instances * self.output_size);
         debug_assert!(out_u.iter().all(|&x| x.is_zero()));
@@ -192,7 +304,7 @@ where
             let mut new_rng = AesRng::from_seed(chi_seed);
 
             // TODO: approximate rather than strictly require N/2
-            // N will always be even
+            // for odd N, the Hamming weight is (N - 1)/2
             let mut i = 0;
             while i < output_size / 2 {
                 let tmp: usize = new_rng.gen_range(0, output_size);
@@ -415,6 +527,11 @@ where
         assert_eq!(out_u.len(), self.total_output_size);
         assert_eq!(out_w.len(), self.total_output_size);
 
+        // refuse to run the checks if this could exceed the leakage budget
+        self.leakage.consume_and_announce(channel, 1)?;
+        self.stats.leaked_bits = self.leakage.leaked_bits();
+        self.stats.remaining_leakage_budget = self.leakage.remaining();
+
         let base_vole = cache.get(2 * self.num_instances);
         assert_eq!(base_vole.0.len(), 2 * self.num_instances);
         assert_eq!(base_vole.1.len(), 2 * self.num_instances);
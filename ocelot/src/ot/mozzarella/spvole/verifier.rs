@@ -1,7 +1,10 @@
 use crate::{
     errors::Error,
     ot::{
-        mozzarella::{cache::verifier::CachedVerifier, ggm::verifier as ggmVerifier},
+        mozzarella::{
+            cache::verifier::CachedVerifier,
+            ggm::{TreeExpansion, TreeVerifier},
+        },
         FixedKeyInitializer, KosDeltaSender,
     },
 };
@@ -20,6 +23,17 @@ use scuttlebutt::{
 use serde::Serialize;
 use std::time::{Duration, Instant};
 
+/// Receive the prover's announcement whether it runs the next consistency checks (see
+/// `LeakageBudget::consume_and_announce`).
+pub fn receive_leakage_announcement<C: AbstractChannel>(channel: &mut C) -> Result<(), Error> {
+    match channel.receive::<[u8; 1]>()?[0] {
+        1 => Ok(()),
+        _ => Err(Error::Other(
+            "the prover aborted, since its leakage budget is exhausted".to_string(),
+        )),
+    }
+}
+
 #[allow(non_snake_case)]
 pub struct BatchedVerifier<RingT>
 where
@@ -30,7 +44,7 @@ where
     num_instances: usize,
     output_size: usize,
     total_output_size: usize,
-    ggm_verifier: ggmVerifier::BatchedVerifier,
+    ggm_verifier: TreeVerifier,
     ot_sender: Option<KosDeltaSender>,
     Delta: RingT,
     a_prime_s: Vec<RingT>,
@@ -67,7 +81,8 @@ where
             num_instances,
             output_size,
             total_output_size: num_instances * output_size,
-            ggm_verifier: ggmVerifier::BatchedVerifier::new_with_output_size(
+            ggm_verifier: TreeVerifier::new_with_output_size(
+                Default::default(),
                 num_instances,
                 output_size,
             ),
@@ -95,16 +110,42 @@ where
         self.stats
     }
 
+    pub fn get_tree_expansion(&self) -> TreeExpansion {
+        self.ggm_verifier.expansion()
+    }
+
+    /// Select how the trees are expanded.  Needs to be called before `init`.
+    pub fn set_tree_expansion(&mut self, expansion: TreeExpansion) {
+        assert!(!self.is_init_done);
+        self.ggm_verifier =
+            TreeVerifier::new_with_output_size(expansion, self.num_instances, self.output_size);
+    }
+
     #[allow(non_snake_case)]
     pub fn init<C: AbstractChannel>(&mut self, channel: &mut C, Delta: RingT) -> Result<(), Error> {
         let mut rng = AesRng::new();
         let ot_key = rng.gen::<[u8; 16]>();
         self.ot_sender = Some(KosDeltaSender::init_fixed_key(channel, ot_key, &mut rng)?);
+        self.ggm_verifier.set_ot_delta(Block::from(ot_key));
         self.Delta = Delta;
         self.is_init_done = true;
         Ok(())
     }
 
+    /// Derive fresh OT extension PRGs for the given `epoch` (see `KosDeltaSender::reseed`).
+    pub fn reseed_ot(&mut self, epoch: u64) {
+        assert!(self.is_init_done);
+        self.ot_sender.as_mut().unwrap().reseed(epoch);
+    }
+
+    /// Switch to a new `Delta` while keeping the OTs.  The base VOLEs of the following
+    /// extensions need to be correlated with the new `Delta`.
+    #[allow(non_snake_case)]
+    pub fn set_delta(&mut self, Delta: RingT) {
+        assert!(self.is_init_done);
+        self.Delta = Delta;
+    }
+
     pub fn stage_1_computation(&mut self, out_v: &mut [RingT], base_vole: &[RingT]) {
         assert!(self.is_init_done);
         assert_eq!(out_v.len(), self.num_instances * self.output_size);
@@ -180,7 +221,7 @@ where
             let mut indices = vec![false; output_size];
             let mut new_rng = AesRng::from_seed(chi_seed);
 
-            // N will always be even
+            // for odd N, the Hamming weight is (N - 1)/2
             let mut i = 0;
             while i < output_size / 2 {
                 let tmp: usize = new_rng.gen_range(0, output_size);
@@ -333,6 +374,7 @@ where
         assert!(self.is_init_done);
         assert_eq!(out_v.len(), self.total_output_size);
 
+        receive_leakage_announcement(channel)?;
         let base_vole = cache.get(2 * self.num_instances);
         assert_eq!(base_vole.len(), 2 * self.num_instances);
 
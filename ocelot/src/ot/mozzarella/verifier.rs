@@ -1,14 +1,22 @@
 use crate::{
-    ot::mozzarella::{
-        cache::verifier::CachedVerifier,
-        spvole::verifier::{
-            BatchedVerifier as SpVerifier, BatchedVerifierStats as SpVerifierStats,
+    ot::{
+        mozzarella::{
+            cache::{bootstrap::BaseVoleVerifier, verifier::CachedVerifier},
+            checkpoint::{self, Checkpoints},
+            spvole::{
+                pipeline::PipelinedVerifier as SpVerifier,
+                verifier::BatchedVerifierStats as SpVerifierStats,
+            },
+            *,
         },
-        *,
+        VoleSender,
     },
     Error,
 };
-use rand::distributions::{Distribution, Standard};
+use rand::{
+    distributions::{Distribution, Standard},
+    CryptoRng, Rng,
+};
 use scuttlebutt::{
     channel::{Receivable, Sendable},
     ring::Ring,
@@ -28,14 +36,40 @@ where
     sp_vole_total_len: usize,
     num_sp_voles: usize,
     cache: CachedVerifier<RingT>,
-    code: &'a LLCode<RingT>,
+    buffers: ExtendBuffers<RingT>,
+    code: &'a dyn LpnCode<RingT>,
+    // code generated from a per-session seed, replaces `code`
+    session_code: Option<Box<dyn LpnCode<RingT>>>,
+    delta: RingT,
     nightly_version: bool, // with extra protocol optimizations
     is_init_done: bool,
+    // number of completed iterations and epoch of the OT extension PRGs
+    iteration: u64,
+    ot_epoch: u64,
+    checkpoints: Option<Checkpoints>,
     stats: VerifierStats,
 }
 
+// buffers reused across iterations of the LPN extension
+struct ExtendBuffers<RingT> {
+    b: Vec<RingT>,
+    k_old: Vec<RingT>,
+    y: Vec<RingT>,
+}
+
+impl<RingT: Copy + Default> ExtendBuffers<RingT> {
+    fn new(base_vole_len: usize, sp_vole_total_len: usize) -> Self {
+        Self {
+            b: vec![Default::default(); sp_vole_total_len],
+            k_old: vec![Default::default(); base_vole_len],
+            y: vec![Default::default(); sp_vole_total_len],
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, Default, Serialize)]
 pub struct VerifierStats {
+    pub setup_run_time: Duration,
     pub expansion_run_time: Duration,
     pub sp_stats: SpVerifierStats,
 }
@@ -46,31 +80,40 @@ where
     Standard: Distribution<RingT>,
     for<'b> &'b RingT: Sendable,
 {
-    pub fn new_with_default_size(cache: CachedVerifier<RingT>, code: &'a LLCode<RingT>) -> Self {
+    pub fn new_with_default_size(
+        cache: CachedVerifier<RingT>,
+        code: &'a dyn LpnCode<RingT>,
+    ) -> Self {
         Self::new(cache, code, REG_MAIN_K, REG_MAIN_T, REG_MAIN_SPLEN, false)
     }
 
     pub fn new(
         cache: CachedVerifier<RingT>,
-        code: &'a LLCode<RingT>,
+        code: &'a dyn LpnCode<RingT>,
         base_vole_len: usize,
         num_sp_voles: usize,
         sp_vole_len: usize,
         nightly_version: bool,
     ) -> Self {
-        let spvole = SpVerifier::<RingT>::new(num_sp_voles, sp_vole_len, nightly_version);
+        let spvole = SpVerifier::<RingT>::new(num_sp_voles, sp_vole_len, 1, nightly_version);
         let sp_vole_total_len = sp_vole_len * num_sp_voles;
-        assert_eq!(code.rows, base_vole_len);
-        assert_eq!(code.columns, sp_vole_total_len);
+        assert_eq!(code.rows(), base_vole_len);
+        assert_eq!(code.columns(), sp_vole_total_len);
         Self {
             spvole,
             base_vole_len,
             sp_vole_total_len,
             num_sp_voles,
             cache,
+            buffers: ExtendBuffers::new(base_vole_len, sp_vole_total_len),
             code,
+            session_code: None,
+            delta: Default::default(),
             nightly_version,
             is_init_done: false,
+            iteration: 0,
+            ot_epoch: 0,
+            checkpoints: None,
             stats: Default::default(),
         }
     }
@@ -79,12 +122,142 @@ where
         self.stats
     }
 
+    /// Run the SP-VOLEs in `num_sub_batches` pipelined sub-batches, such that communication and
+    /// computation overlap.  Needs to be called before `init`.
+    pub fn set_sp_vole_sub_batches(&mut self, num_sub_batches: usize) {
+        assert!(!self.is_init_done);
+        let tree_expansion = self.spvole.get_tree_expansion();
+        self.spvole = SpVerifier::<RingT>::new(
+            self.num_sp_voles,
+            self.sp_vole_total_len / self.num_sp_voles,
+            num_sub_batches,
+            self.nightly_version,
+        );
+        self.spvole.set_tree_expansion(tree_expansion);
+    }
+
+    /// Select how the SP-VOLE trees are expanded (see `Prover::set_tree_expansion`).
+    pub fn set_tree_expansion(&mut self, expansion: TreeExpansion) {
+        assert!(!self.is_init_done);
+        self.spvole.set_tree_expansion(expansion);
+    }
+
+    /// Replace the base VOLE cache by freshly bootstrapped base VOLEs (see
+    /// `Prover::refresh_cache`).
+    pub fn refresh_cache(&mut self, cache: CachedVerifier<RingT>) {
+        self.cache = cache;
+        if self.checkpoints.is_some() {
+            self.checkpoints = Some(Checkpoints::new());
+        }
+    }
+
+    /// Switch to a fresh `delta` in the middle of a session, e.g., at a key rotation.  The OTs of
+    /// the SP-VOLEs are kept, but the cache is replaced by `num_base_voles` base VOLEs for the
+    /// new `delta` that are bootstrapped together with the prover (see `cache::bootstrap`).  If
+    /// these are not enough for the main iteration, `setup` needs to be run afterwards.  VOLEs
+    /// obtained before must not be combined with the ones obtained afterwards.  The prover
+    /// needs to call `Prover::rekey` with the same `num_base_voles`.
+    ///
+    /// To use several independent `delta`s side by side (e.g., one per prover), use one verifier
+    /// per `delta`, each with its own channel and cache.
+    pub fn rekey<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        delta: RingT,
+        num_base_voles: usize,
+    ) -> Result<(), Error> {
+        assert!(self.is_init_done);
+        rekey_parameters(num_base_voles).exchange(channel)?;
+        let mut base_vole_verifier = BaseVoleVerifier::<RingT>::new();
+        base_vole_verifier.init(channel, delta)?;
+        self.refresh_cache(base_vole_verifier.extend(channel, num_base_voles)?);
+        self.delta = base_vole_verifier.get_delta();
+        self.spvole.set_delta(self.delta);
+        Ok(())
+    }
+
+    pub fn get_code(&self) -> &dyn LpnCode<RingT> {
+        self.session_code.as_deref().unwrap_or(self.code)
+    }
+
+    /// Like `init`, but replace the code by a code of the same dimensions generated from
+    /// `code_seed` (e.g., obtained with `lpn::cointoss_code_seed`).
+    pub fn init_with_code_seed<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        delta: RingT,
+        code_seed: Block,
+    ) -> Result<(), Error> {
+        self.session_code = Some(self.code.with_seed(code_seed));
+        self.init(channel, delta)
+    }
+
+    fn session_parameters(&self) -> SessionParameters {
+        session_parameters(
+            self.get_code(),
+            self.base_vole_len,
+            self.num_sp_voles,
+            self.sp_vole_total_len / self.num_sp_voles,
+            self.spvole.num_sub_batches(),
+            self.spvole.get_tree_expansion(),
+            self.nightly_version,
+        )
+    }
+
     pub fn init<C: AbstractChannel>(&mut self, channel: &mut C, delta: RingT) -> Result<(), Error> {
+        self.session_parameters().exchange(channel)?;
         self.spvole.init(channel, delta)?;
+        self.delta = delta;
         self.is_init_done = true;
         Ok(())
     }
 
+    /// See `Prover::enable_checkpoints`.
+    pub fn enable_checkpoints(&mut self) {
+        assert!(self.is_init_done);
+        self.checkpoints = Some(Checkpoints::new());
+    }
+
+    /// Number of iterations completed in this session (counting from `enable_checkpoints`).
+    pub fn completed_iterations(&self) -> u64 {
+        self.iteration
+    }
+
+    /// Counterpart of `Prover::resume`.
+    pub fn resume<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<u64, Error> {
+        assert!(self.is_init_done);
+        let checkpoints = self
+            .checkpoints
+            .as_ref()
+            .expect("checkpoints need to be enabled to resume");
+        let (iteration, epoch) = checkpoint::agree(
+            channel,
+            &self.session_parameters(),
+            self.iteration,
+            self.ot_epoch,
+        )?;
+        let required = reg_vole_required(self.base_vole_len, self.num_sp_voles);
+        let cache_len =
+            checkpoints.resume_len(iteration, self.iteration, self.cache.capacity(), required);
+        let cache_len = checkpoint::agree_cache_len(channel, cache_len)?;
+        if cache_len < required {
+            return Err(Error::Other(
+                "not enough base voles in cache to resume".to_string(),
+            ));
+        }
+        self.cache.truncate(cache_len);
+        self.spvole.reseed_ot(epoch);
+        self.iteration = iteration;
+        self.ot_epoch = epoch;
+        self.checkpoints = Some(Checkpoints::new());
+        Ok(iteration)
+    }
+
+    // count a completed iteration, called once its output is stored
+    fn complete_iteration(&mut self) {
+        self.iteration += 1;
+    }
+
     fn enough_voles_cached(&self, n: usize) -> bool {
         self.cache.capacity() >= n + reg_vole_required(self.base_vole_len, self.num_sp_voles)
     }
@@ -95,10 +268,11 @@ where
         }
 
         // replenish using main iteration
-        let y = self.base_extend(channel)?;
+        self.run_extension(channel)?;
 
         // store voles in the cache
-        self.cache.append(y.into_iter());
+        self.cache.append(self.buffers.y.iter().copied());
+        self.complete_iteration();
         Ok(())
     }
 
@@ -137,20 +311,191 @@ where
         Ok(self.cache.get(n))
     }
 
+    /// Like `extend`, but write the VOLEs into the given buffer instead of allocating a new
+    /// vector.
+    pub fn extend_into<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        v_out: &mut [RingT],
+    ) -> Result<(), Error> {
+        self.ensure(channel, v_out.len())?;
+        self.cache.get_into(v_out);
+        Ok(())
+    }
+
+    /// Iterate over chunks of at most `chunk_size` VOLEs.  A new extension is only run once the
+    /// cache is used up, so at most one extension is held in memory at any time.
+    pub fn stream<'p, 'c, C: AbstractChannel>(
+        &'p mut self,
+        channel: &'c mut C,
+        chunk_size: usize,
+    ) -> VerifierStream<'p, 'a, 'c, RingT, C> {
+        assert!(chunk_size > 0);
+        VerifierStream {
+            verifier: self,
+            channel,
+            chunk_size,
+            failed: false,
+        }
+    }
+
+    /// Run a setup iteration with smaller LPN parameters (given by `setup_code`,
+    /// `setup_num_sp_voles` and `setup_sp_vole_single_len`) to expand a small cache of base
+    /// VOLEs into one that is large enough for the main iteration.
+    pub fn setup<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        setup_code: &dyn LpnCode<RingT>,
+        setup_num_sp_voles: usize,
+        setup_sp_vole_single_len: usize,
+    ) -> Result<(), Error> {
+        assert!(self.is_init_done);
+        assert_eq!(
+            setup_code.columns(),
+            setup_num_sp_voles * setup_sp_vole_single_len
+        );
+        let setup_required = reg_vole_required(setup_code.rows(), setup_num_sp_voles);
+        if self.cache.capacity() < setup_required {
+            return Err(Error::Other(
+                "not enough base voles in cache for the setup iteration".to_string(),
+            ));
+        }
+        if self.cache.capacity() - setup_required + setup_code.columns()
+            < reg_vole_required(self.base_vole_len, self.num_sp_voles)
+        {
+            return Err(Error::Other(
+                "setup iteration does not produce enough base voles for the main iteration"
+                    .to_string(),
+            ));
+        }
+
+        let t_start = Instant::now();
+        let mut setup_spvole = SpVerifier::<RingT>::new(
+            setup_num_sp_voles,
+            setup_sp_vole_single_len,
+            1,
+            self.nightly_version,
+        );
+        setup_spvole.set_tree_expansion(self.spvole.get_tree_expansion());
+        setup_spvole.init(channel, self.delta)?;
+        let mut setup_buffers = ExtendBuffers::new(
+            setup_code.rows(),
+            setup_num_sp_voles * setup_sp_vole_single_len,
+        );
+        Self::lpn_extend(
+            channel,
+            &mut setup_spvole,
+            &mut self.cache,
+            setup_code,
+            &mut setup_buffers,
+            &mut self.stats,
+        )?;
+        self.cache.append(setup_buffers.y.into_iter());
+        self.stats.setup_run_time = t_start.elapsed();
+        Ok(())
+    }
+
     pub fn base_extend<C: AbstractChannel>(
         &mut self,
         channel: &mut C,
     ) -> Result<Vec<RingT>, Error> {
+        self.run_extension(channel)?;
+        self.complete_iteration();
+        Ok(self.buffers.y.clone())
+    }
+
+    // run the main iteration, the output is left in `self.buffers.y`
+    fn run_extension<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
         assert!(self.is_init_done);
+        if let Some(checkpoints) = self.checkpoints.as_mut() {
+            checkpoints.start_iteration(self.iteration, self.cache.capacity());
+        }
+        Self::lpn_extend(
+            channel,
+            &mut self.spvole,
+            &mut self.cache,
+            self.session_code.as_deref().unwrap_or(self.code),
+            &mut self.buffers,
+            &mut self.stats,
+        )
+    }
 
-        let mut b = vec![Default::default(); self.sp_vole_total_len];
-        self.spvole.extend(channel, &mut self.cache, &mut b)?;
-        self.stats.sp_stats = self.spvole.get_stats();
-        let k_cached: Vec<RingT> = self.cache.get(self.base_vole_len);
+    fn lpn_extend<C: AbstractChannel>(
+        channel: &mut C,
+        spvole: &mut SpVerifier<RingT>,
+        cache: &mut CachedVerifier<RingT>,
+        code: &dyn LpnCode<RingT>,
+        buffers: &mut ExtendBuffers<RingT>,
+        stats: &mut VerifierStats,
+    ) -> Result<(), Error> {
+        spvole.extend(channel, cache, &mut buffers.b)?;
+        stats.sp_stats = spvole.get_stats();
+        cache.get_into(&mut buffers.k_old);
         let t_start = Instant::now();
-        let out = self.code.mul_add(&k_cached[..], &b);
-        self.stats.expansion_run_time = t_start.elapsed();
+        code.mul_add_into(&buffers.k_old, &buffers.b, &mut buffers.y);
+        stats.expansion_run_time = t_start.elapsed();
+
+        Ok(())
+    }
+}
+
+/// Iterator over chunks of VOLEs returned by `Verifier::stream`.
+pub struct VerifierStream<'p, 'a, 'c, RingT, C>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    verifier: &'p mut Verifier<'a, RingT>,
+    channel: &'c mut C,
+    chunk_size: usize,
+    failed: bool,
+}
+
+impl<'p, 'a, 'c, RingT, C> Iterator for VerifierStream<'p, 'a, 'c, RingT, C>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+    C: AbstractChannel,
+{
+    type Item = Result<Vec<RingT>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        if !self.verifier.enough_voles_cached(1) {
+            if let Err(e) = self.verifier.replenish_cache(self.channel) {
+                self.failed = true;
+                return Some(Err(e));
+            }
+        }
+        let required = reg_vole_required(self.verifier.base_vole_len, self.verifier.num_sp_voles);
+        let n = usize::min(self.chunk_size, self.verifier.cache.capacity() - required);
+        Some(Ok(self.verifier.cache.get(n)))
+    }
+}
+
+impl<'a, RingT> VoleSender for Verifier<'a, RingT>
+where
+    RingT: Ring + Receivable,
+    Standard: Distribution<RingT>,
+    for<'b> &'b RingT: Sendable,
+{
+    type Value = RingT;
+    type Mac = RingT;
 
-        return Ok(out);
+    fn get_delta(&self) -> RingT {
+        self.delta
+    }
+
+    fn send_vole<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+        _rng: &mut RNG,
+    ) -> Result<Vec<RingT>, Error> {
+        self.extend(channel, n)
     }
 }
@@ -0,0 +1,211 @@
+//! Arithmetic circuits over Z_2^k (or a prime field) that can be proven with QuarkSilver (see
+//! `Prover::prove_circuit` and `Verifier::verify_circuit`).
+//!
+//! Every gate except `AssertZero` defines a new wire.  The values of the input gates are given
+//! by the prover's witness in the order of the gates.
+use rand::distributions::{Distribution, Standard};
+use scuttlebutt::ring::Ring;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Chunk size used for the parallel multiplication check of a circuit.
+pub(super) const CHECK_CHUNK_SIZE: usize = 1 << 14;
+
+/// A wire of a `Circuit`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Wire(pub(super) usize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Gate<RingT> {
+    /// Private input of the prover.
+    Input,
+    /// Public constant.
+    Constant(RingT),
+    Add(Wire, Wire),
+    Sub(Wire, Wire),
+    /// Multiplication with a public constant.
+    CMul(Wire, RingT),
+    Mul(Wire, Wire),
+    /// Assert that the wire is zero (modulo 2^k over Z_2^l).
+    AssertZero(Wire),
+}
+
+/// Number of gates of each type.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct GateCounts {
+    pub inputs: usize,
+    pub constants: usize,
+    pub adds: usize,
+    pub subs: usize,
+    pub cmuls: usize,
+    pub muls: usize,
+    pub assert_zeros: usize,
+}
+
+/// Statistics of proving or verifying a circuit.
+#[derive(Copy, Clone, Debug, Default, Serialize)]
+pub struct CircuitStats {
+    pub gates: GateCounts,
+    /// Time to obtain the VOLEs for the input and multiplication gates.
+    pub vole_time: Duration,
+    /// Time to evaluate the gates and to exchange the values of the input and multiplication
+    /// gates.
+    pub evaluation_time: Duration,
+    pub check_multiply_time: Duration,
+    pub check_zero_time: Duration,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Circuit<RingT> {
+    gates: Vec<Gate<RingT>>,
+    num_wires: usize,
+    counts: GateCounts,
+}
+
+impl<RingT: Copy> Circuit<RingT> {
+    pub fn new() -> Self {
+        Self {
+            gates: Vec::new(),
+            num_wires: 0,
+            counts: Default::default(),
+        }
+    }
+
+    pub fn gates(&self) -> &[Gate<RingT>] {
+        &self.gates
+    }
+
+    pub fn num_wires(&self) -> usize {
+        self.num_wires
+    }
+
+    pub fn gate_counts(&self) -> GateCounts {
+        self.counts
+    }
+
+    fn check_wire(&self, wire: Wire) {
+        assert!(wire.0 < self.num_wires, "unknown wire {}", wire.0);
+    }
+
+    fn push(&mut self, gate: Gate<RingT>) -> Wire {
+        self.gates.push(gate);
+        self.num_wires += 1;
+        Wire(self.num_wires - 1)
+    }
+
+    pub fn input(&mut self) -> Wire {
+        self.counts.inputs += 1;
+        self.push(Gate::Input)
+    }
+
+    pub fn constant(&mut self, c: RingT) -> Wire {
+        self.counts.constants += 1;
+        self.push(Gate::Constant(c))
+    }
+
+    pub fn add(&mut self, a: Wire, b: Wire) -> Wire {
+        self.check_wire(a);
+        self.check_wire(b);
+        self.counts.adds += 1;
+        self.push(Gate::Add(a, b))
+    }
+
+    pub fn sub(&mut self, a: Wire, b: Wire) -> Wire {
+        self.check_wire(a);
+        self.check_wire(b);
+        self.counts.subs += 1;
+        self.push(Gate::Sub(a, b))
+    }
+
+    pub fn cmul(&mut self, a: Wire, c: RingT) -> Wire {
+        self.check_wire(a);
+        self.counts.cmuls += 1;
+        self.push(Gate::CMul(a, c))
+    }
+
+    pub fn mul(&mut self, a: Wire, b: Wire) -> Wire {
+        self.check_wire(a);
+        self.check_wire(b);
+        self.counts.muls += 1;
+        self.push(Gate::Mul(a, b))
+    }
+
+    pub fn assert_zero(&mut self, a: Wire) {
+        self.check_wire(a);
+        self.counts.assert_zeros += 1;
+        self.gates.push(Gate::AssertZero(a));
+    }
+}
+
+impl<RingT: Ring> Circuit<RingT>
+where
+    Standard: Distribution<RingT>,
+{
+    /// Evaluate the circuit in the clear and check whether all `AssertZero` gates hold (modulo
+    /// 2^k over Z_2^l).
+    pub fn is_satisfied(&self, witness: &[RingT], k: usize) -> bool {
+        assert_eq!(witness.len(), self.counts.inputs);
+        let mut wires: Vec<RingT> = Vec::with_capacity(self.num_wires);
+        let mut witness = witness.iter();
+        for gate in self.gates.iter() {
+            let out = match *gate {
+                Gate::Input => *witness.next().unwrap(),
+                Gate::Constant(c) => c,
+                Gate::Add(a, b) => wires[a.0] + wires[b.0],
+                Gate::Sub(a, b) => wires[a.0] - wires[b.0],
+                Gate::CMul(a, c) => c * wires[a.0],
+                Gate::Mul(a, b) => wires[a.0] * wires[b.0],
+                Gate::AssertZero(a) => {
+                    if !wires[a.0].reduce_to_bits(k).is_zero() {
+                        return false;
+                    }
+                    continue;
+                }
+            };
+            wires.push(out);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Circuit, Gate, GateCounts, Wire};
+
+    #[test]
+    fn test_circuit_builder() {
+        let mut circuit = Circuit::<u64>::new();
+        let x = circuit.input();
+        let y = circuit.input();
+        let c = circuit.constant(3);
+        let xy = circuit.mul(x, y);
+        let z = circuit.sub(xy, c);
+        let z = circuit.add(z, x);
+        let z = circuit.cmul(z, 2);
+        circuit.assert_zero(z);
+        assert_eq!(circuit.num_wires(), 7);
+        assert_eq!(circuit.gates().len(), 8);
+        assert_eq!(circuit.gates()[3], Gate::Mul(Wire(0), Wire(1)));
+        assert_eq!(circuit.gates()[7], Gate::AssertZero(Wire(6)));
+        assert_eq!(
+            circuit.gate_counts(),
+            GateCounts {
+                inputs: 2,
+                constants: 1,
+                adds: 1,
+                subs: 1,
+                cmuls: 1,
+                muls: 1,
+                assert_zeros: 1,
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_circuit_unknown_wire() {
+        let mut circuit = Circuit::<u64>::new();
+        let x = circuit.input();
+        circuit.add(x, Wire(1));
+    }
+}
@@ -1,7 +1,16 @@
+use crate::ot::mozzarella::utils::log2;
 use crate::quarksilver::prover::{Prover, ProverStats};
 use crate::quarksilver::verifier::{Verifier, VerifierStats};
+use crate::Error;
+use rand::distributions::{Distribution, Standard};
+use rand::{Rng, SeedableRng};
+use scuttlebutt::ring::Ring;
+use scuttlebutt::{AesHash, AesRng, Block};
+use sha2::{Digest, Sha256};
 
+pub mod circuit;
 mod prover;
+pub mod sieve;
 mod verifier;
 
 pub type QuarkSilverProver<'a, RingT> = Prover<'a, RingT>;
@@ -9,3 +18,579 @@ pub type QuarkSilverVerifier<'a, RingT> = Verifier<'a, RingT>;
 
 pub type QuarkSilverProverStats = ProverStats;
 pub type QuarkSilverVerifierStats = VerifierStats;
+
+// over Z_2^l, values live in Z_2^k and the checks need l >= k + 2s + log s; over a field F_p
+// (where k is the size of p) they fail with probability at most 2/p
+fn check_parameters<RingT: Ring>(k: usize, statsec: usize)
+where
+    Standard: Distribution<RingT>,
+{
+    if let Err(e) = validate_parameters::<RingT>(k, statsec) {
+        panic!("{}", e);
+    }
+}
+
+/// Check that values of `k` bits can be proven with statistical security `statsec` in `RingT`,
+/// which `QuarkSilverProver::new` and `QuarkSilverVerifier::new` require.
+pub fn validate_parameters<RingT: Ring>(k: usize, statsec: usize) -> Result<(), Error>
+where
+    Standard: Distribution<RingT>,
+{
+    if RingT::IS_FIELD {
+        if RingT::BIT_LENGTH != k || RingT::BIT_LENGTH <= statsec + 1 {
+            return Err(Error::Other(format!(
+                "in a {}-bit field, values need {} bits and the statistical security needs to \
+                 be below {}",
+                RingT::BIT_LENGTH,
+                RingT::BIT_LENGTH,
+                RingT::BIT_LENGTH - 1
+            )));
+        }
+    } else if k + 2 * statsec + log2(statsec) > RingT::BIT_LENGTH {
+        return Err(Error::Other(format!(
+            "{}-bit values with statistical security {} need a ring of at least {} bits",
+            k,
+            statsec,
+            k + 2 * statsec + log2(statsec)
+        )));
+    }
+    Ok(())
+}
+
+// 2^k, computed by doubling since k may exceed the width of the machine integers
+fn two_to_the<RingT: Ring>(k: usize) -> RingT
+where
+    Standard: Distribution<RingT>,
+{
+    (0..k).fold(RingT::ONE, |x, _| x + x)
+}
+
+// Opened values are checked by comparing a hash of their MACs instead of sending every MAC.
+// Over Z_2^l, only the lower k bits of a value are meaningful and the upper bits could leak
+// information about the witness, so the prover opens x + 2^k * r for a fresh random VOLE r.  A
+// prover that changes the lower k bits of an opened value by e has to guess delta * e, which
+// depends on at least l - k >= 2s bits of delta.
+fn hash_macs<RingT: Ring>(macs: impl Iterator<Item = RingT>) -> [u8; 32]
+where
+    Standard: Distribution<RingT>,
+{
+    let mut hasher = Sha256::new();
+    for mac in macs {
+        hasher.update(mac.reduce().as_ref());
+    }
+    hasher.finalize().into()
+}
+
+// The challenges of the multiplication checks are drawn in blocks from a PRG seeded with a hash of
+// the verifier's seed and the block index.  Thus chi_i depends only on i, and any range of the
+// challenges can be computed independently of how the check is split between threads.
+const CHI_BLOCK_SIZE: usize = 1 << 12;
+
+// the challenges chi_start, chi_(start + 1), ...
+fn chis<RingT: Ring>(seed: Block, start: usize) -> impl Iterator<Item = RingT>
+where
+    Standard: Distribution<RingT>,
+{
+    let hash = AesHash::new(seed);
+    (start / CHI_BLOCK_SIZE..)
+        .flat_map(move |block| {
+            let block_seed = hash.cr_hash(Block::default(), Block::from(block as u128));
+            let mut rng = AesRng::from_seed(block_seed);
+            (0..CHI_BLOCK_SIZE).map(move |_| rng.gen::<RingT>())
+        })
+        .skip(start % CHI_BLOCK_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        chis, circuit::Circuit, validate_parameters, QuarkSilverProver, QuarkSilverVerifier,
+        CHI_BLOCK_SIZE,
+    };
+    use crate::ot::mozzarella::{
+        cache::cacheinit::GenCache, lpn::LLCode, reg_vole_required, CODE_D,
+    };
+    use rand::{
+        distributions::{Distribution, Standard},
+        rngs::OsRng,
+        Rng,
+    };
+    use scuttlebutt::{
+        channel::{Receivable, Sendable},
+        ring::{F61p, Ring, R64},
+        unix_channel_pair, AbstractChannel, Block,
+    };
+    use std::{sync::Arc, thread::spawn};
+
+    const BASE_VOLE_LEN: usize = 10;
+    const NUM_SP_VOLES: usize = 4;
+    const SINGLE_SP_OUTPUT_SIZE: usize = 16;
+
+    fn test_open_and_check_zero<RingT>(k: usize, statsec: usize)
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        const N: usize = 20;
+        let code = Arc::new(LLCode::<RingT>::from_seed(
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES * SINGLE_SP_OUTPUT_SIZE,
+            CODE_D,
+            Block::default(),
+        ));
+        let delta = OsRng.gen::<RingT>().reduce();
+        let (cached_prover, cached_verifier) = GenCache::new_with_size(
+            OsRng,
+            delta,
+            2 * reg_vole_required(BASE_VOLE_LEN, NUM_SP_VOLES),
+        );
+        let values: Vec<RingT> = (0..N).map(|_| OsRng.gen()).collect();
+        let expected: Vec<RingT> = values.iter().map(|x| x.reduce_to_bits(k)).collect();
+
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let code_p = code.clone();
+        let (values_p, expected_p) = (values.clone(), expected.clone());
+        let prover_thread = spawn(move || {
+            let mut prover = QuarkSilverProver::<RingT>::new(
+                k,
+                statsec,
+                cached_prover,
+                &*code_p,
+                BASE_VOLE_LEN,
+                NUM_SP_VOLES,
+                SINGLE_SP_OUTPUT_SIZE,
+            );
+            prover.init(&mut channel_p).unwrap();
+            let (xs, x_macs) = prover.input_batch(&mut channel_p, values_p).unwrap();
+            prover.open_batch(&mut channel_p, (&xs, &x_macs)).unwrap();
+            let negated: Vec<RingT> = xs.iter().map(|&x| -x).collect();
+            let negated_macs: Vec<RingT> = x_macs.iter().map(|&m| -m).collect();
+            let (diffs, diff_macs) = prover.add_batch((&xs, &x_macs), (&negated, &negated_macs));
+            prover
+                .check_zero_batch(&mut channel_p, (&diffs, &diff_macs))
+                .unwrap();
+            prover
+                .assert_eq_public(&mut channel_p, (&xs, &x_macs), &expected_p)
+                .unwrap();
+            // cheating: claim that the values are zero
+            prover
+                .check_zero_batch(&mut channel_p, (&vec![RingT::ZERO; N], &x_macs))
+                .unwrap();
+            // a false claim about a public value
+            let mut wrong = expected_p;
+            wrong[0] += RingT::ONE;
+            prover
+                .assert_eq_public(&mut channel_p, (&xs, &x_macs), &wrong)
+                .unwrap();
+        });
+
+        let mut verifier = QuarkSilverVerifier::<RingT>::new(
+            k,
+            statsec,
+            cached_verifier,
+            &*code,
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES,
+            SINGLE_SP_OUTPUT_SIZE,
+        );
+        verifier.init(&mut channel_v, delta).unwrap();
+        let x_keys = verifier.input_batch(&mut channel_v, N).unwrap();
+        let opened = verifier.open_batch(&mut channel_v, &x_keys).unwrap();
+        assert_eq!(opened, expected);
+        let negated_keys: Vec<RingT> = x_keys.iter().map(|&key| -key).collect();
+        let diff_keys = verifier.add_batch(&x_keys, &negated_keys);
+        assert!(verifier
+            .check_zero_batch(&mut channel_v, &diff_keys)
+            .is_ok());
+        assert!(verifier
+            .assert_eq_public(&mut channel_v, &x_keys, &expected)
+            .is_ok());
+        assert!(verifier.check_zero_batch(&mut channel_v, &x_keys).is_err());
+        let mut wrong = expected.clone();
+        wrong[0] += RingT::ONE;
+        assert!(verifier
+            .assert_eq_public(&mut channel_v, &x_keys, &wrong)
+            .is_err());
+        prover_thread.join().unwrap();
+    }
+
+    fn test_check_multiply<RingT>(k: usize, statsec: usize)
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        const N: usize = 100;
+        // (prover multi-threaded, verifier multi-threaded, chunk size)
+        const SETTINGS: [(bool, bool, usize); 3] =
+            [(true, false, 7), (false, true, 16), (true, true, 33)];
+        let code = Arc::new(LLCode::<RingT>::from_seed(
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES * SINGLE_SP_OUTPUT_SIZE,
+            CODE_D,
+            Block::default(),
+        ));
+        let delta = OsRng.gen::<RingT>().reduce();
+        let (cached_prover, cached_verifier) = GenCache::new_with_size(
+            OsRng,
+            delta,
+            2 * reg_vole_required(BASE_VOLE_LEN, NUM_SP_VOLES),
+        );
+
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let code_p = code.clone();
+        let prover_thread = spawn(move || {
+            let mut prover = QuarkSilverProver::<RingT>::new(
+                k,
+                statsec,
+                cached_prover,
+                &*code_p,
+                BASE_VOLE_LEN,
+                NUM_SP_VOLES,
+                SINGLE_SP_OUTPUT_SIZE,
+            );
+            prover.init(&mut channel_p).unwrap();
+            let alphas: Vec<RingT> = (0..N).map(|_| OsRng.gen()).collect();
+            let betas: Vec<RingT> = (0..N).map(|_| OsRng.gen()).collect();
+            let (alphas, alpha_macs) = prover.input_batch(&mut channel_p, alphas).unwrap();
+            let (betas, beta_macs) = prover.input_batch(&mut channel_p, betas).unwrap();
+            let (gammas, gamma_macs) = prover
+                .multiply_batch(&mut channel_p, (&alphas, &alpha_macs), (&betas, &beta_macs))
+                .unwrap();
+            let mut triples: Vec<_> = (0..N)
+                .map(|i| {
+                    (
+                        (alphas[i], alpha_macs[i]),
+                        (betas[i], beta_macs[i]),
+                        (gammas[i], gamma_macs[i]),
+                    )
+                })
+                .collect();
+            for &(multi_thread, _, chunk_size) in SETTINGS.iter() {
+                prover
+                    .check_multiply(&mut channel_p, &mut triples, multi_thread, chunk_size)
+                    .unwrap();
+            }
+            // cheating: a wrong product
+            triples[N - 1].2 .1 += delta;
+            prover
+                .check_multiply(&mut channel_p, &mut triples, true, 7)
+                .unwrap();
+        });
+
+        let mut verifier = QuarkSilverVerifier::<RingT>::new(
+            k,
+            statsec,
+            cached_verifier,
+            &*code,
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES,
+            SINGLE_SP_OUTPUT_SIZE,
+        );
+        verifier.init(&mut channel_v, delta).unwrap();
+        let alpha_keys = verifier.input_batch(&mut channel_v, N).unwrap();
+        let beta_keys = verifier.input_batch(&mut channel_v, N).unwrap();
+        let gamma_keys = verifier
+            .multiply_batch(&mut channel_v, &alpha_keys, &beta_keys)
+            .unwrap();
+        let mut triples: Vec<_> = (0..N)
+            .map(|i| (alpha_keys[i], beta_keys[i], gamma_keys[i]))
+            .collect();
+        for &(_, multi_thread, chunk_size) in SETTINGS.iter() {
+            verifier
+                .check_multiply(
+                    &mut channel_v,
+                    OsRng,
+                    &mut triples,
+                    multi_thread,
+                    chunk_size,
+                )
+                .unwrap();
+        }
+        assert!(verifier
+            .check_multiply(&mut channel_v, OsRng, &mut triples, false, 1)
+            .is_err());
+        prover_thread.join().unwrap();
+    }
+
+    fn test_circuit<RingT>(k: usize, statsec: usize)
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        let code = Arc::new(LLCode::<RingT>::from_seed(
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES * SINGLE_SP_OUTPUT_SIZE,
+            CODE_D,
+            Block::default(),
+        ));
+        let delta = OsRng.gen::<RingT>().reduce();
+        let (cached_prover, cached_verifier) = GenCache::new_with_size(
+            OsRng,
+            delta,
+            2 * reg_vole_required(BASE_VOLE_LEN, NUM_SP_VOLES),
+        );
+
+        // x * y * y + 3 * z - c == 0 and x - x * 1 == 0, for a public c
+        let (x, y, z): (RingT, RingT, RingT) = (OsRng.gen(), OsRng.gen(), OsRng.gen());
+        let c = x * y * y + z * 3u64;
+        let mut circuit = Circuit::new();
+        let (x_wire, y_wire, z_wire) = (circuit.input(), circuit.input(), circuit.input());
+        let c_wire = circuit.constant(c);
+        let xy_wire = circuit.mul(x_wire, y_wire);
+        let xyy_wire = circuit.mul(xy_wire, y_wire);
+        let z3_wire = circuit.cmul(z_wire, RingT::ONE + 2u64);
+        let sum_wire = circuit.add(xyy_wire, z3_wire);
+        let out_wire = circuit.sub(sum_wire, c_wire);
+        circuit.assert_zero(out_wire);
+        let one_wire = circuit.constant(RingT::ONE);
+        let x1_wire = circuit.mul(x_wire, one_wire);
+        let out_wire = circuit.sub(x_wire, x1_wire);
+        circuit.assert_zero(out_wire);
+        let circuit = Arc::new(circuit);
+
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let (code_p, circuit_p) = (code.clone(), circuit.clone());
+        let prover_thread = spawn(move || {
+            let mut prover = QuarkSilverProver::<RingT>::new(
+                k,
+                statsec,
+                cached_prover,
+                &*code_p,
+                BASE_VOLE_LEN,
+                NUM_SP_VOLES,
+                SINGLE_SP_OUTPUT_SIZE,
+            );
+            prover.init(&mut channel_p).unwrap();
+            let stats = prover
+                .prove_circuit(&mut channel_p, &circuit_p, &[x, y, z])
+                .unwrap();
+            assert_eq!(stats.gates.muls, 3);
+            assert_eq!(stats.gates.assert_zeros, 2);
+            // a witness that does not satisfy the circuit
+            prover
+                .prove_circuit(&mut channel_p, &circuit_p, &[x, y, z + RingT::ONE])
+                .unwrap();
+        });
+
+        let mut verifier = QuarkSilverVerifier::<RingT>::new(
+            k,
+            statsec,
+            cached_verifier,
+            &*code,
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES,
+            SINGLE_SP_OUTPUT_SIZE,
+        );
+        verifier.init(&mut channel_v, delta).unwrap();
+        let stats = verifier.verify_circuit(&mut channel_v, &circuit).unwrap();
+        assert_eq!(stats.gates, circuit.gate_counts());
+        assert!(verifier.verify_circuit(&mut channel_v, &circuit).is_err());
+        prover_thread.join().unwrap();
+    }
+
+    fn test_inner_product_and_matrix_multiply<RingT>(k: usize, statsec: usize)
+    where
+        RingT: Ring + Receivable,
+        Standard: Distribution<RingT>,
+        for<'a> &'a RingT: Sendable,
+    {
+        // a is ROWS x INNER and b is INNER x COLS
+        const DIMS: (usize, usize, usize) = (3, 5, 4);
+        const LENGTHS: [usize; 4] = [1, 7, 0, 20];
+        let (rows, inner, cols) = DIMS;
+        let code = Arc::new(LLCode::<RingT>::from_seed(
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES * SINGLE_SP_OUTPUT_SIZE,
+            CODE_D,
+            Block::default(),
+        ));
+        let delta = OsRng.gen::<RingT>().reduce();
+        let (cached_prover, cached_verifier) = GenCache::new_with_size(
+            OsRng,
+            delta,
+            2 * reg_vole_required(BASE_VOLE_LEN, NUM_SP_VOLES),
+        );
+
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let code_p = code.clone();
+        let prover_thread = spawn(move || {
+            let mut prover = QuarkSilverProver::<RingT>::new(
+                k,
+                statsec,
+                cached_prover,
+                &*code_p,
+                BASE_VOLE_LEN,
+                NUM_SP_VOLES,
+                SINGLE_SP_OUTPUT_SIZE,
+            );
+            prover.init(&mut channel_p).unwrap();
+
+            let mut vectors = Vec::new();
+            for &n in LENGTHS.iter() {
+                let xs: Vec<RingT> = (0..n).map(|_| OsRng.gen()).collect();
+                let ys: Vec<RingT> = (0..n).map(|_| OsRng.gen()).collect();
+                let xs = prover.input_batch(&mut channel_p, xs).unwrap();
+                let ys = prover.input_batch(&mut channel_p, ys).unwrap();
+                vectors.push((xs, ys));
+            }
+            let a: Vec<_> = vectors
+                .iter()
+                .map(|((xs, x_macs), _)| (xs.as_slice(), x_macs.as_slice()))
+                .collect();
+            let b: Vec<_> = vectors
+                .iter()
+                .map(|(_, (ys, y_macs))| (ys.as_slice(), y_macs.as_slice()))
+                .collect();
+            let (zs, z_macs) = prover.inner_product_batch(&mut channel_p, &a, &b).unwrap();
+            prover
+                .check_inner_product_batch(&mut channel_p, &a, &b, (&zs, &z_macs))
+                .unwrap();
+
+            let a: Vec<RingT> = (0..rows * inner).map(|_| OsRng.gen()).collect();
+            let b: Vec<RingT> = (0..inner * cols).map(|_| OsRng.gen()).collect();
+            let (a, a_macs) = prover.input_batch(&mut channel_p, a).unwrap();
+            let (b, b_macs) = prover.input_batch(&mut channel_p, b).unwrap();
+            let (c, c_macs) = prover
+                .matrix_multiply(&mut channel_p, DIMS, (&a, &a_macs), (&b, &b_macs))
+                .unwrap();
+            for r in 0..rows {
+                for j in 0..cols {
+                    let expected: RingT =
+                        (0..inner).map(|i| a[r * inner + i] * b[i * cols + j]).sum();
+                    assert_eq!(c[r * cols + j], expected);
+                }
+            }
+            prover
+                .check_matrix_multiply(
+                    &mut channel_p,
+                    DIMS,
+                    (&a, &a_macs),
+                    (&b, &b_macs),
+                    (&c, &c_macs),
+                )
+                .unwrap();
+
+            // cheating: commit to a wrong product
+            let mut wrong = c;
+            wrong[rows * cols - 1] += RingT::ONE;
+            let (wrong, wrong_macs) = prover.input_batch(&mut channel_p, wrong).unwrap();
+            channel_p.flush().unwrap();
+            prover
+                .check_matrix_multiply(
+                    &mut channel_p,
+                    DIMS,
+                    (&a, &a_macs),
+                    (&b, &b_macs),
+                    (&wrong, &wrong_macs),
+                )
+                .unwrap();
+        });
+
+        let mut verifier = QuarkSilverVerifier::<RingT>::new(
+            k,
+            statsec,
+            cached_verifier,
+            &*code,
+            BASE_VOLE_LEN,
+            NUM_SP_VOLES,
+            SINGLE_SP_OUTPUT_SIZE,
+        );
+        verifier.init(&mut channel_v, delta).unwrap();
+
+        let mut vectors = Vec::new();
+        for &n in LENGTHS.iter() {
+            let x_keys = verifier.input_batch(&mut channel_v, n).unwrap();
+            let y_keys = verifier.input_batch(&mut channel_v, n).unwrap();
+            vectors.push((x_keys, y_keys));
+        }
+        let a_keys: Vec<&[RingT]> = vectors.iter().map(|(x, _)| x.as_slice()).collect();
+        let b_keys: Vec<&[RingT]> = vectors.iter().map(|(_, y)| y.as_slice()).collect();
+        let z_keys = verifier
+            .inner_product_batch(&mut channel_v, LENGTHS.len())
+            .unwrap();
+        assert!(verifier
+            .check_inner_product_batch(&mut channel_v, &a_keys, &b_keys, &z_keys)
+            .is_ok());
+
+        let a_keys = verifier.input_batch(&mut channel_v, rows * inner).unwrap();
+        let b_keys = verifier.input_batch(&mut channel_v, inner * cols).unwrap();
+        let c_keys = verifier.matrix_multiply(&mut channel_v, DIMS).unwrap();
+        assert!(verifier
+            .check_matrix_multiply(&mut channel_v, DIMS, &a_keys, &b_keys, &c_keys)
+            .is_ok());
+        let c_keys = verifier.matrix_multiply(&mut channel_v, DIMS).unwrap();
+        assert!(verifier
+            .check_matrix_multiply(&mut channel_v, DIMS, &a_keys, &b_keys, &c_keys)
+            .is_err());
+        prover_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_chis() {
+        let seed = OsRng.gen::<Block>();
+        let all: Vec<R64> = chis(seed, 0).take(4 * CHI_BLOCK_SIZE).collect();
+        for &start in [
+            1,
+            CHI_BLOCK_SIZE - 1,
+            CHI_BLOCK_SIZE,
+            2 * CHI_BLOCK_SIZE + 5,
+        ]
+        .iter()
+        {
+            let range: Vec<R64> = chis(seed, start).take(CHI_BLOCK_SIZE).collect();
+            assert_eq!(range[..], all[start..start + CHI_BLOCK_SIZE]);
+        }
+    }
+
+    #[test]
+    fn test_validate_parameters() {
+        assert!(validate_parameters::<R64>(8, 20).is_ok());
+        assert!(validate_parameters::<R64>(32, 20).is_err());
+        assert!(validate_parameters::<F61p>(61, 40).is_ok());
+        assert!(validate_parameters::<F61p>(60, 40).is_err());
+        assert!(validate_parameters::<F61p>(61, 60).is_err());
+    }
+
+    #[test]
+    fn test_check_multiply_r64() {
+        test_check_multiply::<R64>(16, 20);
+    }
+
+    #[test]
+    fn test_check_multiply_f61p() {
+        test_check_multiply::<F61p>(61, 40);
+    }
+
+    #[test]
+    fn test_circuit_r64() {
+        test_circuit::<R64>(16, 20);
+    }
+
+    #[test]
+    fn test_circuit_f61p() {
+        test_circuit::<F61p>(61, 40);
+    }
+
+    #[test]
+    fn test_inner_product_and_matrix_multiply_r64() {
+        test_inner_product_and_matrix_multiply::<R64>(16, 20);
+    }
+
+    #[test]
+    fn test_inner_product_and_matrix_multiply_f61p() {
+        test_inner_product_and_matrix_multiply::<F61p>(61, 40);
+    }
+
+    #[test]
+    fn test_open_and_check_zero_r64() {
+        test_open_and_check_zero::<R64>(16, 20);
+    }
+
+    #[test]
+    fn test_open_and_check_zero_f61p() {
+        test_open_and_check_zero::<F61p>(61, 40);
+    }
+}
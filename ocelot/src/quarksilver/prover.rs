@@ -1,14 +1,15 @@
 use crate::ot::mozzarella::cache::prover::CachedProver;
-use crate::ot::mozzarella::lpn::LLCode;
-use crate::ot::mozzarella::utils::log2;
+use crate::ot::mozzarella::handshake::SessionParameters;
+use crate::ot::mozzarella::lpn::LpnCode;
 use crate::ot::mozzarella::{MozzarellaProver, MozzarellaProverStats};
+use crate::quarksilver::circuit::{Circuit, CircuitStats, Gate, CHECK_CHUNK_SIZE};
+use crate::quarksilver::{check_parameters, chis, hash_macs, two_to_the};
 use crate::Error;
 use rand::distributions::{Distribution, Standard};
-use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
 use scuttlebutt::channel::{Receivable, Sendable};
 use scuttlebutt::ring::Ring;
-use scuttlebutt::{AbstractChannel, AesRng, Block};
+use scuttlebutt::{AbstractChannel, Block};
 use serde::Serialize;
 use std::time::{Duration, Instant};
 
@@ -44,18 +45,18 @@ where
         k: usize,
         statsec: usize,
         cache: CachedProver<RingT>,
-        code: &'a LLCode<RingT>,
+        code: &'a dyn LpnCode<RingT>,
         base_vole_len: usize,
         num_sp_voles: usize,
         sp_vole_len: usize,
     ) -> Self {
-        assert!(RingT::BIT_LENGTH >= k + 2 * statsec + log2(statsec));
+        check_parameters::<RingT>(k, statsec);
         Self {
             k,
             statsec,
             mozProver: MozzarellaProver::<RingT>::new(
                 cache,
-                &code,
+                code,
                 base_vole_len,
                 num_sp_voles,
                 sp_vole_len,
@@ -67,6 +68,10 @@ where
     }
 
     pub fn init<C: AbstractChannel>(&mut self, channel: &mut C) -> Result<(), Error> {
+        SessionParameters::new("QuarkSilver")
+            .add("k", self.k)
+            .add("statsec", self.statsec)
+            .exchange(channel)?;
         let t_start = Instant::now();
         self.mozProver.init(channel)?;
         self.stats.mozz_init = t_start.elapsed();
@@ -74,6 +79,16 @@ where
         Ok(())
     }
 
+    /// Counterpart of `Verifier::rekey`.
+    pub fn rekey<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        num_base_voles: usize,
+    ) -> Result<(), Error> {
+        assert!(self.is_init_done);
+        self.mozProver.rekey(channel, num_base_voles)
+    }
+
     pub fn apply_to_mozzarella_prover<ResT, F: FnOnce(&mut MozzarellaProver<RingT>) -> ResT>(
         &mut self,
         f: F,
@@ -187,9 +202,64 @@ where
         self.input_batch(channel, out)
     }
 
-    pub fn check_zero<C: AbstractChannel>() {
-        // todo: C is supposed to be straight up public, so we need to subtract A*B from C and check
-        //  if it's 0
+    // send the values with their upper bits masked together with a hash of their MACs
+    fn open_masked<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        values: &[RingT],
+        macs: &[RingT],
+    ) -> Result<(), Error> {
+        let n = values.len();
+        assert_eq!(macs.len(), n);
+        let mut values = values.to_vec();
+        let mut macs = macs.to_vec();
+        if !RingT::IS_FIELD {
+            let (r, r_macs) = self.random_batch(channel, n)?;
+            let two_to_k = two_to_the::<RingT>(self.k);
+            for i in 0..n {
+                values[i] += r[i] * two_to_k;
+                macs[i] += r_macs[i] * two_to_k;
+            }
+        }
+        channel.send(values.as_slice())?;
+        channel.send(&hash_macs(macs.into_iter()))?;
+        channel.flush()?;
+        Ok(())
+    }
+
+    /// Open authenticated values to the verifier.  Over Z_2^l, only their lower `k` bits are
+    /// revealed.
+    pub fn open_batch<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        (values, macs): (&[RingT], &[RingT]),
+    ) -> Result<(), Error> {
+        self.open_masked(channel, values, macs)
+    }
+
+    /// Prove that authenticated values are zero (modulo 2^k over Z_2^l).
+    pub fn check_zero_batch<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        (values, macs): (&[RingT], &[RingT]),
+    ) -> Result<(), Error> {
+        self.open_masked(channel, values, macs)
+    }
+
+    /// Prove that authenticated values are equal to public constants (modulo 2^k over Z_2^l).
+    pub fn assert_eq_public<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        (values, macs): (&[RingT], &[RingT]),
+        constants: &[RingT],
+    ) -> Result<(), Error> {
+        assert_eq!(constants.len(), values.len());
+        let diffs: Vec<RingT> = values
+            .iter()
+            .zip(constants.iter())
+            .map(|(&x, &c)| x - c)
+            .collect();
+        self.check_zero_batch(channel, (&diffs, macs))
     }
 
     pub fn check_multiply_batch<C: AbstractChannel>(
@@ -211,10 +281,8 @@ where
         let mut U = RingT::ZERO;
         let mut V = RingT::ZERO;
 
-        let chi_seed: Block = channel.receive().unwrap();
-        let mut seeded_rng = AesRng::from_seed(chi_seed);
-
-        let chis: Vec<RingT> = (0..n).map(|_| seeded_rng.gen()).collect();
+        let chi_seed: Block = channel.receive()?;
+        let chis: Vec<RingT> = chis(chi_seed, 0).take(n).collect();
 
         let t_start = Instant::now();
 
@@ -249,6 +317,23 @@ where
         Ok(())
     }
 
+    // (sum_i chi_i * a0_i, sum_i chi_i * a1_i) for the given triples
+    fn combine_triples(
+        triples: &[((RingT, RingT), (RingT, RingT), (RingT, RingT))],
+        chis: impl Iterator<Item = RingT>,
+    ) -> (RingT, RingT) {
+        triples.iter().zip(chis).fold(
+            (RingT::ZERO, RingT::ZERO),
+            |(U, V), (((w_alpha, m_alpha), (w_beta, m_beta), (_, m_gamma)), chi)| {
+                let a0 = *m_alpha * *m_beta;
+                let a1 = (*w_beta * *m_alpha) + (*w_alpha * *m_beta) - *m_gamma;
+                (U + chi * a0, V + chi * a1)
+            },
+        )
+    }
+
+    /// Prove that the triples are multiplication triples.  With `multi_thread`, the triples are
+    /// processed in chunks of `chunk_size` in parallel, which gives the same result.
     pub fn check_multiply<C: AbstractChannel>(
         &mut self,
         channel: &mut C,
@@ -256,82 +341,259 @@ where
         multi_thread: bool,
         chunk_size: usize,
     ) -> Result<(), Error> {
-        let mut U = RingT::default();
-        let mut V = RingT::default();
+        let seed: Block = channel.receive()?;
 
-        let seed: Block = channel.receive().unwrap();
-        let mut seeded_rng = AesRng::from_seed(seed);
+        let t_start = Instant::now();
+        let (mut U, mut V) = if multi_thread {
+            triples
+                .par_chunks(chunk_size)
+                .enumerate()
+                .map(|(idx, chunk)| Self::combine_triples(chunk, chis(seed, idx * chunk_size)))
+                .reduce(
+                    || (RingT::ZERO, RingT::ZERO),
+                    |(U, V), (tmp_U, tmp_V)| (U + tmp_U, V + tmp_V),
+                )
+        } else {
+            Self::combine_triples(triples, chis(seed, 0))
+        };
+        self.stats.linear_comb_time = t_start.elapsed();
 
-        let check_time = Instant::now();
+        let (A1, A0) = self.random(channel)?;
 
-        if multi_thread {
-            let t_start = Instant::now();
+        U += A0;
+        V += A1;
 
-            let (U_out, V_out) = triples
-                .par_chunks_exact_mut(chunk_size)
-                .map(|x| {
-                    let mut rng = AesRng::from_seed(Block::default());
-                    let (wl_U, wl_V): (RingT, RingT) = x.into_iter().fold(
-                        (RingT::default(), RingT::default()),
-                        |(tmp_U, tmp_V), (alpha, beta, gamma)| {
-                            let chi = rng.gen::<RingT>();
-                            let u = (alpha.1 * beta.1) * chi;
-                            let v = ((beta.0 * alpha.1) + (alpha.0 * beta.1) - gamma.1) * chi;
-                            (tmp_U + u, tmp_V + v)
-                        },
-                    );
-                    (wl_U, wl_V)
-                })
-                .fold(
-                    || (RingT::default(), RingT::default()),
-                    |(U, V), (tmp_u, tmp_v)| (U + tmp_u, V + tmp_v),
-                )
-                .reduce(
-                    || (RingT::default(), RingT::default()),
-                    |(tmp_U, tmp_V), (alpha, beta)| {
-                        let u = alpha;
-                        let v = beta;
-                        (tmp_U + u, tmp_V + v)
-                    },
-                );
-
-            println!("Time elapsed mul: {}", t_start.elapsed().as_millis());
-            U = U_out;
-            V = V_out;
-        } else {
-            let t_start = Instant::now();
+        channel.send(&U)?;
+        channel.send(&V)?;
+
+        Ok(())
+    }
 
-            for cur in triples {
-                let chi: RingT = seeded_rng.gen::<RingT>();
+    // mask the combined check values with a random VOLE and send them
+    fn send_masked_check<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        (U, V): (RingT, RingT),
+    ) -> Result<(), Error> {
+        let (A1, A0) = self.random(channel)?;
+        channel.send(&(U + A0))?;
+        channel.send(&(V + A1))?;
+        channel.flush()?;
+        Ok(())
+    }
 
-                // 0 is x (w), 1 is z (m)
-                let w_alpha = cur.0 .0;
-                let m_alpha = cur.0 .1;
+    /// Commit to the inner products of the vectors in `a` and `b`, which costs one VOLE per
+    /// product.
+    pub fn inner_product_batch<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        a: &[(&[RingT], &[RingT])],
+        b: &[(&[RingT], &[RingT])],
+    ) -> Result<(Vec<RingT>, Vec<RingT>), Error> {
+        assert_eq!(a.len(), b.len());
+        let products = a
+            .iter()
+            .zip(b.iter())
+            .map(|((xs, _), (ys, _))| {
+                assert_eq!(xs.len(), ys.len());
+                xs.iter().zip(ys.iter()).map(|(&x, &y)| x * y).sum()
+            })
+            .collect();
+        let out = self.input_batch(channel, products)?;
+        channel.flush()?;
+        Ok(out)
+    }
 
-                let w_beta = cur.1 .0;
-                let m_beta = cur.1 .1;
+    /// Prove that `c[j]` is the inner product of `a[j]` and `b[j]` for all `j`.  Only the two
+    /// masked check values are sent, independent of the lengths of the vectors.
+    pub fn check_inner_product_batch<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        a: &[(&[RingT], &[RingT])],
+        b: &[(&[RingT], &[RingT])],
+        (_, c_macs): (&[RingT], &[RingT]),
+    ) -> Result<(), Error> {
+        let n = a.len();
+        assert_eq!(n, b.len());
+        assert_eq!(n, c_macs.len());
+        let seed: Block = channel.receive()?;
+
+        let chis: Vec<RingT> = chis(seed, 0).take(n).collect();
 
-                // let w_gamma = cur.2.0;
-                let m_gamma = cur.2 .1;
+        let t_start = Instant::now();
+        let (U, V) = a
+            .par_iter()
+            .zip(b.par_iter())
+            .zip(c_macs.par_iter())
+            .zip(chis.par_iter())
+            .map(|((((xs, x_macs), (ys, y_macs)), &m_c), &chi)| {
+                let (a0, a1) = (0..xs.len()).fold((RingT::ZERO, RingT::ZERO), |(a0, a1), i| {
+                    (
+                        a0 + x_macs[i] * y_macs[i],
+                        a1 + ys[i] * x_macs[i] + xs[i] * y_macs[i],
+                    )
+                });
+                (chi * a0, chi * (a1 - m_c))
+            })
+            .reduce(
+                || (RingT::ZERO, RingT::ZERO),
+                |(U, V), (tmp_U, tmp_V)| (U + tmp_U, V + tmp_V),
+            );
+        self.stats.linear_comb_time = t_start.elapsed();
 
-                let a0i = m_alpha * m_beta;
-                let a1i = (w_beta * m_alpha) + (w_alpha * m_beta) - m_gamma;
+        self.send_masked_check(channel, (U, V))
+    }
 
-                U += chi * a0i;
-                V += chi * a1i;
+    /// Commit to the product of the `rows x inner` matrix `a` and the `inner x cols` matrix `b`
+    /// (both in row-major order), which costs one VOLE per entry of the product.
+    pub fn matrix_multiply<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        (rows, inner, cols): (usize, usize, usize),
+        (a, _): (&[RingT], &[RingT]),
+        (b, _): (&[RingT], &[RingT]),
+    ) -> Result<(Vec<RingT>, Vec<RingT>), Error> {
+        assert_eq!(a.len(), rows * inner);
+        assert_eq!(b.len(), inner * cols);
+        let mut c = vec![RingT::ZERO; rows * cols];
+        c.par_chunks_mut(cols).enumerate().for_each(|(r, row)| {
+            for i in 0..inner {
+                let x = a[r * inner + i];
+                for (z, &y) in row.iter_mut().zip(b[i * cols..(i + 1) * cols].iter()) {
+                    *z += x * y;
+                }
             }
-            println!("Time elapsed mul: {}", t_start.elapsed().as_millis());
-        }
-        self.stats.linear_comb_time = check_time.elapsed();
+        });
+        let out = self.input_batch(channel, c)?;
+        channel.flush()?;
+        Ok(out)
+    }
 
-        let (A1, A0) = self.random(channel)?;
+    /// Prove that `c = a * b` for matrices as in `matrix_multiply`.  Only the two masked check
+    /// values are sent, independent of the dimensions.
+    pub fn check_matrix_multiply<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        (rows, inner, cols): (usize, usize, usize),
+        (a, a_macs): (&[RingT], &[RingT]),
+        (b, b_macs): (&[RingT], &[RingT]),
+        (_, c_macs): (&[RingT], &[RingT]),
+    ) -> Result<(), Error> {
+        assert_eq!(a_macs.len(), rows * inner);
+        assert_eq!(b_macs.len(), inner * cols);
+        assert_eq!(c_macs.len(), rows * cols);
+        let seed: Block = channel.receive()?;
 
-        U += A0;
-        V += A1;
+        // entry (r, j) of the product gets the challenge with index r * cols + j, and
+        // sum_j chi_rj a_ri b_ij is computed as a_ri * (sum_j chi_rj b_ij)
+        let t_start = Instant::now();
+        let (U, V) = (0..rows)
+            .into_par_iter()
+            .map(|r| {
+                let row_chis: Vec<RingT> = chis(seed, r * cols).take(cols).collect();
+                let (mut a0, mut a1) = (RingT::ZERO, RingT::ZERO);
+                for i in 0..inner {
+                    let (mut s, mut t) = (RingT::ZERO, RingT::ZERO);
+                    for j in 0..cols {
+                        s += row_chis[j] * b_macs[i * cols + j];
+                        t += row_chis[j] * b[i * cols + j];
+                    }
+                    a0 += a_macs[r * inner + i] * s;
+                    a1 += a[r * inner + i] * s + a_macs[r * inner + i] * t;
+                }
+                for j in 0..cols {
+                    a1 -= row_chis[j] * c_macs[r * cols + j];
+                }
+                (a0, a1)
+            })
+            .reduce(
+                || (RingT::ZERO, RingT::ZERO),
+                |(U, V), (tmp_U, tmp_V)| (U + tmp_U, V + tmp_V),
+            );
+        self.stats.linear_comb_time = t_start.elapsed();
 
-        channel.send(&U)?;
-        channel.send(&V)?;
+        self.send_masked_check(channel, (U, V))
+    }
 
-        Ok(())
+    /// Prove that the circuit is satisfied, i.e., that all its `AssertZero` gates hold, when its
+    /// input gates take the values of `witness`.
+    pub fn prove_circuit<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        circuit: &Circuit<RingT>,
+        witness: &[RingT],
+    ) -> Result<CircuitStats, Error> {
+        let counts = circuit.gate_counts();
+        assert_eq!(witness.len(), counts.inputs);
+        let mut stats = CircuitStats {
+            gates: counts,
+            ..Default::default()
+        };
+
+        // inputs and products are authenticated with one VOLE each
+        let t_start = Instant::now();
+        let (masks, mask_macs) = self.random_batch(channel, counts.inputs + counts.muls)?;
+        stats.vole_time = t_start.elapsed();
+
+        let t_start = Instant::now();
+        let mut wires: Vec<(RingT, RingT)> = Vec::with_capacity(circuit.num_wires());
+        let mut diffs: Vec<RingT> = Vec::with_capacity(masks.len());
+        let mut triples = Vec::with_capacity(counts.muls);
+        let mut zeros: Vec<RingT> = Vec::with_capacity(counts.assert_zeros);
+        let mut zero_macs: Vec<RingT> = Vec::with_capacity(counts.assert_zeros);
+        let mut witness = witness.iter();
+        for gate in circuit.gates() {
+            let out = match *gate {
+                Gate::Input => {
+                    let x = *witness.next().unwrap();
+                    let i = diffs.len();
+                    diffs.push(x - masks[i]);
+                    (x, mask_macs[i])
+                }
+                Gate::Constant(c) => (c, RingT::ZERO),
+                Gate::Add(a, b) => {
+                    let ((x, x_mac), (y, y_mac)) = (wires[a.0], wires[b.0]);
+                    (x + y, x_mac + y_mac)
+                }
+                Gate::Sub(a, b) => {
+                    let ((x, x_mac), (y, y_mac)) = (wires[a.0], wires[b.0]);
+                    (x - y, x_mac - y_mac)
+                }
+                Gate::CMul(a, c) => {
+                    let (x, x_mac) = wires[a.0];
+                    (c * x, c * x_mac)
+                }
+                Gate::Mul(a, b) => {
+                    let (alpha, beta) = (wires[a.0], wires[b.0]);
+                    let z = alpha.0 * beta.0;
+                    let i = diffs.len();
+                    diffs.push(z - masks[i]);
+                    let gamma = (z, mask_macs[i]);
+                    triples.push((alpha, beta, gamma));
+                    gamma
+                }
+                Gate::AssertZero(a) => {
+                    zeros.push(wires[a.0].0);
+                    zero_macs.push(wires[a.0].1);
+                    continue;
+                }
+            };
+            wires.push(out);
+        }
+        channel.send(diffs.as_slice())?;
+        channel.flush()?;
+        stats.evaluation_time = t_start.elapsed();
+
+        if !triples.is_empty() {
+            let t_start = Instant::now();
+            self.check_multiply(channel, &mut triples, true, CHECK_CHUNK_SIZE)?;
+            stats.check_multiply_time = t_start.elapsed();
+        }
+        if !zeros.is_empty() {
+            let t_start = Instant::now();
+            self.check_zero_batch(channel, (&zeros, &zero_macs))?;
+            stats.check_zero_time = t_start.elapsed();
+        }
+        Ok(stats)
     }
 }
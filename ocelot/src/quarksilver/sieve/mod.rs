@@ -0,0 +1,610 @@
+//! Import of statements given in the text format of the SIEVE IR 1.0 (relation, instance and
+//! short witness files) as QuarkSilver circuits.
+//!
+//! The relation is flattened: functions and `@for` loops are inlined, and the instance values
+//! become constant gates.  Over Z_2^k (characteristic 2^k) the circuit is evaluated in a larger
+//! ring Z_2^l, and `@assert_zero` holds if a wire is zero modulo 2^k.
+use crate::quarksilver::circuit::{Circuit, Wire};
+use crate::Error;
+use parser::{Directive, Expr, Function, Parser, WireRange};
+use rand::distributions::{Distribution, Standard};
+use scuttlebutt::ring::Ring;
+use std::collections::HashMap;
+
+mod parser;
+
+// 2^128 does not fit into a u128
+const TWO_TO_128: &str = "340282366920938463463374607431768211456";
+
+// bound on the nesting of function calls, to fail on recursive functions
+const MAX_CALL_DEPTH: usize = 256;
+
+/// Characteristic of the field or ring over which a statement is defined.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Characteristic {
+    /// The ring Z_2^k.
+    PowerOfTwo(usize),
+    /// A prime field (the primality is not checked).
+    Prime(u128),
+}
+
+impl Characteristic {
+    fn parse(word: &str) -> Result<Self, Error> {
+        if word == TWO_TO_128 {
+            return Ok(Characteristic::PowerOfTwo(128));
+        }
+        match parser::parse_number(word) {
+            Some(n) if n > 1 && n.is_power_of_two() => {
+                Ok(Characteristic::PowerOfTwo(n.trailing_zeros() as usize))
+            }
+            Some(n) if n > 2 => Ok(Characteristic::Prime(n)),
+            _ => Err(Error::Other(format!("invalid characteristic {}", word))),
+        }
+    }
+
+    /// Size of the values in bits.
+    pub fn bit_length(&self) -> usize {
+        match *self {
+            Characteristic::PowerOfTwo(k) => k,
+            Characteristic::Prime(p) => 128 - p.leading_zeros() as usize,
+        }
+    }
+
+    fn contains(&self, value: u128) -> bool {
+        match *self {
+            Characteristic::PowerOfTwo(k) => k >= 128 || value >> k == 0,
+            Characteristic::Prime(p) => value < p,
+        }
+    }
+}
+
+/// The public part of a statement.
+#[derive(Clone, Debug)]
+pub struct Relation {
+    characteristic: Characteristic,
+    functions: HashMap<String, Function>,
+    body: Vec<Directive>,
+}
+
+impl Relation {
+    pub fn characteristic(&self) -> Characteristic {
+        self.characteristic
+    }
+}
+
+/// Values of an instance or a short witness file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Values {
+    pub characteristic: Characteristic,
+    pub values: Vec<u128>,
+}
+
+pub fn parse_relation(input: &str) -> Result<Relation, Error> {
+    let mut parser = Parser::new(input)?;
+    let header = parser.header("relation")?;
+    parser.gate_set_and_features()?;
+    let mut functions = HashMap::new();
+    let body = parser.relation_body(&mut functions)?;
+    Ok(Relation {
+        characteristic: Characteristic::parse(&header.characteristic)?,
+        functions,
+        body,
+    })
+}
+
+fn parse_values(input: &str, kind: &str) -> Result<Values, Error> {
+    let mut parser = Parser::new(input)?;
+    let header = parser.header(kind)?;
+    let characteristic = Characteristic::parse(&header.characteristic)?;
+    let values = parser.values()?;
+    if let Some(value) = values.iter().find(|&&v| !characteristic.contains(v)) {
+        return Err(Error::Other(format!(
+            "{} value {} is out of range",
+            kind, value
+        )));
+    }
+    Ok(Values {
+        characteristic,
+        values,
+    })
+}
+
+pub fn parse_instance(input: &str) -> Result<Values, Error> {
+    parse_values(input, "instance")
+}
+
+pub fn parse_witness(input: &str) -> Result<Values, Error> {
+    parse_values(input, "short_witness")
+}
+
+/// Convert a value of a statement into a ring element.
+pub fn to_ring<RingT: Ring>(value: u128) -> RingT
+where
+    Standard: Distribution<RingT>,
+{
+    let high = RingT::ZERO + (value >> 64) as u64;
+    high * (1u64 << 32) * (1u64 << 32) + value as u64
+}
+
+struct Builder<'r, RingT> {
+    relation: &'r Relation,
+    instance: std::slice::Iter<'r, u128>,
+    circuit: Circuit<RingT>,
+    depth: usize,
+}
+
+// wires of the current function (or of the main body) and values of the loop variables
+struct Scope {
+    wires: HashMap<u64, Wire>,
+    vars: Vec<(String, u64)>,
+}
+
+fn error<T>(message: String) -> Result<T, Error> {
+    Err(Error::Other(message))
+}
+
+impl Scope {
+    fn new() -> Self {
+        Self {
+            wires: HashMap::new(),
+            vars: Vec::new(),
+        }
+    }
+
+    fn eval(&self, expr: &Expr) -> Result<u64, Error> {
+        let binary = |a: &Expr, b: &Expr, op: fn(u64, u64) -> Option<u64>| {
+            op(self.eval(a)?, self.eval(b)?)
+                .ok_or_else(|| Error::Other("overflow in wire expression".to_string()))
+        };
+        match expr {
+            Expr::Number(n) => Ok(*n),
+            Expr::Var(var) => match self.vars.iter().rev().find(|(name, _)| name == var) {
+                Some((_, value)) => Ok(*value),
+                None => error(format!("unknown variable {}", var)),
+            },
+            Expr::Add(a, b) => binary(a, b, u64::checked_add),
+            Expr::Sub(a, b) => binary(a, b, u64::checked_sub),
+            Expr::Mul(a, b) => binary(a, b, u64::checked_mul),
+        }
+    }
+
+    fn get(&self, expr: &Expr) -> Result<Wire, Error> {
+        let id = self.eval(expr)?;
+        match self.wires.get(&id) {
+            Some(wire) => Ok(*wire),
+            None => error(format!("wire ${} is not defined", id)),
+        }
+    }
+
+    fn set_id(&mut self, id: u64, wire: Wire) -> Result<(), Error> {
+        if self.wires.insert(id, wire).is_some() {
+            return error(format!("wire ${} is assigned twice", id));
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, expr: &Expr, wire: Wire) -> Result<(), Error> {
+        let id = self.eval(expr)?;
+        self.set_id(id, wire)
+    }
+
+    // the first and the last wire of a range
+    fn bounds(&self, range: &WireRange) -> Result<(u64, u64), Error> {
+        let first = self.eval(&range.first)?;
+        let last = match &range.last {
+            Some(last) => self.eval(last)?,
+            None => first,
+        };
+        if last < first {
+            return error(format!("invalid wire range ${} ... ${}", first, last));
+        }
+        Ok((first, last))
+    }
+
+    // the number of wires in a list of ranges
+    fn count(&self, ranges: &[WireRange]) -> Result<u64, Error> {
+        let mut count = 0u64;
+        for range in ranges {
+            let (first, last) = self.bounds(range)?;
+            count = count
+                .checked_add(last - first)
+                .and_then(|count| count.checked_add(1))
+                .ok_or_else(|| Error::Other("overflow in wire range".to_string()))?;
+        }
+        Ok(count)
+    }
+
+    // the wire numbers of a list of ranges, which may contain at most `limit` wires
+    fn ids(&self, ranges: &[WireRange], limit: usize) -> Result<Vec<u64>, Error> {
+        let count = self.count(ranges)?;
+        if count > limit as u64 {
+            return error(format!(
+                "wire ranges cover {} wires, but only {} are defined",
+                count, limit
+            ));
+        }
+        let mut ids = Vec::with_capacity(count as usize);
+        for range in ranges {
+            let (first, last) = self.bounds(range)?;
+            ids.extend(first..=last);
+        }
+        Ok(ids)
+    }
+}
+
+impl<'r, RingT: Ring> Builder<'r, RingT>
+where
+    Standard: Distribution<RingT>,
+{
+    fn run(&mut self, directives: &[Directive], scope: &mut Scope) -> Result<(), Error> {
+        for directive in directives {
+            match directive {
+                Directive::Add(out, a, b) => {
+                    let wire = self.circuit.add(scope.get(a)?, scope.get(b)?);
+                    scope.set(out, wire)?;
+                }
+                Directive::Mul(out, a, b) => {
+                    let wire = self.circuit.mul(scope.get(a)?, scope.get(b)?);
+                    scope.set(out, wire)?;
+                }
+                Directive::AddC(out, a, c) => {
+                    let c = self.circuit.constant(to_ring(*c));
+                    let wire = self.circuit.add(scope.get(a)?, c);
+                    scope.set(out, wire)?;
+                }
+                Directive::MulC(out, a, c) => {
+                    let wire = self.circuit.cmul(scope.get(a)?, to_ring(*c));
+                    scope.set(out, wire)?;
+                }
+                Directive::Copy(out, a) => {
+                    let wire = scope.get(a)?;
+                    scope.set(out, wire)?;
+                }
+                Directive::Assign(out, c) => {
+                    let wire = self.circuit.constant(to_ring(*c));
+                    scope.set(out, wire)?;
+                }
+                Directive::Instance(out) => {
+                    let value = match self.instance.next() {
+                        Some(value) => *value,
+                        None => return error("not enough instance values".to_string()),
+                    };
+                    let wire = self.circuit.constant(to_ring(value));
+                    scope.set(out, wire)?;
+                }
+                Directive::Witness(out) => {
+                    let wire = self.circuit.input();
+                    scope.set(out, wire)?;
+                }
+                Directive::AssertZero(a) => self.circuit.assert_zero(scope.get(a)?),
+                Directive::Delete(first, last) => {
+                    let first = scope.eval(first)?;
+                    let last = match last {
+                        Some(last) => scope.eval(last)?,
+                        None => first,
+                    };
+                    scope.wires.retain(|id, _| *id < first || *id > last);
+                }
+                Directive::Call {
+                    outputs,
+                    name,
+                    inputs,
+                } => {
+                    let relation = self.relation;
+                    let function = match relation.functions.get(name) {
+                        Some(function) => function,
+                        None => return error(format!("unknown function {}", name)),
+                    };
+                    self.call(
+                        scope,
+                        outputs,
+                        inputs,
+                        Some((function.num_outputs, function.num_inputs)),
+                        &function.body,
+                    )?;
+                }
+                Directive::AnonCall {
+                    outputs,
+                    inputs,
+                    body,
+                } => self.call(scope, outputs, inputs, None, body)?,
+                Directive::For {
+                    outputs,
+                    var,
+                    first,
+                    last,
+                    body,
+                } => {
+                    for i in *first..=*last {
+                        scope.vars.push((var.clone(), i));
+                        let result = self.run(body, scope);
+                        scope.vars.pop();
+                        result?;
+                    }
+                    // the loop has to define all its outputs
+                    for id in scope.ids(outputs, scope.wires.len())? {
+                        scope.get(&Expr::Number(id))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // run the body in a new scope with the outputs on $0, ... followed by the inputs
+    fn call(
+        &mut self,
+        scope: &mut Scope,
+        outputs: &[WireRange],
+        inputs: &[WireRange],
+        signature: Option<(usize, usize)>,
+        body: &[Directive],
+    ) -> Result<(), Error> {
+        // compare with the declared signature before collecting the wires of the ranges
+        let num_outputs = scope.count(outputs)?;
+        let num_inputs = scope.count(inputs)?;
+        if let Some((expected_outputs, expected_inputs)) = signature {
+            if (expected_outputs as u64, expected_inputs as u64) != (num_outputs, num_inputs) {
+                return error(format!(
+                    "function expects {} outputs and {} inputs, but got {} and {}",
+                    expected_outputs, expected_inputs, num_outputs, num_inputs
+                ));
+            }
+        }
+        if num_outputs.checked_add(num_inputs).is_none() {
+            return error("overflow in wire range".to_string());
+        }
+        if self.depth == MAX_CALL_DEPTH {
+            return error("function calls are nested too deeply".to_string());
+        }
+
+        // the inputs have to be defined in the outer scope and the outputs in the inner one
+        let input_ids = scope.ids(inputs, scope.wires.len())?;
+        let mut inner = Scope::new();
+        for (i, id) in input_ids.iter().enumerate() {
+            let wire = scope.get(&Expr::Number(*id))?;
+            inner.set_id(num_outputs + i as u64, wire)?;
+        }
+        self.depth += 1;
+        let result = self.run(body, &mut inner);
+        self.depth -= 1;
+        result?;
+        for (i, id) in scope.ids(outputs, inner.wires.len())?.iter().enumerate() {
+            let wire = inner.get(&Expr::Number(i as u64))?;
+            scope.set_id(*id, wire)?;
+        }
+        Ok(())
+    }
+}
+
+/// Flatten the relation into a circuit.  The input gates of the circuit correspond to the
+/// short witness values in order.
+pub fn build_circuit<RingT: Ring>(
+    relation: &Relation,
+    instance: &Values,
+) -> Result<Circuit<RingT>, Error>
+where
+    Standard: Distribution<RingT>,
+{
+    let characteristic = relation.characteristic;
+    if instance.characteristic != characteristic {
+        return error("instance and relation are over different fields".to_string());
+    }
+    match characteristic {
+        Characteristic::PowerOfTwo(k) if !RingT::IS_FIELD && k <= RingT::BIT_LENGTH => (),
+        // p divides the modulus and both have the same size, hence they are equal
+        Characteristic::Prime(p)
+            if RingT::IS_FIELD
+                && to_ring::<RingT>(p) == RingT::ZERO
+                && characteristic.bit_length() == RingT::BIT_LENGTH => {}
+        _ => {
+            return error(format!(
+                "cannot evaluate a statement with characteristic {:?} in the chosen ring",
+                characteristic
+            ))
+        }
+    }
+    let mut builder = Builder {
+        relation,
+        instance: instance.values.iter(),
+        circuit: Circuit::new(),
+        depth: 0,
+    };
+    builder.run(&relation.body, &mut Scope::new())?;
+    if builder.instance.next().is_some() {
+        return error("not all instance values are used".to_string());
+    }
+    Ok(builder.circuit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_circuit, parse_instance, parse_relation, parse_witness, to_ring, Characteristic,
+    };
+    use scuttlebutt::ring::{z2r, F61p, Ring, R64};
+
+    const RELATION: &str = "version 1.0.0;
+        field characteristic 18446744073709551616 degree 1;
+        relation
+        gate_set: arithmetic;
+        features: @function, @for;
+        @begin
+            // x * y + c == x + z, for c the instance and x, y, z the witness
+            @function(mul_add, @out: 1, @in: 3, @instance: 0, @short_witness: 0)
+            @body
+                $4 <- @mul($1, $2);
+                $0 <- @add($3, $4);
+            @end
+            $0 <- @instance;
+            $1 ... $3 <- @for i @first 1 @last 3
+                $(i) <- @anon_call(@instance: 0, @short_witness: 1)
+                    $0 <- @short_witness;
+                @end
+            @end
+            $4 <- @call(mul_add, $1, $2, $0);
+            $5 <- @add($1, $3);
+            $6 <- @mulc($5, < 0xffffffffffffffff >);
+            $7 <- @add($4, $6);
+            $8 <- $7;
+            @assert_zero($8);
+            @delete($0 ... $8);
+        @end";
+
+    fn instance(c: u64) -> String {
+        format!(
+            "version 1.0.0;
+            field characteristic 18446744073709551616 degree 1;
+            instance
+            @begin
+                < {} >;
+            @end",
+            c
+        )
+    }
+
+    const WITNESS: &str = "version 1.0.0;
+        field characteristic 18446744073709551616 degree 1;
+        short_witness
+        @begin
+            < 3 >;
+            < 5 >;
+            < 0x10 >;
+        @end";
+
+    #[test]
+    fn test_characteristic() {
+        assert_eq!(
+            Characteristic::parse("18446744073709551616").unwrap(),
+            Characteristic::PowerOfTwo(64)
+        );
+        assert_eq!(
+            Characteristic::parse(super::TWO_TO_128).unwrap(),
+            Characteristic::PowerOfTwo(128)
+        );
+        assert_eq!(
+            Characteristic::parse("2305843009213693951").unwrap(),
+            Characteristic::Prime(F61p::MODULUS as u128)
+        );
+        assert_eq!(
+            Characteristic::Prime(F61p::MODULUS as u128).bit_length(),
+            61
+        );
+        assert!(Characteristic::parse("1").is_err());
+    }
+
+    #[test]
+    fn test_to_ring() {
+        let x: z2r::R144 = to_ring(u128::MAX);
+        assert_eq!(
+            x + z2r::R144::ONE,
+            to_ring::<z2r::R144>(1) * (1u64 << 32) * (1u64 << 32) * (1u64 << 32) * (1u64 << 32)
+        );
+        assert_eq!(to_ring::<R64>(u128::MAX), -R64::ONE);
+    }
+
+    #[test]
+    fn test_build_circuit() {
+        let relation = parse_relation(RELATION).unwrap();
+        let witness = parse_witness(WITNESS).unwrap();
+        let witness: Vec<z2r::R144> = witness.values.iter().map(|&v| to_ring(v)).collect();
+        // 3 * 5 + c == 3 + 16
+        let circuit =
+            build_circuit::<z2r::R144>(&relation, &parse_instance(&instance(4)).unwrap()).unwrap();
+        let counts = circuit.gate_counts();
+        assert_eq!(
+            (
+                counts.inputs,
+                counts.muls,
+                counts.adds,
+                counts.cmuls,
+                counts.assert_zeros
+            ),
+            (3, 1, 3, 1, 1)
+        );
+        assert!(circuit.is_satisfied(&witness, 64));
+        let circuit =
+            build_circuit::<z2r::R144>(&relation, &parse_instance(&instance(5)).unwrap()).unwrap();
+        assert!(!circuit.is_satisfied(&witness, 64));
+        // the ring is too small or a field
+        assert!(build_circuit::<F61p>(&relation, &parse_instance(&instance(4)).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_build_circuit_prime() {
+        let relation_and_instance = |p: u128| {
+            (
+                parse_relation(&format!(
+                    "version 1.0.0; field characteristic {} degree 1; relation
+                    gate_set: arithmetic; features: simple;
+                    @begin $0 <- @instance; @assert_zero($0); @end",
+                    p
+                ))
+                .unwrap(),
+                parse_instance(&format!(
+                    "version 1.0.0; field characteristic {} degree 1; instance @begin < 0 >; @end",
+                    p
+                ))
+                .unwrap(),
+            )
+        };
+        let (relation, instance) = relation_and_instance(F61p::MODULUS as u128);
+        assert!(build_circuit::<F61p>(&relation, &instance).is_ok());
+        // a different prime field, or a multiple of the modulus (primality is not checked)
+        for &p in &[
+            2147483647,
+            2305843009213693921,
+            (1 << 89) - 1,
+            2 * F61p::MODULUS as u128,
+        ] {
+            let (relation, instance) = relation_and_instance(p);
+            assert!(build_circuit::<F61p>(&relation, &instance).is_err());
+        }
+    }
+
+    #[test]
+    fn test_build_circuit_errors() {
+        let wrap = |body: &str| {
+            format!(
+                "version 1.0.0; field characteristic 256 degree 1; relation
+                gate_set: arithmetic; features: simple;
+                @begin {} @end",
+                body
+            )
+        };
+        let instance = parse_instance(
+            "version 1.0.0; field characteristic 256 degree 1; instance @begin < 1 >; @end",
+        )
+        .unwrap();
+        for body in [
+            // undefined wire
+            "$0 <- @instance; $1 <- @add($0, $2);",
+            // assigned twice
+            "$0 <- @instance; $0 <- @add($0, $0);",
+            // instance value unused
+            "$0 <- < 1 >;",
+            // wrong number of arguments
+            "@function(f, @out: 1, @in: 1, @instance: 0, @short_witness: 0) @body $0 <- $1; @end
+             $0 <- @instance; $1 <- @call(f, $0, $0);",
+            // recursion
+            "@function(f, @out: 1, @in: 1, @instance: 0, @short_witness: 0) @body $0 <- @call(f, $1); @end
+             $0 <- @instance; $1 <- @call(f, $0);",
+            // wire ranges far larger than the defined wires
+            "$0 <- @instance; $1 <- @anon_call($0 ... $10000000000000, @instance: 0, @short_witness: 0)
+             $0 <- $1; @end",
+            "$0 <- @instance; $1 ... $10000000000000 <- @anon_call($0, @instance: 0, @short_witness: 0)
+             $0 <- < 1 >; @end",
+            "$0 <- @instance; $1 ... $10000000000000 <- @for i @first 1 @last 1 $(i) <- $0; @end",
+        ]
+        .iter()
+        {
+            let relation = parse_relation(&wrap(body)).unwrap();
+            assert!(build_circuit::<R64>(&relation, &instance).is_err());
+        }
+        // a value that is too large
+        assert!(parse_instance(
+            "version 1.0.0; field characteristic 256 degree 1; instance @begin < 256 >; @end",
+        )
+        .is_err());
+    }
+}
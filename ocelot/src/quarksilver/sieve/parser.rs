@@ -0,0 +1,767 @@
+//! Parser for the text format of the SIEVE IR 1.0 (IR0 and the IR1 features `@function` and
+//! `@for`).
+use crate::Error;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    /// Identifiers and numbers (including version numbers such as `1.0.0`).
+    Word(String),
+    /// Directives such as `@add`.
+    Keyword(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 13] = [
+    "<-", "...", "$", "(", ")", "<", ">", ";", ",", ":", "+", "-", "*",
+];
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.'
+}
+
+// split the input into tokens together with their line numbers
+fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, Error> {
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut rest = input;
+    while let Some(c) = rest.chars().next() {
+        if c == '\n' {
+            line += 1;
+            rest = &rest[1..];
+        } else if c.is_whitespace() {
+            rest = &rest[c.len_utf8()..];
+        } else if rest.starts_with("//") {
+            rest = rest.find('\n').map_or("", |i| &rest[i..]);
+        } else if rest.starts_with("/*") {
+            let end = rest
+                .find("*/")
+                .ok_or_else(|| Error::Other(format!("line {}: unterminated comment", line)))?;
+            line += rest[..end].matches('\n').count();
+            rest = &rest[end + 2..];
+        } else if (c == '@' || is_word_char(c)) && !rest.starts_with("...") {
+            // words end before a "..." so that ranges like $0...$3 work
+            let mut end = 1;
+            for (i, c) in rest.char_indices().skip(1) {
+                if !is_word_char(c) || rest[i..].starts_with("...") {
+                    break;
+                }
+                end = i + c.len_utf8();
+            }
+            let word = rest[..end].to_string();
+            if word == "@" {
+                return Err(Error::Other(format!(
+                    "line {}: missing directive name",
+                    line
+                )));
+            }
+            tokens.push((
+                if c == '@' {
+                    Token::Keyword(word)
+                } else {
+                    Token::Word(word)
+                },
+                line,
+            ));
+            rest = &rest[end..];
+        } else if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) {
+            tokens.push((Token::Symbol(symbol), line));
+            rest = &rest[symbol.len()..];
+        } else {
+            return Err(Error::Other(format!(
+                "line {}: unexpected character '{}'",
+                line, c
+            )));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parse a decimal or hexadecimal (`0x`) number.
+pub(super) fn parse_number(word: &str) -> Option<u128> {
+    if let Some(hex) = word.strip_prefix("0x") {
+        u128::from_str_radix(hex, 16).ok()
+    } else {
+        word.parse().ok()
+    }
+}
+
+/// Expression for a wire number, which may depend on loop variables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum Expr {
+    Number(u64),
+    Var(String),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+}
+
+/// A single wire (`last` is `None`) or a range of wires.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct WireRange {
+    pub first: Expr,
+    pub last: Option<Expr>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum Directive {
+    Add(Expr, Expr, Expr),
+    Mul(Expr, Expr, Expr),
+    AddC(Expr, Expr, u128),
+    MulC(Expr, Expr, u128),
+    Copy(Expr, Expr),
+    Assign(Expr, u128),
+    Instance(Expr),
+    Witness(Expr),
+    AssertZero(Expr),
+    Delete(Expr, Option<Expr>),
+    Call {
+        outputs: Vec<WireRange>,
+        name: String,
+        inputs: Vec<WireRange>,
+    },
+    AnonCall {
+        outputs: Vec<WireRange>,
+        inputs: Vec<WireRange>,
+        body: Vec<Directive>,
+    },
+    For {
+        outputs: Vec<WireRange>,
+        var: String,
+        first: u64,
+        last: u64,
+        body: Vec<Directive>,
+    },
+}
+
+/// A function with `num_outputs` outputs on the wires `$0, ...` followed by `num_inputs` inputs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Function {
+    pub num_outputs: usize,
+    pub num_inputs: usize,
+    pub body: Vec<Directive>,
+}
+
+/// The header common to all three kinds of files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct Header {
+    pub version: String,
+    pub characteristic: String,
+}
+
+// bound on the nesting of blocks and parenthesized expressions
+const MAX_NESTING_DEPTH: usize = 64;
+
+pub(super) struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    pub fn new(input: &str) -> Result<Self, Error> {
+        Ok(Self {
+            tokens: tokenize(input)?,
+            pos: 0,
+            depth: 0,
+        })
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, Error> {
+        let line = self
+            .tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map_or(0, |(_, line)| *line);
+        Err(Error::Other(format!("line {}: {}", line, message)))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Result<Token, Error> {
+        match self.tokens.get(self.pos) {
+            Some((token, _)) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+            None => self.error("unexpected end of file"),
+        }
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Keyword(k)) if k == keyword)
+    }
+
+    fn symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        if self.is_symbol(symbol) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", symbol))
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        if self.is_keyword(keyword) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", keyword))
+        }
+    }
+
+    fn word(&mut self) -> Result<String, Error> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => self.error("expected a name or a number"),
+        }
+    }
+
+    fn expect_word(&mut self, expected: &str) -> Result<(), Error> {
+        match self.peek() {
+            Some(Token::Word(word)) if word == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => self.error(&format!("expected '{}'", expected)),
+        }
+    }
+
+    fn number(&mut self) -> Result<u64, Error> {
+        let word = self.word()?;
+        match parse_number(&word) {
+            Some(n) if n <= u64::MAX as u128 => Ok(n as u64),
+            _ => self.error(&format!("invalid number '{}'", word)),
+        }
+    }
+
+    // `< value >`
+    fn value(&mut self) -> Result<u128, Error> {
+        self.symbol("<")?;
+        let word = self.word()?;
+        let value = match parse_number(&word) {
+            Some(value) => value,
+            None => return self.error(&format!("invalid value '{}'", word)),
+        };
+        self.symbol(">")?;
+        Ok(value)
+    }
+
+    // `@name: number`
+    fn count(&mut self, name: &str) -> Result<usize, Error> {
+        self.keyword(name)?;
+        self.symbol(":")?;
+        Ok(self.number()? as usize)
+    }
+
+    /// Parse `version ...; field characteristic ... degree 1; <kind>`.
+    pub fn header(&mut self, kind: &str) -> Result<Header, Error> {
+        self.expect_word("version")?;
+        let version = self.word()?;
+        if !version.starts_with("1.") {
+            return self.error(&format!("unsupported version {}", version));
+        }
+        self.symbol(";")?;
+        self.expect_word("field")?;
+        self.expect_word("characteristic")?;
+        let characteristic = self.word()?;
+        self.expect_word("degree")?;
+        if self.number()? != 1 {
+            return self.error("only fields of degree 1 are supported");
+        }
+        self.symbol(";")?;
+        self.expect_word(kind)?;
+        Ok(Header {
+            version,
+            characteristic,
+        })
+    }
+
+    // `name: item, item, ...;`, returns the items
+    fn list(&mut self, name: &str) -> Result<Vec<String>, Error> {
+        self.expect_word(name)?;
+        self.symbol(":")?;
+        let mut items = Vec::new();
+        loop {
+            match self.next()? {
+                Token::Word(item) | Token::Keyword(item) => items.push(item),
+                _ => return self.error(&format!("invalid {}", name)),
+            }
+            if self.is_symbol(";") {
+                self.pos += 1;
+                return Ok(items);
+            }
+            self.symbol(",")?;
+        }
+    }
+
+    /// Parse the `gate_set` and `features` of a relation.
+    pub fn gate_set_and_features(&mut self) -> Result<(), Error> {
+        for gate in self.list("gate_set")? {
+            match gate.as_str() {
+                "arithmetic" | "@add" | "@addc" | "@mul" | "@mulc" => (),
+                _ => return self.error(&format!("unsupported gate {}", gate)),
+            }
+        }
+        for feature in self.list("features")? {
+            match feature.as_str() {
+                "simple" | "@function" | "@for" => (),
+                _ => return self.error(&format!("unsupported feature {}", feature)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse `@begin < value >; ... @end`.
+    pub fn values(&mut self) -> Result<Vec<u128>, Error> {
+        self.keyword("@begin")?;
+        let mut values = Vec::new();
+        while !self.is_keyword("@end") {
+            values.push(self.value()?);
+            self.symbol(";")?;
+        }
+        self.keyword("@end")?;
+        self.end_of_file()?;
+        Ok(values)
+    }
+
+    /// Parse `@begin ... @end` of a relation.
+    pub fn relation_body(
+        &mut self,
+        functions: &mut HashMap<String, Function>,
+    ) -> Result<Vec<Directive>, Error> {
+        self.keyword("@begin")?;
+        let body = self.directives(functions)?;
+        self.keyword("@end")?;
+        self.end_of_file()?;
+        Ok(body)
+    }
+
+    fn end_of_file(&self) -> Result<(), Error> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => self.error("unexpected input after @end"),
+        }
+    }
+
+    // parse a nested block or expression, to fail on deeply nested input instead of overflowing
+    // the stack
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        if self.depth == MAX_NESTING_DEPTH {
+            return self.error("blocks or expressions are nested too deeply");
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // directives until the next `@end`
+    fn directives(
+        &mut self,
+        functions: &mut HashMap<String, Function>,
+    ) -> Result<Vec<Directive>, Error> {
+        self.nested(|parser| {
+            let mut directives = Vec::new();
+            while !parser.is_keyword("@end") {
+                if parser.is_keyword("@function") {
+                    parser.function(functions)?;
+                } else {
+                    directives.push(parser.directive(functions)?);
+                }
+            }
+            Ok(directives)
+        })
+    }
+
+    fn function(&mut self, functions: &mut HashMap<String, Function>) -> Result<(), Error> {
+        self.keyword("@function")?;
+        self.symbol("(")?;
+        let name = self.word()?;
+        self.symbol(",")?;
+        let num_outputs = self.count("@out")?;
+        self.symbol(",")?;
+        let num_inputs = self.count("@in")?;
+        self.symbol(",")?;
+        self.count("@instance")?;
+        self.symbol(",")?;
+        self.count("@short_witness")?;
+        self.symbol(")")?;
+        self.keyword("@body")?;
+        let body = self.directives(functions)?;
+        self.keyword("@end")?;
+        let function = Function {
+            num_outputs,
+            num_inputs,
+            body,
+        };
+        if functions.insert(name.clone(), function).is_some() {
+            return self.error(&format!("function {} is defined twice", name));
+        }
+        Ok(())
+    }
+
+    // factor := number | variable | ( expr )
+    fn factor(&mut self) -> Result<Expr, Error> {
+        if self.is_symbol("(") {
+            self.pos += 1;
+            let expr = self.nested(Self::expr)?;
+            self.symbol(")")?;
+            return Ok(expr);
+        }
+        let word = self.word()?;
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            match parse_number(&word) {
+                Some(n) if n <= u64::MAX as u128 => Ok(Expr::Number(n as u64)),
+                _ => self.error(&format!("invalid number '{}'", word)),
+            }
+        } else {
+            Ok(Expr::Var(word))
+        }
+    }
+
+    // expr := term (('+' | '-') term)*, term := factor ('*' factor)*
+    fn expr(&mut self) -> Result<Expr, Error> {
+        let mut term = self.term()?;
+        loop {
+            if self.is_symbol("+") {
+                self.pos += 1;
+                term = Expr::Add(Box::new(term), Box::new(self.term()?));
+            } else if self.is_symbol("-") {
+                self.pos += 1;
+                term = Expr::Sub(Box::new(term), Box::new(self.term()?));
+            } else {
+                return Ok(term);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, Error> {
+        let mut factor = self.factor()?;
+        while self.is_symbol("*") {
+            self.pos += 1;
+            factor = Expr::Mul(Box::new(factor), Box::new(self.factor()?));
+        }
+        Ok(factor)
+    }
+
+    // `$n` or `$(expr)`
+    fn wire(&mut self) -> Result<Expr, Error> {
+        self.symbol("$")?;
+        if self.is_symbol("(") {
+            self.factor()
+        } else {
+            Ok(Expr::Number(self.number()?))
+        }
+    }
+
+    fn wire_range(&mut self) -> Result<WireRange, Error> {
+        let first = self.wire()?;
+        let last = if self.is_symbol("...") {
+            self.pos += 1;
+            Some(self.wire()?)
+        } else {
+            None
+        };
+        Ok(WireRange { first, last })
+    }
+
+    // comma separated wire ranges, possibly empty
+    fn wire_ranges(&mut self) -> Result<Vec<WireRange>, Error> {
+        let mut ranges = Vec::new();
+        while self.is_symbol("$") {
+            ranges.push(self.wire_range()?);
+            // the comma may also separate the ranges from what follows them, as in `@anon_call`
+            let next = self.tokens.get(self.pos + 1).map(|(token, _)| token);
+            if !self.is_symbol(",") || next != Some(&Token::Symbol("$")) {
+                break;
+            }
+            self.pos += 1;
+        }
+        Ok(ranges)
+    }
+
+    fn single_output(&self, outputs: Vec<WireRange>) -> Result<Expr, Error> {
+        match outputs.as_slice() {
+            [WireRange { first, last: None }] => Ok(first.clone()),
+            _ => self.error("expected a single output wire"),
+        }
+    }
+
+    // `(a, b)` for binary gates
+    fn two_wires(&mut self) -> Result<(Expr, Expr), Error> {
+        self.symbol("(")?;
+        let a = self.wire()?;
+        self.symbol(",")?;
+        let b = self.wire()?;
+        self.symbol(")")?;
+        Ok((a, b))
+    }
+
+    // `(a, < c >)` for gates with a constant
+    fn wire_and_value(&mut self) -> Result<(Expr, u128), Error> {
+        self.symbol("(")?;
+        let a = self.wire()?;
+        self.symbol(",")?;
+        let c = self.value()?;
+        self.symbol(")")?;
+        Ok((a, c))
+    }
+
+    fn directive(&mut self, functions: &mut HashMap<String, Function>) -> Result<Directive, Error> {
+        if self.is_keyword("@assert_zero") {
+            self.pos += 1;
+            self.symbol("(")?;
+            let a = self.wire()?;
+            self.symbol(")")?;
+            self.symbol(";")?;
+            return Ok(Directive::AssertZero(a));
+        }
+        if self.is_keyword("@delete") {
+            self.pos += 1;
+            self.symbol("(")?;
+            let range = self.wire_range()?;
+            self.symbol(")")?;
+            self.symbol(";")?;
+            return Ok(Directive::Delete(range.first, range.last));
+        }
+        if !self.is_symbol("$") {
+            return match self.peek() {
+                Some(Token::Keyword(keyword)) => {
+                    self.error(&format!("unsupported directive {}", keyword))
+                }
+                _ => self.error("expected a directive"),
+            };
+        }
+
+        let outputs = self.wire_ranges()?;
+        self.symbol("<-")?;
+        if self.is_symbol("<") {
+            let c = self.value()?;
+            self.symbol(";")?;
+            return Ok(Directive::Assign(self.single_output(outputs)?, c));
+        }
+        if self.is_symbol("$") {
+            let a = self.wire()?;
+            self.symbol(";")?;
+            return Ok(Directive::Copy(self.single_output(outputs)?, a));
+        }
+        let keyword = match self.next()? {
+            Token::Keyword(keyword) => keyword,
+            _ => return self.error("expected a gate"),
+        };
+        let directive = match keyword.as_str() {
+            "@add" => {
+                let (a, b) = self.two_wires()?;
+                Directive::Add(self.single_output(outputs)?, a, b)
+            }
+            "@mul" => {
+                let (a, b) = self.two_wires()?;
+                Directive::Mul(self.single_output(outputs)?, a, b)
+            }
+            "@addc" => {
+                let (a, c) = self.wire_and_value()?;
+                Directive::AddC(self.single_output(outputs)?, a, c)
+            }
+            "@mulc" => {
+                let (a, c) = self.wire_and_value()?;
+                Directive::MulC(self.single_output(outputs)?, a, c)
+            }
+            "@instance" => Directive::Instance(self.single_output(outputs)?),
+            "@short_witness" | "@witness" => Directive::Witness(self.single_output(outputs)?),
+            "@call" => {
+                self.symbol("(")?;
+                let name = self.word()?;
+                let inputs = if self.is_symbol(",") {
+                    self.pos += 1;
+                    self.wire_ranges()?
+                } else {
+                    Vec::new()
+                };
+                self.symbol(")")?;
+                Directive::Call {
+                    outputs,
+                    name,
+                    inputs,
+                }
+            }
+            "@anon_call" => {
+                self.symbol("(")?;
+                let inputs = self.wire_ranges()?;
+                if !inputs.is_empty() {
+                    self.symbol(",")?;
+                }
+                self.count("@instance")?;
+                self.symbol(",")?;
+                self.count("@short_witness")?;
+                self.symbol(")")?;
+                let body = self.directives(functions)?;
+                self.keyword("@end")?;
+                Directive::AnonCall {
+                    outputs,
+                    inputs,
+                    body,
+                }
+            }
+            "@for" => {
+                let var = self.word()?;
+                self.keyword("@first")?;
+                let first = self.number()?;
+                self.keyword("@last")?;
+                let last = self.number()?;
+                let body = self.directives(functions)?;
+                self.keyword("@end")?;
+                Directive::For {
+                    outputs,
+                    var,
+                    first,
+                    last,
+                    body,
+                }
+            }
+            _ => return self.error(&format!("unsupported gate {}", keyword)),
+        };
+        // blocks ending with @end may omit the semicolon
+        match directive {
+            Directive::AnonCall { .. } | Directive::For { .. } => {
+                if self.is_symbol(";") {
+                    self.pos += 1;
+                }
+            }
+            _ => self.symbol(";")?,
+        }
+        Ok(directive)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, Directive, Expr, Parser, Token, WireRange};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<Token> =
+            tokenize("$0...$3 <- @add($1, < 0x1f >); // comment\n/* x */ 1.0.0")
+                .unwrap()
+                .into_iter()
+                .map(|(token, _)| token)
+                .collect();
+        let word = |w: &str| Token::Word(w.to_string());
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Symbol("$"),
+                word("0"),
+                Token::Symbol("..."),
+                Token::Symbol("$"),
+                word("3"),
+                Token::Symbol("<-"),
+                Token::Keyword("@add".to_string()),
+                Token::Symbol("("),
+                Token::Symbol("$"),
+                word("1"),
+                Token::Symbol(","),
+                Token::Symbol("<"),
+                word("0x1f"),
+                Token::Symbol(">"),
+                Token::Symbol(")"),
+                Token::Symbol(";"),
+                word("1.0.0"),
+            ]
+        );
+        assert!(tokenize("$0 <- #").is_err());
+    }
+
+    #[test]
+    fn test_parse_directives() {
+        let mut parser = Parser::new(
+            "@begin
+                $2 <- @mulc($(i + 1), < 3 >);
+                $0 ... $1 <- @for i @first 0 @last 1
+                    $(i) <- @call(f, $(2 * i));
+                @end
+                @assert_zero($0);
+            @end",
+        )
+        .unwrap();
+        let mut functions = HashMap::new();
+        let body = parser.relation_body(&mut functions).unwrap();
+        let i = || Box::new(Expr::Var("i".to_string()));
+        assert_eq!(
+            body[0],
+            Directive::MulC(
+                Expr::Number(2),
+                Expr::Add(i(), Box::new(Expr::Number(1))),
+                3
+            )
+        );
+        assert_eq!(
+            body[1],
+            Directive::For {
+                outputs: vec![WireRange {
+                    first: Expr::Number(0),
+                    last: Some(Expr::Number(1)),
+                }],
+                var: "i".to_string(),
+                first: 0,
+                last: 1,
+                body: vec![Directive::Call {
+                    outputs: vec![WireRange {
+                        first: Expr::Var("i".to_string()),
+                        last: None,
+                    }],
+                    name: "f".to_string(),
+                    inputs: vec![WireRange {
+                        first: Expr::Mul(Box::new(Expr::Number(2)), i()),
+                        last: None,
+                    }],
+                }],
+            }
+        );
+        assert_eq!(body[2], Directive::AssertZero(Expr::Number(0)));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut functions = HashMap::new();
+        for input in [
+            "@begin $0 <- @xor($1, $2); @end",
+            "@begin $0 ... $1 <- @add($1, $2); @end",
+            "@begin $0 <- @add($1, $2) @end",
+            "@begin $0 <- @add($1, $2); @end trailing",
+        ]
+        .iter()
+        {
+            let mut parser = Parser::new(input).unwrap();
+            assert!(parser.relation_body(&mut functions).is_err());
+        }
+        // deeply nested input fails instead of overflowing the stack
+        for input in [
+            format!(
+                "@begin $0 <- ${}1{}; @end",
+                "(".repeat(100_000),
+                ")".repeat(100_000)
+            ),
+            format!(
+                "@begin {}",
+                "$0 <- @for i @first 0 @last 0 ".repeat(100_000)
+            ),
+        ]
+        .iter()
+        {
+            let mut parser = Parser::new(input).unwrap();
+            assert!(parser.relation_body(&mut functions).is_err());
+        }
+    }
+}
@@ -1,14 +1,16 @@
 use crate::ot::mozzarella::cache::verifier::CachedVerifier;
-use crate::ot::mozzarella::lpn::LLCode;
-use crate::ot::mozzarella::utils::log2;
+use crate::ot::mozzarella::handshake::SessionParameters;
+use crate::ot::mozzarella::lpn::LpnCode;
 use crate::ot::mozzarella::{MozzarellaVerifier, MozzarellaVerifierStats};
+use crate::quarksilver::circuit::{Circuit, CircuitStats, Gate, CHECK_CHUNK_SIZE};
+use crate::quarksilver::{check_parameters, chis, hash_macs, two_to_the};
 use crate::Error;
 use rand::distributions::{Distribution, Standard};
-use rand::{rngs::OsRng, CryptoRng, Rng, SeedableRng};
+use rand::{rngs::OsRng, CryptoRng, Rng};
 use rayon::prelude::*;
 use scuttlebutt::channel::{Receivable, Sendable};
 use scuttlebutt::ring::Ring;
-use scuttlebutt::{AbstractChannel, AesRng, Block};
+use scuttlebutt::{AbstractChannel, Block};
 use serde::Serialize;
 use std::time::{Duration, Instant};
 
@@ -45,18 +47,18 @@ where
         k: usize,
         statsec: usize,
         cache: CachedVerifier<RingT>,
-        code: &'a LLCode<RingT>,
+        code: &'a dyn LpnCode<RingT>,
         base_vole_len: usize,
         num_sp_voles: usize,
         sp_vole_len: usize,
     ) -> Self {
-        assert!(RingT::BIT_LENGTH >= k + 2 * statsec + log2(statsec));
+        check_parameters::<RingT>(k, statsec);
         Self {
             k,
             statsec,
             mozVerifier: MozzarellaVerifier::<RingT>::new(
                 cache,
-                &code,
+                code,
                 base_vole_len,
                 num_sp_voles,
                 sp_vole_len,
@@ -70,6 +72,10 @@ where
 
     pub fn init<C: AbstractChannel>(&mut self, channel: &mut C, delta: RingT) -> Result<(), Error> {
         self.delta = delta;
+        SessionParameters::new("QuarkSilver")
+            .add("k", self.k)
+            .add("statsec", self.statsec)
+            .exchange(channel)?;
         let t_start = Instant::now();
         self.mozVerifier.init(channel, delta)?;
         self.stats.mozz_init = t_start.elapsed();
@@ -77,6 +83,20 @@ where
         Ok(())
     }
 
+    /// Switch to a fresh `delta` (see `MozzarellaVerifier::rekey`).  Values that were
+    /// authenticated before cannot be used together with values authenticated afterwards.
+    pub fn rekey<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        delta: RingT,
+        num_base_voles: usize,
+    ) -> Result<(), Error> {
+        assert!(self.is_init_done);
+        self.mozVerifier.rekey(channel, delta, num_base_voles)?;
+        self.delta = delta.reduce();
+        Ok(())
+    }
+
     pub fn apply_to_mozzarella_verifier<ResT, F: FnOnce(&mut MozzarellaVerifier<RingT>) -> ResT>(
         &mut self,
         f: F,
@@ -163,6 +183,77 @@ where
         self.input_batch(channel, n)
     }
 
+    // receive the masked values and check the hash of their MACs
+    fn open_masked<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        keys: &[RingT],
+    ) -> Result<Vec<RingT>, Error> {
+        let n = keys.len();
+        let mut keys = keys.to_vec();
+        if !RingT::IS_FIELD {
+            let r_keys = self.random_batch(channel, n)?;
+            let two_to_k = two_to_the::<RingT>(self.k);
+            for i in 0..n {
+                keys[i] += r_keys[i] * two_to_k;
+            }
+        }
+        let values: Vec<RingT> = channel.receive_n(n)?;
+        let hash: [u8; 32] = channel.receive()?;
+        let expected = hash_macs(
+            values
+                .iter()
+                .zip(keys.iter())
+                .map(|(&x, &key)| x * self.delta + key),
+        );
+        if hash != expected {
+            return Err(Error::Other("MAC check of opened values fails".to_string()));
+        }
+        Ok(values)
+    }
+
+    /// Counterpart of `Prover::open_batch`, returns the opened values (reduced modulo 2^k over
+    /// Z_2^l).
+    pub fn open_batch<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        keys: &[RingT],
+    ) -> Result<Vec<RingT>, Error> {
+        let values = self.open_masked(channel, keys)?;
+        Ok(values.iter().map(|x| x.reduce_to_bits(self.k)).collect())
+    }
+
+    /// Counterpart of `Prover::check_zero_batch`.
+    pub fn check_zero_batch<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        keys: &[RingT],
+    ) -> Result<(), Error> {
+        let values = self.open_batch(channel, keys)?;
+        if values.iter().all(|x| x.is_zero()) {
+            Ok(())
+        } else {
+            Err(Error::Other("checkZero fails".to_string()))
+        }
+    }
+
+    /// Counterpart of `Prover::assert_eq_public`.
+    pub fn assert_eq_public<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        keys: &[RingT],
+        constants: &[RingT],
+    ) -> Result<(), Error> {
+        assert_eq!(constants.len(), keys.len());
+        // the MAC of x - c is the MAC of x with the key shifted by delta * c
+        let keys: Vec<RingT> = keys
+            .iter()
+            .zip(constants.iter())
+            .map(|(&key, &c)| key + c * self.delta)
+            .collect();
+        self.check_zero_batch(channel, &keys)
+    }
+
     pub fn check_multiply_batch<C: AbstractChannel>(
         &mut self,
         channel: &mut C,
@@ -180,8 +271,7 @@ where
 
         let chi_seed = OsRng.gen::<Block>();
         channel.send(&chi_seed)?;
-        let mut seeded_rng = AesRng::from_seed(chi_seed);
-        let chis: Vec<RingT> = (0..n).map(|_| seeded_rng.gen()).collect();
+        let chis: Vec<RingT> = chis(chi_seed, 0).take(n).collect();
 
         let t_start = Instant::now();
         for i in 0..n {
@@ -212,6 +302,23 @@ where
         }
     }
 
+    // sum_i chi_i * b_i for the given triples of keys
+    fn combine_triples(
+        triples: &[(RingT, RingT, RingT)],
+        chis: impl Iterator<Item = RingT>,
+        delta: RingT,
+    ) -> RingT {
+        triples
+            .iter()
+            .zip(chis)
+            .map(|((k_alpha, k_beta, k_gamma), chi)| {
+                chi * ((*k_alpha * *k_beta) + (*k_gamma * delta))
+            })
+            .sum()
+    }
+
+    /// Counterpart of `Prover::check_multiply`.  The result does not depend on `multi_thread` and
+    /// `chunk_size`, so they do not need to match the prover's.
     pub fn check_multiply<C: AbstractChannel, R: CryptoRng + Rng>(
         &mut self,
         channel: &mut C,
@@ -220,51 +327,27 @@ where
         multi_thread: bool,
         chunk_size: usize,
     ) -> Result<(), Error> {
-        let mut W = RingT::default();
-
         let seed = rng.gen::<Block>();
         channel.send(&seed)?;
-        let t_start = Instant::now();
-        let mut seeded_rng = AesRng::from_seed(seed);
-        println!("Time to create rng: {}", t_start.elapsed().as_millis());
-        let check_start = Instant::now();
-
-        if multi_thread {
-            let t_start = Instant::now();
-
-            println!("Sampling chis: {}", t_start.elapsed().as_millis());
+        channel.flush()?;
 
-            let t_start = Instant::now();
-            //let mut tmp: Vec<(&RingT, &(RingT, RingT, RingT))> = chis.iter().zip(triples.iter()).collect();
-            println!("Zipping: {}", t_start.elapsed().as_millis());
-            let t_start = Instant::now();
-            W = triples
-                .par_chunks_exact_mut(chunk_size)
+        let t_start = Instant::now();
+        let delta = self.delta;
+        let mut W: RingT = if multi_thread {
+            triples
+                .par_chunks(chunk_size)
                 .enumerate()
-                .map(|(_idx, x)| {
-                    // TODO: An initial seed should be sent from the verifier prior to this
-                    let mut rng = AesRng::from_seed(Block::default());
-                    x.into_iter()
-                        .map(|y| rng.gen::<RingT>() * ((y.0 * y.1) + (y.2 * self.delta)))
-                        .sum()
+                .map(|(idx, chunk)| {
+                    Self::combine_triples(chunk, chis(seed, idx * chunk_size), delta)
                 })
-                .sum();
-            println!("Computing sum: {}", t_start.elapsed().as_millis());
+                .sum()
         } else {
-            let t_start = Instant::now();
-            for (x, y, z) in triples.iter() {
-                let chi = seeded_rng.gen::<RingT>();
-
-                let bi = (*x) * (*y) + (*z * self.delta);
-
-                W += bi * chi;
-            }
-            println!("Computing sum: {}", t_start.elapsed().as_millis());
-        }
+            Self::combine_triples(triples, chis(seed, 0), delta)
+        };
         let B = self.random(channel)?;
         W += B;
 
-        self.stats.linear_comb_time = check_start.elapsed();
+        self.stats.linear_comb_time = t_start.elapsed();
 
         let U: RingT = channel.receive()?;
         let V: RingT = channel.receive()?;
@@ -272,11 +355,188 @@ where
         let tmp = U - (V * self.delta);
 
         if W == tmp {
-            println!("Check passed");
             Ok(())
         } else {
-            println!("Someone lied");
             Err(Error::Other("checkMultiply fails".to_string()))
         }
     }
+
+    // send a fresh challenge seed
+    fn send_chi_seed<C: AbstractChannel>(channel: &mut C) -> Result<Block, Error> {
+        let seed = OsRng.gen::<Block>();
+        channel.send(&seed)?;
+        channel.flush()?;
+        Ok(seed)
+    }
+
+    // unmask the prover's check values and compare them with the combined keys
+    fn receive_masked_check<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        W: RingT,
+        error: &str,
+    ) -> Result<(), Error> {
+        let B = self.random(channel)?;
+        let U: RingT = channel.receive()?;
+        let V: RingT = channel.receive()?;
+        if W + B == U - V * self.delta {
+            Ok(())
+        } else {
+            Err(Error::Other(error.to_string()))
+        }
+    }
+
+    /// Counterpart of `Prover::inner_product_batch`.
+    pub fn inner_product_batch<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        n: usize,
+    ) -> Result<Vec<RingT>, Error> {
+        self.input_batch(channel, n)
+    }
+
+    /// Counterpart of `Prover::check_inner_product_batch`.
+    pub fn check_inner_product_batch<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        a_keys: &[&[RingT]],
+        b_keys: &[&[RingT]],
+        c_keys: &[RingT],
+    ) -> Result<(), Error> {
+        let n = a_keys.len();
+        assert_eq!(n, b_keys.len());
+        assert_eq!(n, c_keys.len());
+        let seed = Self::send_chi_seed(channel)?;
+        let chis: Vec<RingT> = chis(seed, 0).take(n).collect();
+
+        let t_start = Instant::now();
+        let delta = self.delta;
+        let W: RingT = a_keys
+            .par_iter()
+            .zip(b_keys.par_iter())
+            .zip(c_keys.par_iter())
+            .zip(chis.par_iter())
+            .map(|(((k_as, k_bs), &k_c), &chi)| {
+                assert_eq!(k_as.len(), k_bs.len());
+                let b: RingT = k_as.iter().zip(k_bs.iter()).map(|(&x, &y)| x * y).sum();
+                chi * (b + k_c * delta)
+            })
+            .sum();
+        self.stats.linear_comb_time = t_start.elapsed();
+
+        self.receive_masked_check(channel, W, "checkInnerProduct fails")
+    }
+
+    /// Counterpart of `Prover::matrix_multiply`, returns the keys of the `rows x cols` product.
+    pub fn matrix_multiply<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        (rows, _, cols): (usize, usize, usize),
+    ) -> Result<Vec<RingT>, Error> {
+        self.input_batch(channel, rows * cols)
+    }
+
+    /// Counterpart of `Prover::check_matrix_multiply`.
+    pub fn check_matrix_multiply<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        (rows, inner, cols): (usize, usize, usize),
+        a_keys: &[RingT],
+        b_keys: &[RingT],
+        c_keys: &[RingT],
+    ) -> Result<(), Error> {
+        assert_eq!(a_keys.len(), rows * inner);
+        assert_eq!(b_keys.len(), inner * cols);
+        assert_eq!(c_keys.len(), rows * cols);
+        let seed = Self::send_chi_seed(channel)?;
+
+        let t_start = Instant::now();
+        let delta = self.delta;
+        let W: RingT = (0..rows)
+            .into_par_iter()
+            .map(|r| {
+                let row_chis: Vec<RingT> = chis(seed, r * cols).take(cols).collect();
+                let mut w = RingT::ZERO;
+                for i in 0..inner {
+                    let mut s = RingT::ZERO;
+                    for j in 0..cols {
+                        s += row_chis[j] * b_keys[i * cols + j];
+                    }
+                    w += a_keys[r * inner + i] * s;
+                }
+                for j in 0..cols {
+                    w += row_chis[j] * c_keys[r * cols + j] * delta;
+                }
+                w
+            })
+            .sum();
+        self.stats.linear_comb_time = t_start.elapsed();
+
+        self.receive_masked_check(channel, W, "checkMatrixMultiply fails")
+    }
+
+    /// Counterpart of `Prover::prove_circuit`, returns an error if the circuit is not satisfied.
+    pub fn verify_circuit<C: AbstractChannel>(
+        &mut self,
+        channel: &mut C,
+        circuit: &Circuit<RingT>,
+    ) -> Result<CircuitStats, Error> {
+        let counts = circuit.gate_counts();
+        let mut stats = CircuitStats {
+            gates: counts,
+            ..Default::default()
+        };
+
+        let t_start = Instant::now();
+        let n = counts.inputs + counts.muls;
+        let mask_keys = self.random_batch(channel, n)?;
+        stats.vole_time = t_start.elapsed();
+
+        let t_start = Instant::now();
+        let diffs: Vec<RingT> = channel.receive_n(n)?;
+        let delta = self.delta;
+        let mut wires: Vec<RingT> = Vec::with_capacity(circuit.num_wires());
+        let mut num_masks_used = 0;
+        let mut triples = Vec::with_capacity(counts.muls);
+        let mut zero_keys: Vec<RingT> = Vec::with_capacity(counts.assert_zeros);
+        for gate in circuit.gates() {
+            let out = match *gate {
+                Gate::Input => {
+                    let i = num_masks_used;
+                    num_masks_used += 1;
+                    mask_keys[i] - diffs[i] * delta
+                }
+                // the prover uses the MAC zero
+                Gate::Constant(c) => -(c * delta),
+                Gate::Add(a, b) => wires[a.0] + wires[b.0],
+                Gate::Sub(a, b) => wires[a.0] - wires[b.0],
+                Gate::CMul(a, c) => c * wires[a.0],
+                Gate::Mul(a, b) => {
+                    let i = num_masks_used;
+                    num_masks_used += 1;
+                    let gamma = mask_keys[i] - diffs[i] * delta;
+                    triples.push((wires[a.0], wires[b.0], gamma));
+                    gamma
+                }
+                Gate::AssertZero(a) => {
+                    zero_keys.push(wires[a.0]);
+                    continue;
+                }
+            };
+            wires.push(out);
+        }
+        stats.evaluation_time = t_start.elapsed();
+
+        if !triples.is_empty() {
+            let t_start = Instant::now();
+            self.check_multiply(channel, OsRng, &mut triples, true, CHECK_CHUNK_SIZE)?;
+            stats.check_multiply_time = t_start.elapsed();
+        }
+        if !zero_keys.is_empty() {
+            let t_start = Instant::now();
+            self.check_zero_batch(channel, &zero_keys)?;
+            stats.check_zero_time = t_start.elapsed();
+        }
+        Ok(stats)
+    }
 }
@@ -1,6 +1,8 @@
+mod f61p;
 mod r64;
 pub mod z2r;
 
+pub use f61p::F61p;
 pub use r64::R64;
 
 use crate::Block;
@@ -44,6 +46,9 @@ where
     const ZERO: Self;
     const ONE: Self;
 
+    /// Whether every non-zero element is invertible (see `Field`).
+    const IS_FIELD: bool = false;
+
     #[inline(always)]
     fn is_zero(&self) -> bool {
         *self == Self::ZERO
@@ -68,6 +73,9 @@ where
 
     fn is_reduced_to<const BITS: usize>(&self) -> bool;
 
+    /// Like `reduce_to`, but with the number of bits given at run time.
+    fn reduce_to_bits(&self, bits: usize) -> Self;
+
     fn reduce_to_32(&self) -> u32;
 
     fn reduce_to_64(&self) -> u64;
@@ -80,3 +88,12 @@ where
 
     // fn as_ptr(&self) -> *const u8;
 }
+
+/// Marker for rings in which every non-zero element is invertible.
+pub trait Field: Ring
+where
+    Standard: Distribution<Self>,
+{
+    /// Multiplicative inverse, panics for zero.
+    fn inverse(&self) -> Self;
+}
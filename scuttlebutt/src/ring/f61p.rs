@@ -0,0 +1,397 @@
+use crate::{
+    channel::{AbstractChannel, Receivable, Sendable},
+    ring::{Field, Ring},
+    Block,
+};
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng,
+};
+use std::{
+    convert::From,
+    fmt, io,
+    iter::Sum,
+    mem,
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+    slice,
+};
+
+/// The prime field F_p with the Mersenne prime p = 2^61 - 1.
+///
+/// Elements are always kept in canonical form, i.e., in the range [0, p).
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct F61p(u64);
+
+impl F61p {
+    pub const MODULUS: u64 = (1u64 << 61) - 1;
+}
+
+// reduce an arbitrary u128 modulo p = 2^61 - 1
+#[inline(always)]
+fn reduce_u128(x: u128) -> u64 {
+    const P: u128 = F61p::MODULUS as u128;
+    // 2^61 = 1 mod p, so we can fold the high bits onto the low bits
+    let x = (x & P) + (x >> 61);
+    let x = ((x & P) + (x >> 61)) as u64;
+    if x >= F61p::MODULUS {
+        x - F61p::MODULUS
+    } else {
+        x
+    }
+}
+
+impl From<u64> for F61p {
+    #[inline(always)]
+    fn from(x: u64) -> Self {
+        Self(reduce_u128(x as u128))
+    }
+}
+
+impl From<u128> for F61p {
+    #[inline(always)]
+    fn from(x: u128) -> Self {
+        Self(reduce_u128(x))
+    }
+}
+
+impl From<F61p> for u64 {
+    #[inline(always)]
+    fn from(x: F61p) -> u64 {
+        x.0
+    }
+}
+
+impl From<Block> for F61p {
+    // statistically close to uniform (distance ~ 2^-67) for uniform blocks
+    #[inline(always)]
+    fn from(x: Block) -> Self {
+        Self(reduce_u128(x.extract_u128()))
+    }
+}
+
+impl Add<Self> for F61p {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self::Output {
+        let s = self.0 + rhs.0;
+        Self(if s >= Self::MODULUS {
+            s - Self::MODULUS
+        } else {
+            s
+        })
+    }
+}
+
+impl AddAssign<Self> for F61p {
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Add<u64> for F61p {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, rhs: u64) -> Self::Output {
+        Self(reduce_u128(self.0 as u128 + rhs as u128))
+    }
+}
+
+impl Sub<Self> for F61p {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(if self.0 >= rhs.0 {
+            self.0 - rhs.0
+        } else {
+            self.0 + Self::MODULUS - rhs.0
+        })
+    }
+}
+
+impl SubAssign<Self> for F61p {
+    #[inline(always)]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Mul<Self> for F61p {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self(reduce_u128(self.0 as u128 * rhs.0 as u128))
+    }
+}
+
+impl MulAssign<Self> for F61p {
+    #[inline(always)]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Mul<u64> for F61p {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, rhs: u64) -> Self::Output {
+        Self(reduce_u128(self.0 as u128 * rhs as u128))
+    }
+}
+
+impl Neg for F61p {
+    type Output = Self;
+    #[inline(always)]
+    fn neg(self) -> Self {
+        Self(if self.0 == 0 {
+            0
+        } else {
+            Self::MODULUS - self.0
+        })
+    }
+}
+
+impl Sum for F61p {
+    #[inline(always)]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        // the accumulator overflows only after 2^67 summands
+        let mut s = 0u128;
+        for x in iter {
+            s += x.0 as u128;
+        }
+        Self(reduce_u128(s))
+    }
+}
+
+impl<'a> Sum<&'a F61p> for F61p {
+    #[inline(always)]
+    fn sum<I: Iterator<Item = &'a F61p>>(iter: I) -> Self {
+        iter.copied().sum()
+    }
+}
+
+impl fmt::Display for F61p {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for F61p {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "F61p({})", self.0)
+    }
+}
+
+impl Default for F61p {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl Ring for F61p {
+    const ZERO: Self = Self(0);
+    const ONE: Self = Self(1);
+    const BIT_LENGTH: usize = 61;
+    const BYTE_LENGTH: usize = 8;
+    const IS_FIELD: bool = true;
+
+    #[inline(always)]
+    fn is_reduced(&self) -> bool {
+        self.0 < Self::MODULUS
+    }
+
+    // keeps the lowest bits of the canonical representative
+    #[inline(always)]
+    fn reduce_to<const BITS: usize>(&self) -> Self {
+        let mask: u64 = (1u64 << BITS) - 1;
+        Self(self.0 & mask)
+    }
+
+    #[inline(always)]
+    fn is_reduced_to<const BITS: usize>(&self) -> bool {
+        let mask: u64 = !((1u64 << BITS) - 1);
+        self.0 & mask == 0
+    }
+
+    #[inline(always)]
+    fn reduce_to_bits(&self, bits: usize) -> Self {
+        if bits >= 61 {
+            *self
+        } else {
+            Self(self.0 & ((1u64 << bits) - 1))
+        }
+    }
+
+    #[inline(always)]
+    fn reduce_to_32(&self) -> u32 {
+        (self.0 & 0xffffffff) as u32
+    }
+
+    #[inline(always)]
+    fn reduce_to_64(&self) -> u64 {
+        self.0
+    }
+
+    #[inline(always)]
+    fn sum(slice: &[Self]) -> Self {
+        slice.iter().copied().sum()
+    }
+}
+
+impl Field for F61p {
+    fn inverse(&self) -> Self {
+        assert!(!self.is_zero(), "zero has no inverse");
+        // x^(p - 2) = x^-1 by Fermat's little theorem
+        let mut exp = Self::MODULUS - 2;
+        let mut base = *self;
+        let mut acc = Self::ONE;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                acc *= base;
+            }
+            base *= base;
+            exp >>= 1;
+        }
+        acc
+    }
+}
+
+impl AsRef<[u8]> for F61p {
+    #[inline(always)]
+    fn as_ref(&self) -> &[u8] {
+        unsafe {
+            slice::from_raw_parts(&*(self as *const F61p as *const u8), mem::size_of::<Self>())
+        }
+    }
+}
+
+impl AsMut<[u8]> for F61p {
+    #[inline(always)]
+    fn as_mut(&mut self) -> &mut [u8] {
+        unsafe {
+            slice::from_raw_parts_mut(&mut *(self as *mut F61p as *mut u8), mem::size_of::<Self>())
+        }
+    }
+}
+
+impl Distribution<F61p> for Standard {
+    // rejection sampling of 61-bit values
+    #[inline(always)]
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> F61p {
+        loop {
+            let x = rng.gen::<u64>() & F61p::MODULUS;
+            if x != F61p::MODULUS {
+                return F61p(x);
+            }
+        }
+    }
+}
+
+impl Receivable for F61p {
+    #[inline(always)]
+    fn receive<C: AbstractChannel>(chan: &mut C) -> io::Result<Self> {
+        let mut bytes = [0u8; 8];
+        chan.read_bytes(&mut bytes)?;
+        Ok(Self::from(u64::from_le_bytes(bytes)))
+    }
+}
+
+impl Sendable for &F61p {
+    #[inline(always)]
+    fn send<C: AbstractChannel>(self, chan: &mut C) -> io::Result<()> {
+        chan.write_bytes(&self.0.to_le_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::F61p;
+    use crate::{
+        channel::AbstractChannel,
+        ring::{Field, Ring},
+        unix_channel_pair, Block,
+    };
+    use rand::{rngs::OsRng, Rng};
+
+    const P: u128 = F61p::MODULUS as u128;
+
+    #[test]
+    fn test_f61p_constants() {
+        assert_eq!(F61p::default(), F61p::from(0u64));
+        assert_eq!(F61p::ZERO, F61p::from(F61p::MODULUS));
+        assert_eq!(F61p::ONE, F61p::from(F61p::MODULUS + 1));
+        assert_eq!(
+            F61p::from(u64::MAX),
+            F61p::from((u64::MAX as u128 % P) as u64)
+        );
+    }
+
+    #[test]
+    fn test_f61p_arithmetic() {
+        for _ in 0..1000 {
+            let a: u64 = OsRng.gen_range(0, F61p::MODULUS);
+            let b: u64 = OsRng.gen_range(0, F61p::MODULUS);
+            let c: u64 = OsRng.gen();
+            let (x, y) = (F61p::from(a), F61p::from(b));
+            assert_eq!(u64::from(x + y), ((a as u128 + b as u128) % P) as u64);
+            assert_eq!(u64::from(x - y), ((a as u128 + P - b as u128) % P) as u64);
+            assert_eq!(u64::from(x * y), ((a as u128 * b as u128) % P) as u64);
+            assert_eq!(u64::from(x + c), ((a as u128 + c as u128) % P) as u64);
+            assert_eq!(u64::from(x * c), ((a as u128 * c as u128) % P) as u64);
+            assert_eq!(x + (-x), F61p::ZERO);
+            assert!((x * y).is_reduced());
+        }
+        let m = F61p::from(F61p::MODULUS - 1);
+        assert_eq!(m * m, F61p::ONE);
+        assert_eq!(m + F61p::ONE, F61p::ZERO);
+        assert_eq!(F61p::ZERO - F61p::ONE, m);
+    }
+
+    #[test]
+    fn test_f61p_sum() {
+        let xs: Vec<F61p> = (0..1000).map(|_| OsRng.gen()).collect();
+        let expected = xs.iter().fold(F61p::ZERO, |acc, &x| acc + x);
+        assert_eq!(xs.iter().sum::<F61p>(), expected);
+        assert_eq!(<F61p as Ring>::sum(&xs), expected);
+    }
+
+    #[test]
+    fn test_f61p_inverse() {
+        for _ in 0..100 {
+            let x: F61p = OsRng.gen();
+            if x.is_zero() {
+                continue;
+            }
+            assert_eq!(x * x.inverse(), F61p::ONE);
+        }
+    }
+
+    #[test]
+    fn test_f61p_from_block() {
+        let x = F61p::from(Block::from(u128::MAX));
+        assert_eq!(u64::from(x), (u128::MAX % P) as u64);
+        assert!(x.is_reduced());
+    }
+
+    #[test]
+    fn test_f61p_send_receive() {
+        let (mut channel_p, mut channel_v) = unix_channel_pair();
+        let a: F61p = OsRng.gen();
+        channel_p.send(&a).unwrap();
+        let b: F61p = channel_v.receive().unwrap();
+        assert_eq!(a, b);
+
+        let xs: Vec<F61p> = (0..32).map(|_| OsRng.gen()).collect();
+        let mut ys = [F61p::default(); 32];
+        channel_v.send(xs.as_slice()).unwrap();
+        channel_p.receive_into(&mut ys).unwrap();
+        assert_eq!(xs, ys);
+
+        // non-canonical representatives are reduced
+        channel_p.send(u64::MAX).unwrap();
+        let c: F61p = channel_v.receive().unwrap();
+        assert_eq!(c, F61p::from(u64::MAX));
+    }
+}
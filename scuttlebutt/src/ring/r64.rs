@@ -39,6 +39,15 @@ impl Ring for R64 {
         self.0 & mask == 0
     }
 
+    #[inline(always)]
+    fn reduce_to_bits(&self, bits: usize) -> Self {
+        if bits >= 64 {
+            *self
+        } else {
+            Self(self.0 & ((1u64 << bits) - 1))
+        }
+    }
+
     #[inline(always)]
     fn reduce_to_32(&self) -> u32 {
         (self.0 & 0xffffffff) as u32
@@ -165,6 +165,15 @@ impl<const BIT_LENGTH: usize> Ring for Z2rU128<BIT_LENGTH> {
         self.0 & mask == 0
     }
 
+    #[inline(always)]
+    fn reduce_to_bits(&self, bits: usize) -> Self {
+        if bits >= 128 {
+            *self
+        } else {
+            Self(self.0 & ((1u128 << bits) - 1))
+        }
+    }
+
     #[inline(always)]
     fn sum(slice: &[Self]) -> Self {
         let mut s = Self::ZERO;
@@ -426,6 +435,11 @@ impl<const BIT_LENGTH: usize> Ring for Z2rU192<BIT_LENGTH> {
         (self.0 & mask).is_zero()
     }
 
+    #[inline(always)]
+    fn reduce_to_bits(&self, bits: usize) -> Self {
+        Self(self.0 & U192(compute_bit_mask_192(bits)))
+    }
+
     #[inline(always)]
     fn sum(slice: &[Self]) -> Self {
         let mut s = Self::ZERO;
@@ -694,6 +708,11 @@ impl<const BIT_LENGTH: usize> Ring for Z2rU256<BIT_LENGTH> {
         (self.0 & mask).is_zero()
     }
 
+    #[inline(always)]
+    fn reduce_to_bits(&self, bits: usize) -> Self {
+        Self(self.0 & U256(compute_bit_mask_256(bits)))
+    }
+
     #[inline(always)]
     fn sum(slice: &[Self]) -> Self {
         let mut s = Self::ZERO;
@@ -953,6 +972,8 @@ mod tests {
         let z_a = R104::from(a);
         let z_b = R104::from(b);
         assert_eq!(z_a.reduce_to::<40>(), z_b);
+        assert_eq!(z_a.reduce_to_bits(40), z_b);
+        assert_eq!(z_a.reduce_to_bits(128), z_a);
         assert!(z_a.reduce_to::<40>().is_reduced_to::<40>());
         assert!(z_b.is_reduced_to::<40>());
     }
@@ -1175,6 +1196,7 @@ mod tests {
         let z_a = R144_192::from(a);
         let z_b = R144_192::from(b);
         assert_eq!(z_a.reduce_to::<80>(), z_b);
+        assert_eq!(z_a.reduce_to_bits(80), z_b);
         assert!(z_a.reduce_to::<80>().is_reduced_to::<80>());
         assert!(z_b.is_reduced_to::<80>());
     }
@@ -1551,6 +1573,7 @@ mod tests {
         let z_a = R144_256::from(a);
         let z_b = R144_256::from(b);
         assert_eq!(z_a.reduce_to::<80>(), z_b);
+        assert_eq!(z_a.reduce_to_bits(80), z_b);
         assert!(z_a.reduce_to::<80>().is_reduced_to::<80>());
         assert!(z_b.is_reduced_to::<80>());
     }